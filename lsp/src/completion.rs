@@ -0,0 +1,96 @@
+//! Completion of units and metadata keys
+
+use std::collections::HashSet;
+
+use cooklang::{CooklangParser, Extensions};
+use lsp_types::{CompletionItem, CompletionItemKind, Position};
+
+use crate::document::Document;
+
+/// Keys with a special meaning in [`Metadata`](cooklang::Metadata)
+const METADATA_KEYS: &[(&str, &str)] = &[
+    ("description", "Description of the recipe"),
+    ("tags", "Comma separated list of tags"),
+    ("emoji", "Emoji of the recipe"),
+    ("author", "Name and/or URL"),
+    ("source", "Name and/or URL"),
+    ("time", "Total time"),
+    ("prep time", "Preparation time"),
+    ("cook time", "Cooking time"),
+    ("servings", "Servings, separated by `|`"),
+    ("yield", "What the recipe makes, like `1 loaf`"),
+];
+
+/// Special keys with [`MODES`](Extensions::MODES)
+const SPECIAL_KEYS: &[(&str, &str)] = &[
+    ("[mode]", "all | components | steps | text"),
+    ("[duplicate]", "new | reference"),
+    ("[auto scale]", "true | false"),
+];
+
+pub fn completion(
+    parser: &CooklangParser,
+    doc: &Document,
+    position: Position,
+) -> Option<Vec<CompletionItem>> {
+    let text = doc.text();
+    let offset = doc.offset(position);
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let before = &text[line_start..offset];
+
+    if let Some(key) = before.trim_start().strip_prefix(">>") {
+        if key.contains(':') {
+            return None;
+        }
+        return Some(metadata_keys(parser, doc));
+    }
+
+    // inside a component quantity, after the unit separator
+    let open = before.rfind('{')?;
+    let quantity = &before[open..];
+    if quantity.contains('}') || !quantity.contains('%') {
+        return None;
+    }
+    Some(units(parser))
+}
+
+fn metadata_keys(parser: &CooklangParser, doc: &Document) -> Vec<CompletionItem> {
+    let special = if parser.extensions().contains(Extensions::MODES) {
+        SPECIAL_KEYS
+    } else {
+        &[]
+    };
+    let used = doc.recipe().map(|r| &r.metadata.map);
+    METADATA_KEYS
+        .iter()
+        .chain(special)
+        .filter(|(key, _)| used.is_none_or(|used| !used.contains_key(*key)))
+        .map(|&(key, detail)| CompletionItem {
+            label: key.to_string(),
+            kind: Some(CompletionItemKind::PROPERTY),
+            detail: Some(detail.to_string()),
+            insert_text: Some(format!("{key}: ")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn units(parser: &CooklangParser) -> Vec<CompletionItem> {
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for unit in parser.converter().all_units() {
+        let name = unit.names.first().map_or("", |n| n);
+        let detail = format!("{name} ({})", unit.physical_quantity);
+        for label in unit.symbols.iter().chain(&unit.names) {
+            if seen.insert(label.clone()) {
+                items.push(CompletionItem {
+                    label: label.to_string(),
+                    kind: Some(CompletionItemKind::UNIT),
+                    detail: Some(detail.clone()),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    items
+}
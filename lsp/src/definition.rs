@@ -0,0 +1,20 @@
+//! Go to the definition of ingredient references
+
+use cooklang::IngredientReferenceTarget;
+use lsp_types::{Location, Position, Url};
+
+use crate::document::Document;
+
+pub fn definition(uri: &Url, doc: &Document, position: Position) -> Option<Location> {
+    let recipe = doc.recipe()?;
+    let (index, _) = doc.ingredient_at(doc.offset(position))?;
+
+    match recipe.ingredients[index].relation.references_to()? {
+        (definition, IngredientReferenceTarget::IngredientTarget) => {
+            let span = recipe.ingredients[definition].span?;
+            Some(Location::new(uri.clone(), doc.range(span)))
+        }
+        // steps and sections are not in a single place
+        _ => None,
+    }
+}
@@ -0,0 +1,70 @@
+//! Errors and warnings of a recipe as LSP diagnostics
+
+use cooklang::error::RichError;
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Url,
+};
+
+use crate::document::Document;
+
+pub fn diagnostics(uri: &Url, doc: &Document) -> Vec<Diagnostic> {
+    let result = doc.parse.result();
+    let errors = result
+        .errors()
+        .iter()
+        .map(|e| diagnostic(uri, doc, e, DiagnosticSeverity::ERROR));
+    let warnings = result
+        .warnings()
+        .iter()
+        .map(|w| diagnostic(uri, doc, w, DiagnosticSeverity::WARNING));
+    errors.chain(warnings).collect()
+}
+
+/// The first label is the range of the diagnostic, the rest are related
+/// information.
+fn diagnostic(
+    uri: &Url,
+    doc: &Document,
+    err: &impl RichError,
+    severity: DiagnosticSeverity,
+) -> Diagnostic {
+    let labels = err.labels();
+
+    let mut message = err.to_string();
+    if let Some((_, Some(label))) = labels.first() {
+        message = format!("{message}: {label}");
+    }
+    let mut source = err.source();
+    while let Some(s) = source {
+        message = format!("{message}: {s}");
+        source = s.source();
+    }
+    if let Some(help) = err.help() {
+        message = format!("{message}\nhelp: {help}");
+    }
+    if let Some(note) = err.note() {
+        message = format!("{message}\nnote: {note}");
+    }
+
+    let related: Vec<_> = labels
+        .iter()
+        .skip(1)
+        .map(|(span, label)| DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), doc.range(*span)),
+            message: label.as_deref().unwrap_or("here").to_string(),
+        })
+        .collect();
+
+    Diagnostic {
+        range: labels
+            .first()
+            .map(|(span, _)| doc.range(*span))
+            .unwrap_or_default(),
+        severity: Some(severity),
+        code: err.code().map(|c| NumberOrString::String(c.to_string())),
+        source: Some("cooklang".to_string()),
+        message,
+        related_information: (!related.is_empty()).then_some(related),
+        ..Default::default()
+    }
+}
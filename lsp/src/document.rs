@@ -0,0 +1,74 @@
+//! Open documents
+
+use cooklang::{
+    incremental::{IncrementalParse, TextEdit},
+    CooklangParser, Recipe, Span,
+};
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+
+use crate::line_index::LineIndex;
+
+/// A recipe open in the editor
+pub struct Document {
+    pub version: i32,
+    pub parse: IncrementalParse,
+    pub lines: LineIndex,
+}
+
+impl Document {
+    pub fn new(parser: &CooklangParser, text: &str, name: &str, version: i32) -> Self {
+        Self {
+            version,
+            parse: parser.parse_incremental(text, name),
+            lines: LineIndex::new(text),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.parse.input()
+    }
+
+    pub fn recipe(&self) -> Option<&Recipe> {
+        self.parse.result().output()
+    }
+
+    /// Applies the changes of a `textDocument/didChange` notification
+    pub fn change(
+        &mut self,
+        parser: &CooklangParser,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        version: i32,
+    ) {
+        // every change is relative to the text after the previous one, so the
+        // line index has to be updated after each
+        for change in changes {
+            let range = match change.range {
+                Some(range) => self.lines.span(self.text(), range),
+                None => 0..self.text().len(),
+            };
+            parser.reparse(&mut self.parse, [TextEdit::new(range, change.text)]);
+            self.lines = LineIndex::new(self.text());
+        }
+        self.version = version;
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        self.lines.offset(self.text(), position)
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        self.lines.range(self.text(), span)
+    }
+
+    /// Index and location of the ingredient at `offset`
+    pub fn ingredient_at(&self, offset: usize) -> Option<(usize, Span)> {
+        self.recipe()?
+            .ingredients
+            .iter()
+            .enumerate()
+            .find_map(|(index, igr)| {
+                let span = igr.span?;
+                (span.start() <= offset && offset <= span.end()).then_some((index, span))
+            })
+    }
+}
@@ -0,0 +1,44 @@
+//! Hover on ingredients with their total quantity
+
+use cooklang::{CooklangParser, IngredientReferenceTarget, TotalQuantity};
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+
+use crate::document::Document;
+
+pub fn hover(parser: &CooklangParser, doc: &Document, position: Position) -> Option<Hover> {
+    let recipe = doc.recipe()?;
+    let (index, span) = doc.ingredient_at(doc.offset(position))?;
+
+    // the total is in the definition
+    let definition = match recipe.ingredients[index].relation.references_to() {
+        Some((i, IngredientReferenceTarget::IngredientTarget)) => i,
+        _ => index,
+    };
+    let ingredient = &recipe.ingredients[definition];
+
+    let total = ingredient
+        .group_quantities(&recipe.ingredients, parser.converter())
+        .total();
+    let total = match total {
+        TotalQuantity::None => "no quantity".to_string(),
+        TotalQuantity::Single(q) => q.to_string(),
+        TotalQuantity::Many(many) => many
+            .iter()
+            .map(|q| q.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    let mut value = format!("**{}**\n\nTotal: {total}", ingredient.display_name());
+    if let Some(note) = &ingredient.note {
+        value += &format!("\n\n*{note}*");
+    }
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(doc.range(span)),
+    })
+}
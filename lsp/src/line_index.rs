@@ -0,0 +1,88 @@
+//! Conversion between byte offsets and LSP positions
+//!
+//! The parser works with byte offsets, while LSP positions are a line and a
+//! column in UTF-16 code units.
+
+use cooklang::Span;
+use lsp_types::{Position, Range};
+
+/// Start of every line of a text
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    /// Position of a byte offset of `text`
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        let offset = offset.min(text.len());
+        let line = self.line_starts.partition_point(|&s| s <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = text[start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    /// Byte offset of a position in `text`
+    ///
+    /// Positions after the end of a line are clamped to the end of the line,
+    /// before the line break, as the LSP specification says.
+    pub fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let line = &text[start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        start + line.len()
+    }
+
+    pub fn range(&self, text: &str, span: Span) -> Range {
+        Range::new(
+            self.position(text, span.start()),
+            self.position(text, span.end()),
+        )
+    }
+
+    pub fn span(&self, text: &str, range: Range) -> std::ops::Range<usize> {
+        self.offset(text, range.start)..self.offset(text, range.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16() {
+        let text = "a ºC\n🍅 b\r\nc";
+        let index = LineIndex::new(text);
+        let pos = |offset| index.position(text, offset);
+        assert_eq!(pos(0), Position::new(0, 0));
+        assert_eq!(pos(4), Position::new(0, 3));
+        assert_eq!(pos(6), Position::new(1, 0));
+        assert_eq!(pos(10), Position::new(1, 2));
+        assert_eq!(pos(text.len()), Position::new(2, 1));
+
+        for offset in [0, 2, 4, 6, 10, 11, 14, text.len()] {
+            assert_eq!(index.offset(text, pos(offset)), offset);
+        }
+        // past the end of the line
+        assert_eq!(index.offset(text, Position::new(0, 100)), 5);
+        assert_eq!(index.offset(text, Position::new(1, 100)), 12);
+        assert_eq!(index.offset(text, Position::new(5, 0)), text.len());
+    }
+}
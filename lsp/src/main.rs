@@ -0,0 +1,27 @@
+//! Language server for cooklang
+//!
+//! Speaks the Language Server Protocol over stdio. It provides:
+//! - Diagnostics with the errors and warnings of the recipe.
+//! - Hover on ingredients with their total quantity.
+//! - Go to the definition of an ingredient from a reference (`@&salt`).
+//! - Completion of units and metadata keys.
+//!
+//! All the [extensions](cooklang::Extensions) are enabled.
+
+mod completion;
+mod definition;
+mod diagnostics;
+mod document;
+mod hover;
+mod line_index;
+mod server;
+
+use cooklang::CooklangParser;
+use lsp_server::Connection;
+
+fn main() -> Result<(), server::Error> {
+    let (connection, io_threads) = Connection::stdio();
+    server::run(connection, CooklangParser::default())?;
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! Main loop and message dispatch
+
+use std::collections::HashMap;
+
+use cooklang::CooklangParser;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as RequestTrait},
+    CompletionOptions, CompletionResponse, GotoDefinitionResponse, HoverProviderCapability, OneOf,
+    PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Url,
+};
+
+use crate::{completion, definition, diagnostics, document::Document, hover};
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["%".to_string(), ">".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Initializes the connection and handles messages until the client asks
+/// to shut down
+pub fn run(connection: Connection, parser: CooklangParser) -> Result<(), Error> {
+    let capabilities = serde_json::to_value(capabilities())?;
+    connection.initialize(capabilities)?;
+
+    let mut server = Server {
+        parser,
+        documents: HashMap::new(),
+    };
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                let response = server.request(req);
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(not) => {
+                for out in server.notification(not) {
+                    connection.sender.send(out.into())?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    parser: CooklangParser,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn request(&mut self, req: Request) -> Response {
+        match req.method.as_str() {
+            HoverRequest::METHOD => self.handle::<HoverRequest>(req, |s, params| {
+                let params = params.text_document_position_params;
+                let doc = s.documents.get(&params.text_document.uri)?;
+                hover::hover(&s.parser, doc, params.position)
+            }),
+            GotoDefinition::METHOD => self.handle::<GotoDefinition>(req, |s, params| {
+                let params = params.text_document_position_params;
+                let uri = params.text_document.uri;
+                let doc = s.documents.get(&uri)?;
+                definition::definition(&uri, doc, params.position)
+                    .map(GotoDefinitionResponse::Scalar)
+            }),
+            Completion::METHOD => self.handle::<Completion>(req, |s, params| {
+                let params = params.text_document_position;
+                let doc = s.documents.get(&params.text_document.uri)?;
+                completion::completion(&s.parser, doc, params.position)
+                    .map(CompletionResponse::Array)
+            }),
+            _ => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown method: {}", req.method),
+            ),
+        }
+    }
+
+    fn handle<R: RequestTrait>(
+        &self,
+        req: Request,
+        f: impl FnOnce(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(req.params) {
+            Ok(params) => Response::new_ok(req.id, f(self, params)),
+            Err(err) => Response::new_err(req.id, ErrorCode::InvalidParams as i32, err.to_string()),
+        }
+    }
+
+    /// Returns the notifications to send back
+    fn notification(&mut self, not: Notification) -> Vec<Notification> {
+        let uri = match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Ok(params) = not.extract::<<DidOpenTextDocument as NotificationTrait>::Params>(
+                    DidOpenTextDocument::METHOD,
+                ) else {
+                    return vec![];
+                };
+                let doc = params.text_document;
+                let name = recipe_name(&doc.uri);
+                let document = Document::new(&self.parser, &doc.text, &name, doc.version);
+                self.documents.insert(doc.uri.clone(), document);
+                doc.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let Ok(params) = not
+                    .extract::<<DidChangeTextDocument as NotificationTrait>::Params>(
+                        DidChangeTextDocument::METHOD,
+                    )
+                else {
+                    return vec![];
+                };
+                let uri = params.text_document.uri;
+                let Some(doc) = self.documents.get_mut(&uri) else {
+                    return vec![];
+                };
+                doc.change(
+                    &self.parser,
+                    params.content_changes,
+                    params.text_document.version,
+                );
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let Ok(params) = not
+                    .extract::<<DidCloseTextDocument as NotificationTrait>::Params>(
+                        DidCloseTextDocument::METHOD,
+                    )
+                else {
+                    return vec![];
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                // clear the diagnostics
+                let params = PublishDiagnosticsParams::new(uri, vec![], None);
+                return vec![Notification::new(
+                    PublishDiagnostics::METHOD.to_string(),
+                    params,
+                )];
+            }
+            _ => return vec![],
+        };
+
+        let doc = &self.documents[&uri];
+        let params = PublishDiagnosticsParams::new(
+            uri.clone(),
+            diagnostics::diagnostics(&uri, doc),
+            Some(doc.version),
+        );
+        vec![Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )]
+    }
+}
+
+/// In cooklang the name of the recipe is the name of the file
+fn recipe_name(uri: &Url) -> String {
+    uri.path_segments()
+        .and_then(|mut s| s.next_back())
+        .map(|file| file.strip_suffix(".cook").unwrap_or(file))
+        .unwrap_or_default()
+        .to_string()
+}
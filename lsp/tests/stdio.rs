@@ -0,0 +1,232 @@
+//! Drives the server binary over stdio like an editor would
+
+use std::{
+    io::BufReader,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use lsp_server::{Message, Notification, Request, RequestId};
+use serde_json::{json, Value};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_cooklang-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("server starts");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        let init = client.request("initialize", json!({ "capabilities": {} }));
+        assert!(init["capabilities"]["hoverProvider"].as_bool().unwrap());
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, msg: Message) {
+        msg.write(&mut self.stdin).unwrap();
+    }
+
+    fn recv(&mut self) -> Message {
+        Message::read(&mut self.stdout)
+            .unwrap()
+            .expect("server closed")
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        self.send(Request::new(id.clone(), method.to_string(), params).into());
+        loop {
+            match self.recv() {
+                Message::Response(r) if r.id == id => {
+                    assert!(r.error.is_none(), "{:?}", r.error);
+                    return r.result.unwrap_or(Value::Null);
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(Notification::new(method.to_string(), params).into());
+    }
+
+    fn diagnostics(&mut self) -> Value {
+        loop {
+            if let Message::Notification(n) = self.recv() {
+                if n.method == "textDocument/publishDiagnostics" {
+                    return n.params;
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "cooklang", "version": 1, "text": text }
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character }
+            }),
+        )
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+const URI: &str = "file:///recipes/Pancakes.cook";
+
+const RECIPE: &str = "\
+>> servings: 2
+Mix @flour{200%g} and @milk{300%ml}.
+Add more @&flour{50%g}.
+Cook for ~{3%min} in a #pan.
+";
+
+#[test]
+fn diagnostics() {
+    let mut client = Client::start();
+    let diags = client.open(RECIPE);
+    assert_eq!(diags["uri"], URI);
+    assert_eq!(diags["diagnostics"], json!([]));
+
+    // introduce an error: `@&milk{}` has a note
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{
+                "range": { "start": { "line": 2, "character": 22 }, "end": { "line": 2, "character": 22 } },
+                "text": " and @&milk(cold)"
+            }]
+        }),
+    );
+    let diags = client.diagnostics();
+    assert_eq!(diags["version"], 2);
+    let diags = diags["diagnostics"].as_array().unwrap();
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0]["severity"], 1);
+    assert_eq!(
+        diags[0]["range"],
+        json!({ "start": { "line": 2, "character": 34 }, "end": { "line": 2, "character": 38 } })
+    );
+
+    // and a warning, replacing the whole text
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 3 },
+            "contentChanges": [{ "text": ">> notes:\nA step.\n" }]
+        }),
+    );
+    let diags = client.diagnostics();
+    let diags = diags["diagnostics"].as_array().unwrap();
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0]["severity"], 2);
+
+    client.notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert_eq!(client.diagnostics()["diagnostics"], json!([]));
+    client.shutdown();
+}
+
+#[test]
+fn hover() {
+    let mut client = Client::start();
+    client.open(RECIPE);
+
+    // on the reference, the total of flour
+    let hover = client.at("textDocument/hover", 2, 12);
+    let value = hover["contents"]["value"].as_str().unwrap();
+    assert!(value.contains("**flour**"), "{value}");
+    assert!(value.contains("250 g"), "{value}");
+    assert_eq!(
+        hover["range"],
+        json!({ "start": { "line": 2, "character": 9 }, "end": { "line": 2, "character": 22 } })
+    );
+
+    let hover = client.at("textDocument/hover", 1, 24);
+    let value = hover["contents"]["value"].as_str().unwrap();
+    assert!(value.contains("300 ml"), "{value}");
+
+    // not an ingredient
+    assert_eq!(client.at("textDocument/hover", 3, 25), Value::Null);
+    client.shutdown();
+}
+
+#[test]
+fn definition() {
+    let mut client = Client::start();
+    client.open(RECIPE);
+
+    let def = client.at("textDocument/definition", 2, 14);
+    assert_eq!(def["uri"], URI);
+    assert_eq!(
+        def["range"],
+        json!({ "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 17 } })
+    );
+    // a definition has no definition
+    assert_eq!(client.at("textDocument/definition", 1, 6), Value::Null);
+    client.shutdown();
+}
+
+#[test]
+fn completion() {
+    let mut client = Client::start();
+    client.open(">> \nAdd @salt{1%\n");
+
+    let items = client.at("textDocument/completion", 1, 12);
+    let labels: Vec<_> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["label"].as_str().unwrap())
+        .collect();
+    for unit in ["g", "kg", "ml", "tsp", "cup"] {
+        assert!(labels.contains(&unit), "{unit} missing");
+    }
+
+    let items = client.at("textDocument/completion", 0, 3);
+    let labels: Vec<_> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["label"].as_str().unwrap())
+        .collect();
+    assert!(labels.contains(&"servings"));
+    assert!(labels.contains(&"[mode]"));
+
+    // not in a quantity
+    assert_eq!(client.at("textDocument/completion", 1, 4), Value::Null);
+    client.shutdown();
+}
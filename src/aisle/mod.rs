@@ -70,7 +70,7 @@ impl AisleConf<'_> {
 }
 
 /// Parse an [`AisleConf`] with the cooklang shopping list format
-pub fn parse(input: &str) -> Result<AisleConf, AisleConfError> {
+pub fn parse(input: &str) -> Result<AisleConf<'_>, AisleConfError> {
     let pairs =
         AisleConfParser::parse(Rule::shopping_list, input).map_err(|e| AisleConfError::Parse {
             span: e.location.into(),
@@ -1,4 +1,3 @@
-use std::borrow::Cow;
 use std::collections::HashMap;
 
 use regex::Regex;
@@ -7,14 +6,14 @@ use crate::ast::{self, IntermediateData, Modifiers, Text};
 use crate::context::Context;
 use crate::convert::{Converter, PhysicalQuantity};
 use crate::located::Located;
-use crate::metadata::Metadata;
+use crate::metadata::{schema::MetadataSchema, EntrySpan, Metadata};
 use crate::quantity::{Quantity, QuantityValue, UnitInfo, Value};
 use crate::span::Span;
 use crate::{model::*, Extensions, RecipeRefChecker};
 
 use super::{AnalysisError, AnalysisResult, AnalysisWarning};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct RecipeContent {
     pub metadata: Metadata,
     pub sections: Vec<Section>,
@@ -24,50 +23,76 @@ pub struct RecipeContent {
     pub inline_quantities: Vec<Quantity>,
 }
 
+/// Lines of the AST between two [`Checkpoint`]s
+const CHECKPOINT_LINES: usize = 32;
+
 #[tracing::instrument(level = "debug", skip_all, target = "cooklang::analysis", fields(ast_lines = ast.lines.len()))]
 pub fn parse_ast<'a>(
     ast: ast::Ast<'a>,
     extensions: Extensions,
     converter: &Converter,
+    schema: Option<&MetadataSchema>,
     recipe_ref_checker: Option<RecipeRefChecker>,
 ) -> AnalysisResult {
-    let mut context = Context::default();
-    let temperature_regex = extensions
-        .contains(Extensions::TEMPERATURE)
-        .then(|| match converter.temperature_regex() {
-            Ok(re) => Some(re),
-            Err(source) => {
-                context.warn(AnalysisWarning::TemperatureRegexCompile { source });
-                None
-            }
-        })
-        .flatten();
-
-    let walker = Walker {
-        extensions,
-        temperature_regex,
-        converter,
-        recipe_ref_checker,
-
-        content: Default::default(),
-        current_section: Section::default(),
-
-        define_mode: DefineMode::All,
-        duplicate_mode: DuplicateMode::New,
-        auto_scale_ingredients: false,
-        context,
-
-        ingredient_locations: Default::default(),
-        metadata_locations: Default::default(),
-        step_counter: 1,
+    let mut walker = Walker::new(extensions, converter, schema, recipe_ref_checker);
+    for line in ast.lines {
+        walker.line(line);
+    }
+    walker.finish()
+}
+
+/// Same as [`parse_ast`], but resumes from the last of the `checkpoints` and
+/// saves new ones.
+///
+/// The checkpoints have to come from analysing, with the same configuration,
+/// an AST with the same lines up to them. See [`Checkpoints::invalidate`].
+#[tracing::instrument(level = "debug", skip_all, target = "cooklang::analysis", fields(ast_lines = ast.lines.len()))]
+pub(crate) fn parse_ast_from_checkpoint(
+    ast: ast::Ast,
+    checkpoints: &mut Checkpoints,
+    extensions: Extensions,
+    converter: &Converter,
+    schema: Option<&MetadataSchema>,
+) -> AnalysisResult {
+    let mut walker = Walker::new(extensions, converter, schema, None);
+    let start = match checkpoints.0.last() {
+        Some(checkpoint) => {
+            walker.restore(checkpoint.clone());
+            checkpoint.line
+        }
+        None => 0,
     };
-    walker.ast(ast)
+    for (index, line) in ast.lines.into_iter().enumerate().skip(start) {
+        if index > start && index % CHECKPOINT_LINES == 0 {
+            checkpoints.0.push(walker.checkpoint(index));
+        }
+        walker.line(line);
+    }
+    walker.finish()
+}
+
+/// Saved states of the analysis to resume it after the AST changes
+#[derive(Debug, Default)]
+pub(crate) struct Checkpoints(Vec<Checkpoint>);
+
+impl Checkpoints {
+    /// Removes the checkpoints after the line `changed_from` of the AST, as
+    /// they depend on it
+    pub(crate) fn invalidate(&mut self, changed_from: usize) {
+        let valid = self.0.partition_point(|c| c.line <= changed_from);
+        self.0.truncate(valid);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
 }
 
-struct Walker<'a, 'c> {
+struct Walker<'c> {
     extensions: Extensions,
     temperature_regex: Option<&'c Regex>,
     converter: &'c Converter,
+    schema: Option<&'c MetadataSchema>,
     recipe_ref_checker: Option<RecipeRefChecker<'c>>,
 
     content: RecipeContent,
@@ -78,12 +103,40 @@ struct Walker<'a, 'c> {
     auto_scale_ingredients: bool,
     context: Context<AnalysisError, AnalysisWarning>,
 
-    ingredient_locations: Vec<Located<ast::Ingredient<'a>>>,
-    metadata_locations: HashMap<Cow<'a, str>, (Text<'a>, Text<'a>)>,
+    ingredient_locations: Vec<IngredientLocation>,
+    /// Span of the value of every metadata key
+    metadata_locations: HashMap<String, Span>,
     step_counter: u32,
 }
 
-#[derive(PartialEq)]
+/// State of the analysis before a line of the AST
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    /// Index of the line
+    line: usize,
+
+    content: RecipeContent,
+    current_section: Section,
+
+    define_mode: DefineMode,
+    duplicate_mode: DuplicateMode,
+    auto_scale_ingredients: bool,
+    context: Context<AnalysisError, AnalysisWarning>,
+
+    ingredient_locations: Vec<IngredientLocation>,
+    metadata_locations: HashMap<String, Span>,
+    step_counter: u32,
+}
+
+/// Locations of an ingredient needed after it's analysed
+#[derive(Debug, Clone)]
+struct IngredientLocation {
+    span: Span,
+    /// Span of the unit or, if it has none, of the whole quantity
+    quantity: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 enum DefineMode {
     All,
     Components,
@@ -91,51 +144,128 @@ enum DefineMode {
     Text,
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum DuplicateMode {
     New,
     Reference,
 }
 
-crate::context::impl_deref_context!(Walker<'_, '_>, AnalysisError, AnalysisWarning);
-
-impl<'a, 'r> Walker<'a, 'r> {
-    fn ast(mut self, ast: ast::Ast<'a>) -> AnalysisResult {
-        for line in ast.lines {
-            match line {
-                ast::Line::Metadata { key, value } => self.metadata(key, value),
-                ast::Line::Step { is_text, items } => {
-                    let new_step = self.step(is_text, items);
-
-                    // If define mode is ingredients, don't add the
-                    // step to the section. The components should have been
-                    // added to their lists
-                    if self.define_mode != DefineMode::Components {
-                        if !is_text {
-                            self.step_counter += 1;
-                        }
-                        self.current_section.steps.push(new_step);
-                    }
+crate::context::impl_deref_context!(Walker<'_>, AnalysisError, AnalysisWarning);
+
+impl<'c> Walker<'c> {
+    fn new(
+        extensions: Extensions,
+        converter: &'c Converter,
+        schema: Option<&'c MetadataSchema>,
+        recipe_ref_checker: Option<RecipeRefChecker<'c>>,
+    ) -> Self {
+        let mut context = Context::default();
+        let temperature_regex = extensions
+            .contains(Extensions::TEMPERATURE)
+            .then(|| match converter.temperature_regex() {
+                Ok(re) => Some(re),
+                Err(source) => {
+                    context.warn(AnalysisWarning::TemperatureRegexCompile { source });
+                    None
                 }
-                ast::Line::Section { name } => {
-                    self.step_counter = 1;
-                    if !self.current_section.is_empty() {
-                        self.content.sections.push(self.current_section);
+            })
+            .flatten();
+
+        Self {
+            extensions,
+            temperature_regex,
+            converter,
+            schema,
+            recipe_ref_checker,
+
+            content: Default::default(),
+            current_section: Section::default(),
+
+            define_mode: DefineMode::All,
+            duplicate_mode: DuplicateMode::New,
+            auto_scale_ingredients: false,
+            context,
+
+            ingredient_locations: Default::default(),
+            metadata_locations: Default::default(),
+            step_counter: 1,
+        }
+    }
+
+    fn checkpoint(&self, line: usize) -> Checkpoint {
+        Checkpoint {
+            line,
+            content: self.content.clone(),
+            current_section: self.current_section.clone(),
+            define_mode: self.define_mode.clone(),
+            duplicate_mode: self.duplicate_mode.clone(),
+            auto_scale_ingredients: self.auto_scale_ingredients,
+            context: self.context.clone(),
+            ingredient_locations: self.ingredient_locations.clone(),
+            metadata_locations: self.metadata_locations.clone(),
+            step_counter: self.step_counter,
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.content = checkpoint.content;
+        self.current_section = checkpoint.current_section;
+        self.define_mode = checkpoint.define_mode;
+        self.duplicate_mode = checkpoint.duplicate_mode;
+        self.auto_scale_ingredients = checkpoint.auto_scale_ingredients;
+        self.context = checkpoint.context;
+        self.ingredient_locations = checkpoint.ingredient_locations;
+        self.metadata_locations = checkpoint.metadata_locations;
+        self.step_counter = checkpoint.step_counter;
+    }
+
+    fn line(&mut self, line: ast::Line) {
+        match line {
+            ast::Line::Metadata { key, value } => self.metadata(key, value),
+            ast::Line::Step { is_text, items } => {
+                let new_step = self.step(is_text, items);
+
+                // If define mode is ingredients, don't add the
+                // step to the section. The components should have been
+                // added to their lists
+                if self.define_mode != DefineMode::Components {
+                    if !is_text {
+                        self.step_counter += 1;
                     }
-                    self.current_section =
-                        Section::new(name.map(|t| t.text_trimmed().into_owned()));
+                    self.current_section.steps.push(new_step);
+                }
+            }
+            ast::Line::Section { name } => {
+                self.step_counter = 1;
+                let span = name.as_ref().map(|t| t.span_trimmed());
+                let section = Section::new(name.map(|t| t.text_trimmed().into_owned()), span);
+                let previous = std::mem::replace(&mut self.current_section, section);
+                if !previous.is_empty() {
+                    self.content.sections.push(previous);
                 }
             }
         }
+    }
+
+    fn finish(mut self) -> AnalysisResult {
         if !self.current_section.is_empty() {
             self.content.sections.push(self.current_section);
         }
+        if let Some(schema) = self.schema {
+            for (key, _) in schema.iter().filter(|(_, s)| s.is_required()) {
+                if !self.content.metadata.map.contains_key(key) {
+                    self.context.error(AnalysisError::MissingMetadataKey {
+                        key: key.to_string(),
+                    });
+                }
+            }
+        }
         self.context.finish(Some(self.content))
     }
 
-    fn metadata(&mut self, key: Text<'a>, value: Text<'a>) {
+    fn metadata(&mut self, key: Text, value: Text) {
         self.metadata_locations
-            .insert(key.text_trimmed(), (key.clone(), value.clone()));
+            .insert(key.text_trimmed().into_owned(), value.span());
 
         let invalid_value = |possible_values| AnalysisError::InvalidSpecialMetadataValue {
             key: key.located_string_trimmed(),
@@ -172,21 +302,53 @@ impl<'a, 'r> Walker<'a, 'r> {
                     key: key.located_string_trimmed(),
                 }),
             }
-        } else if let Err(warn) = self
-            .content
-            .metadata
-            .insert(key_t.into_owned(), value_t.into_owned())
-        {
-            self.warn(AnalysisWarning::InvalidMetadataValue {
-                key: key.located_string_trimmed(),
-                value: value.located_string_trimmed(),
-                source: warn,
-            });
+        } else {
+            let span = EntrySpan {
+                key: key.span_trimmed(),
+                value: value.span_trimmed(),
+            };
+            self.content.metadata.spans.insert(key_t.to_string(), span);
+            if let Some(key_schema) = self.schema.and_then(|s| s.get(&key_t)) {
+                match key_schema.parse(&value_t) {
+                    Ok(typed) => {
+                        self.content.metadata.typed.insert(key_t.to_string(), typed);
+                    }
+                    Err(source) => {
+                        let key = key.located_string_trimmed();
+                        let value = value.located_string_trimmed();
+                        self.content.metadata.typed.shift_remove(key.as_str());
+                        if key_schema.is_required() {
+                            self.error(AnalysisError::InvalidRequiredMetadata {
+                                key,
+                                value,
+                                source,
+                            });
+                        } else {
+                            self.warn(AnalysisWarning::InvalidTypedMetadata { key, value, source });
+                        }
+                    }
+                }
+            }
+            if let Err(warn) = self
+                .content
+                .metadata
+                .insert(key_t.into_owned(), value_t.into_owned())
+            {
+                self.warn(AnalysisWarning::InvalidMetadataValue {
+                    key: key.located_string_trimmed(),
+                    value: value.located_string_trimmed(),
+                    source: warn,
+                });
+            }
         }
     }
 
-    fn step(&mut self, is_text: bool, items: Vec<ast::Item<'a>>) -> Step {
+    fn step(&mut self, is_text: bool, items: Vec<ast::Item>) -> Step {
         let mut new_items = Vec::new();
+        let span = items
+            .first()
+            .zip(items.last())
+            .map(|(first, last)| Span::new(first.span().start(), last.span().end()));
 
         let is_text = is_text || self.define_mode == DefineMode::Text;
 
@@ -207,7 +369,9 @@ impl<'a, 'r> Walker<'a, 'r> {
                     }
 
                     if let Some(re) = &self.temperature_regex {
-                        if let Some((before, temperature, after)) = find_temperature(&t, re) {
+                        if let Some((before, mut temperature, after)) = find_temperature(&t, re) {
+                            let range = before.len()..t.len() - after.len();
+                            temperature.span = Some(text.span_of(range));
                             if !before.is_empty() {
                                 new_items.push(Item::Text {
                                     value: before.to_string(),
@@ -250,10 +414,11 @@ impl<'a, 'r> Walker<'a, 'r> {
         Step {
             items: new_items,
             number,
+            span,
         }
     }
 
-    fn component(&mut self, component: Box<Located<ast::Component<'a>>>) -> Component {
+    fn component(&mut self, component: Box<Located<ast::Component>>) -> Component {
         let (inner, span) = component.take_pair();
 
         match inner {
@@ -272,7 +437,7 @@ impl<'a, 'r> Walker<'a, 'r> {
         }
     }
 
-    fn ingredient(&mut self, ingredient: Located<ast::Ingredient<'a>>) -> usize {
+    fn ingredient(&mut self, ingredient: Located<ast::Ingredient>) -> usize {
         let located_ingredient = ingredient.clone();
         let (ingredient, location) = ingredient.take_pair();
 
@@ -285,6 +450,7 @@ impl<'a, 'r> Walker<'a, 'r> {
             note: ingredient.note.map(|n| n.text_trimmed().into_owned()),
             modifiers: ingredient.modifiers.into_inner(),
             relation: IngredientRelation::definition(Vec::new()),
+            span: Some(location),
             defined_in_step: self.define_mode != DefineMode::Components,
         };
 
@@ -329,7 +495,7 @@ impl<'a, 'r> Walker<'a, 'r> {
                 && new_igr.quantity.is_some()
                 && !referenced.defined_in_step
             {
-                let definition_span = self.ingredient_locations[references_to].span();
+                let definition_span = self.ingredient_locations[references_to].span;
                 self.context
                     .error(AnalysisError::ConflictingReferenceQuantities {
                         ingredient_name: new_igr.name.to_string(),
@@ -350,19 +516,8 @@ impl<'a, 'r> Walker<'a, 'r> {
                         });
                     for (index, q) in all_quantities {
                         if let Err(e) = q.compatible_unit(new_quantity, self.converter) {
-                            let old_q_loc =
-                                self.ingredient_locations[index].quantity.as_ref().unwrap();
-                            let a = old_q_loc
-                                .unit
-                                .as_ref()
-                                .map(|l| l.span())
-                                .unwrap_or(old_q_loc.span());
-                            let new_q_loc = located_ingredient.quantity.as_ref().unwrap();
-                            let b = new_q_loc
-                                .unit
-                                .as_ref()
-                                .map(|l| l.span())
-                                .unwrap_or(new_q_loc.span());
+                            let a = self.ingredient_locations[index].quantity.unwrap();
+                            let b = quantity_location(&located_ingredient).unwrap();
                             self.context.warn(AnalysisWarning::IncompatibleUnits {
                                 a,
                                 b,
@@ -409,7 +564,10 @@ impl<'a, 'r> Walker<'a, 'r> {
             }
         }
 
-        self.ingredient_locations.push(located_ingredient);
+        self.ingredient_locations.push(IngredientLocation {
+            span: location,
+            quantity: quantity_location(&located_ingredient),
+        });
         self.content.ingredients.push(new_igr);
         self.content.ingredients.len() - 1
     }
@@ -523,7 +681,7 @@ impl<'a, 'r> Walker<'a, 'r> {
         Ok(relation)
     }
 
-    fn cookware(&mut self, cookware: Located<ast::Cookware<'a>>) -> usize {
+    fn cookware(&mut self, cookware: Located<ast::Cookware>) -> usize {
         let located_cookware = cookware.clone();
         let (cookware, location) = cookware.take_pair();
 
@@ -536,6 +694,7 @@ impl<'a, 'r> Walker<'a, 'r> {
             relation: ComponentRelation::Definition {
                 referenced_from: Vec::new(),
             },
+            span: Some(location),
         };
 
         if let Some((references_to, implicit)) =
@@ -566,7 +725,7 @@ impl<'a, 'r> Walker<'a, 'r> {
         self.content.cookware.len() - 1
     }
 
-    fn timer(&mut self, timer: Located<ast::Timer<'a>>) -> usize {
+    fn timer(&mut self, timer: Located<ast::Timer>) -> usize {
         let located_timer = timer.clone();
         let (timer, span) = timer.take_pair();
         let quantity = timer.quantity.map(|q| {
@@ -602,13 +761,14 @@ impl<'a, 'r> Walker<'a, 'r> {
         let new_timer = Timer {
             name: timer.name.map(|t| t.text_trimmed().into_owned()),
             quantity,
+            span: Some(span),
         };
 
         self.content.timers.push(new_timer);
         self.content.timers.len() - 1
     }
 
-    fn quantity(&mut self, quantity: Located<ast::Quantity<'a>>, is_ingredient: bool) -> Quantity {
+    fn quantity(&mut self, quantity: Located<ast::Quantity>, is_ingredient: bool) -> Quantity {
         let ast::Quantity { value, unit, .. } = quantity.into_inner();
         Quantity::new(
             self.value(value, is_ingredient),
@@ -621,7 +781,7 @@ impl<'a, 'r> Walker<'a, 'r> {
             ast::QuantityValue::Single {
                 value,
                 auto_scale: Some(auto_scale_marker),
-            } => {
+            } if value.is_text() => {
                 self.error(AnalysisError::ScaleTextValue {
                     value_span: value.span(),
                     auto_scale_marker: *auto_scale_marker,
@@ -629,10 +789,7 @@ impl<'a, 'r> Walker<'a, 'r> {
             }
             ast::QuantityValue::Many(v) => {
                 if let Some(s) = &self.content.metadata.servings {
-                    let servings_meta_span = self
-                        .metadata_locations
-                        .get("servings")
-                        .map(|(_, value)| value.span());
+                    let servings_meta_span = self.metadata_locations.get("servings").copied();
                     if s.len() != v.len() {
                         self.context
                             .error(AnalysisError::ScalableValueManyConflict {
@@ -837,8 +994,20 @@ impl RefComponent for Cookware {
     }
 }
 
+/// Span of the unit of the quantity of an ingredient or, if it has none, of
+/// the whole quantity
+fn quantity_location(ingredient: &Located<ast::Ingredient>) -> Option<Span> {
+    let quantity = ingredient.quantity.as_ref()?;
+    Some(
+        quantity
+            .unit
+            .as_ref()
+            .map_or(quantity.span(), |unit| unit.span()),
+    )
+}
+
 fn find_temperature<'a>(text: &'a str, re: &Regex) -> Option<(&'a str, Quantity, &'a str)> {
-    let Some(caps) = re.captures(text) else { return None; };
+    let caps = re.captures(text)?;
 
     let value = caps[1].replace(',', ".").parse::<f64>().ok()?;
     let unit = caps.get(3).unwrap().range();
@@ -4,16 +4,21 @@ use thiserror::Error;
 
 use crate::error::PassResult;
 use crate::span::Span;
-use crate::{error::RichError, located::Located, metadata::MetadataError};
+use crate::{
+    error::RichError,
+    located::Located,
+    metadata::{schema::SchemaError, MetadataError},
+};
 
 mod ast_walker;
 
 pub use ast_walker::parse_ast;
 pub use ast_walker::RecipeContent;
+pub(crate) use ast_walker::{parse_ast_from_checkpoint, Checkpoints};
 
 pub type AnalysisResult = PassResult<RecipeContent, AnalysisError, AnalysisWarning>;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Clone)]
 pub enum AnalysisError {
     #[error("Invalid value for '{key}': {value}")]
     InvalidSpecialMetadataValue {
@@ -69,6 +74,18 @@ pub enum AnalysisError {
         implicit: bool,
     },
 
+    #[error("Invalid value for required metadata key '{key}'")]
+    InvalidRequiredMetadata {
+        key: Located<String>,
+        value: Located<String>,
+
+        #[source]
+        source: SchemaError,
+    },
+
+    #[error("Missing required metadata key '{key}'")]
+    MissingMetadataKey { key: String },
+
     #[error("Invalid intermediate ingredient refrence: {reason}")]
     InvalidIntermediateReferece {
         reference_span: Span,
@@ -77,7 +94,7 @@ pub enum AnalysisError {
     },
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Clone)]
 pub enum AnalysisWarning {
     #[error("Ignoring unknown special metadata key: {key}")]
     UnknownSpecialMetadataKey { key: Located<String> },
@@ -106,6 +123,15 @@ pub enum AnalysisWarning {
         source: MetadataError,
     },
 
+    #[error("Invalid value for metadata key '{key}'")]
+    InvalidTypedMetadata {
+        key: Located<String>,
+        value: Located<String>,
+
+        #[source]
+        source: SchemaError,
+    },
+
     #[error("Component found in text mode")]
     ComponentInTextMode { component_span: Span },
 
@@ -178,6 +204,11 @@ impl RichError for AnalysisError {
             AnalysisError::InvalidIntermediateReferece { reference_span, .. } => {
                 vec![label![reference_span]]
             }
+            AnalysisError::InvalidRequiredMetadata { key, value, .. } => vec![
+                label!(key, "this key"),
+                label!(value, "does not match the schema"),
+            ],
+            AnalysisError::MissingMetadataKey { .. } => vec![],
         }
     }
 
@@ -210,6 +241,9 @@ impl RichError for AnalysisError {
                 }
             }
             AnalysisError::InvalidIntermediateReferece { help, .. } => Some(help.clone()),
+            AnalysisError::MissingMetadataKey { key } => {
+                help!(format!("Add it with: `>> {key}: value`"))
+            }
             _ => None
         }
     }
@@ -267,6 +301,10 @@ impl RichError for AnalysisWarning {
                 label!(key, "this key"),
                 label!(value, "does not understand this value"),
             ],
+            AnalysisWarning::InvalidTypedMetadata { key, value, .. } => vec![
+                label!(key, "this key"),
+                label!(value, "does not match the schema"),
+            ],
             AnalysisWarning::ComponentInTextMode { component_span } => {
                 vec![label!(component_span, "this will be ignored")]
             }
@@ -324,7 +362,7 @@ impl RichError for AnalysisWarning {
         Some("analysis")
     }
 
-    fn kind(&self) -> ariadne::ReportKind {
+    fn kind(&self) -> ariadne::ReportKind<'_> {
         ariadne::ReportKind::Warning
     }
 }
@@ -173,6 +173,164 @@ impl QuantityValue {
     }
 }
 
+// The incremental parser keeps lines between edits, so their texts have to
+// be taken out of the input and their locations have to follow the edits.
+
+impl<'a> Line<'a> {
+    /// Replaces all the texts, in the order they are in the line
+    pub(crate) fn map_text<'b, F>(self, f: &mut F) -> Line<'b>
+    where
+        F: FnMut(Text<'a>) -> Text<'b>,
+    {
+        match self {
+            Line::Metadata { key, value } => Line::Metadata {
+                key: f(key),
+                value: f(value),
+            },
+            Line::Step { is_text, items } => Line::Step {
+                is_text,
+                items: items.into_iter().map(|i| i.map_text(f)).collect(),
+            },
+            Line::Section { name } => Line::Section { name: name.map(f) },
+        }
+    }
+
+    /// Moves all the locations `delta` bytes
+    pub(crate) fn shift(&mut self, delta: isize) {
+        match self {
+            Line::Metadata { key, value } => {
+                key.shift(delta);
+                value.shift(delta);
+            }
+            Line::Step { items, .. } => items.iter_mut().for_each(|i| i.shift(delta)),
+            Line::Section { name } => shift_opt(name, delta),
+        }
+    }
+}
+
+impl<'a> Item<'a> {
+    fn map_text<'b, F>(self, f: &mut F) -> Item<'b>
+    where
+        F: FnMut(Text<'a>) -> Text<'b>,
+    {
+        match self {
+            Item::Text(t) => Item::Text(f(t)),
+            Item::Component(c) => Item::Component(Box::new(c.map(|c| c.map_text(f)))),
+        }
+    }
+
+    fn shift(&mut self, delta: isize) {
+        match self {
+            Item::Text(t) => t.shift(delta),
+            Item::Component(c) => {
+                c.shift(delta);
+                Component::shift(c, delta);
+            }
+        }
+    }
+}
+
+impl<'a> Component<'a> {
+    fn map_text<'b, F>(self, f: &mut F) -> Component<'b>
+    where
+        F: FnMut(Text<'a>) -> Text<'b>,
+    {
+        match self {
+            Component::Ingredient(i) => Component::Ingredient(Ingredient {
+                modifiers: i.modifiers,
+                intermediate_data: i.intermediate_data,
+                name: f(i.name),
+                alias: i.alias.map(&mut *f),
+                quantity: i.quantity.map(|q| q.map(|q| q.map_text(f))),
+                note: i.note.map(f),
+            }),
+            Component::Cookware(c) => Component::Cookware(Cookware {
+                modifiers: c.modifiers,
+                name: f(c.name),
+                alias: c.alias.map(&mut *f),
+                quantity: c.quantity,
+                note: c.note.map(f),
+            }),
+            Component::Timer(t) => Component::Timer(Timer {
+                name: t.name.map(&mut *f),
+                quantity: t.quantity.map(|q| q.map(|q| q.map_text(f))),
+            }),
+        }
+    }
+
+    fn shift(&mut self, delta: isize) {
+        match self {
+            Component::Ingredient(i) => {
+                i.modifiers.shift(delta);
+                if let Some(data) = &mut i.intermediate_data {
+                    data.shift(delta);
+                }
+                i.name.shift(delta);
+                shift_opt(&mut i.alias, delta);
+                if let Some(q) = &mut i.quantity {
+                    q.shift(delta);
+                    Quantity::shift(q, delta);
+                }
+                shift_opt(&mut i.note, delta);
+            }
+            Component::Cookware(c) => {
+                c.modifiers.shift(delta);
+                c.name.shift(delta);
+                shift_opt(&mut c.alias, delta);
+                if let Some(q) = &mut c.quantity {
+                    q.shift(delta);
+                    QuantityValue::shift(q, delta);
+                }
+                shift_opt(&mut c.note, delta);
+            }
+            Component::Timer(t) => {
+                shift_opt(&mut t.name, delta);
+                if let Some(q) = &mut t.quantity {
+                    q.shift(delta);
+                    Quantity::shift(q, delta);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Quantity<'a> {
+    fn map_text<'b, F>(self, f: &mut F) -> Quantity<'b>
+    where
+        F: FnMut(Text<'a>) -> Text<'b>,
+    {
+        Quantity {
+            value: self.value,
+            unit: self.unit.map(f),
+        }
+    }
+
+    fn shift(&mut self, delta: isize) {
+        self.value.shift(delta);
+        shift_opt(&mut self.unit, delta);
+    }
+}
+
+impl QuantityValue {
+    fn shift(&mut self, delta: isize) {
+        match self {
+            QuantityValue::Single { value, auto_scale } => {
+                value.shift(delta);
+                if let Some(marker) = auto_scale {
+                    marker.shift(delta);
+                }
+            }
+            QuantityValue::Many(values) => values.iter_mut().for_each(|v| v.shift(delta)),
+        }
+    }
+}
+
+fn shift_opt(text: &mut Option<Text>, delta: isize) {
+    if let Some(text) = text {
+        text.shift(delta);
+    }
+}
+
 impl Recover for Text<'_> {
     fn recover() -> Self {
         Self::empty(0)
@@ -251,6 +409,23 @@ impl std::fmt::Display for Modifiers {
     }
 }
 
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Modifiers {
+    fn schema_name() -> String {
+        "Modifiers".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        // serialized by bitflags as the names of the flags, like `RECIPE | OPT`
+        let mut schema = String::json_schema(gen).into_object();
+        schema.string().pattern = Some(
+            r"^(\s*(RECIPE|REF|HIDDEN|OPT|NEW)\s*(\|\s*(RECIPE|REF|HIDDEN|OPT|NEW)\s*)*)?$"
+                .to_string(),
+        );
+        schema.into()
+    }
+}
+
 /// Data for interemediate references
 ///
 /// This is not checked, and may point to inexistent or future steps/sections
@@ -76,6 +76,34 @@ impl<'a> Text<'a> {
         Span::new(start, end)
     }
 
+    /// Get the span of the original input of a range of [`Self::text`]
+    pub(crate) fn span_of(&self, range: std::ops::Range<usize>) -> Span {
+        // offset of an index of the text, `end` tells which fragment to use
+        // when the index is in the boundary of two of them
+        let offset = |mut index: usize, end: bool| {
+            for f in &self.fragments {
+                let len = f.text.len();
+                if index < len || (end && index == len) {
+                    return f.offset + index;
+                }
+                index -= len;
+            }
+            self.span().end()
+        };
+        if range.is_empty() {
+            return Span::pos(offset(range.start, false));
+        }
+        Span::new(offset(range.start, false), offset(range.end, true))
+    }
+
+    /// Get the span of the original input of [`Self::text_trimmed`]
+    pub(crate) fn span_trimmed(&self) -> Span {
+        let text = self.text();
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len().max(start);
+        self.span_of(start..end)
+    }
+
     /// Get the text of all the fragments concatenated
     pub fn text(&self) -> Cow<'a, str> {
         // Contiguous text fragments may be joined together without a copy.
@@ -107,7 +135,7 @@ impl<'a> Text<'a> {
     }
 
     /// Convenience method to the the text in [`Located`]
-    pub fn located_text_trimmed(&self) -> Located<Cow<str>> {
+    pub fn located_text_trimmed(&self) -> Located<Cow<'_, str>> {
         Located::new(self.text_trimmed(), self.span())
     }
 
@@ -115,6 +143,14 @@ impl<'a> Text<'a> {
     pub fn located_string_trimmed(&self) -> Located<String> {
         self.located_text_trimmed().map(Cow::into_owned)
     }
+
+    /// Moves the location `delta` bytes
+    pub(crate) fn shift(&mut self, delta: isize) {
+        self.offset = self.offset.wrapping_add_signed(delta);
+        for f in &mut self.fragments {
+            f.offset = f.offset.wrapping_add_signed(delta);
+        }
+    }
 }
 
 impl std::fmt::Display for Text<'_> {
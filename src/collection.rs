@@ -0,0 +1,463 @@
+//! Index a directory of recipes and search them
+//!
+//! A [`Collection`] indexes all the `.cook` files inside a directory. Only the
+//! metadata is parsed when indexing, with [`CooklangParser::parse_metadata`].
+//! The full recipe is parsed, and the result cached, the first time a query
+//! needs the ingredients or cookware.
+//!
+//! When the files change on disk, [`Collection::refresh`] only parses again
+//! the ones that were modified.
+//!
+//! ```no_run
+//! # use cooklang::{CooklangParser, collection::{Collection, Query}};
+//! let mut collection = Collection::new("recipes", CooklangParser::default());
+//! collection.refresh()?;
+//!
+//! let query = Query::new().tag("vegan").ingredient("chickpeas").max_time(30);
+//! for entry in collection.search(&query) {
+//!     println!("{}", entry.name());
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::{metadata::Metadata, CooklangParser};
+
+/// Index of the recipes in a directory
+#[derive(Debug)]
+pub struct Collection {
+    root: PathBuf,
+    parser: CooklangParser,
+    /// By id, so iteration order is stable
+    entries: BTreeMap<String, Entry>,
+}
+
+/// A recipe in a [`Collection`]
+#[derive(Debug)]
+pub struct Entry {
+    id: String,
+    path: PathBuf,
+    name: String,
+    modified: Option<SystemTime>,
+    len: u64,
+    metadata: Metadata,
+    valid_metadata: bool,
+    components: OnceCell<Option<Components>>,
+}
+
+/// Normalized names of the components of a recipe
+#[derive(Debug)]
+struct Components {
+    ingredients: Vec<String>,
+    cookware: Vec<String>,
+}
+
+/// What changed in a [`Collection::refresh`]
+///
+/// All the lists contain ids and are sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    /// New recipes
+    pub added: Vec<String>,
+    /// Recipes modified since the last refresh
+    pub updated: Vec<String>,
+    /// Recipes no longer in the directory
+    pub removed: Vec<String>,
+    /// Recipes that could not be read, with the kind of error
+    ///
+    /// They are not indexed, or keep their previous entry if they were, and
+    /// will be read again in the next refresh.
+    pub failed: Vec<(String, std::io::ErrorKind)>,
+}
+
+impl Changes {
+    /// Checks if nothing changed and all the files could be read
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.removed.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+impl Collection {
+    /// Creates an empty collection for the recipes inside `root`
+    ///
+    /// Nothing is read until [`Self::refresh`] is called.
+    pub fn new(root: impl Into<PathBuf>, parser: CooklangParser) -> Self {
+        Self {
+            root: root.into(),
+            parser,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the parser
+    pub fn parser(&self) -> &CooklangParser {
+        &self.parser
+    }
+
+    /// Walks the directory and updates the index
+    ///
+    /// A file is parsed again only if its modification time or size changed.
+    /// Hidden files and directories, the ones starting with `.`, are ignored.
+    ///
+    /// Errors reading the directories are returned, and the index is left as
+    /// it was. Errors reading a single file are in [`Changes::failed`].
+    pub fn refresh(&mut self) -> std::io::Result<Changes> {
+        let mut files = BTreeMap::new();
+        walk(&self.root, &self.root, &mut files)?;
+
+        let mut changes = Changes::default();
+        self.entries.retain(|id, _| {
+            let keep = files.contains_key(id);
+            if !keep {
+                changes.removed.push(id.clone());
+            }
+            keep
+        });
+
+        for (id, (path, fs_meta)) in files {
+            let modified = fs_meta.modified().ok();
+            let len = fs_meta.len();
+            let list = match self.entries.get(&id) {
+                Some(e) if e.modified.is_some() && e.modified == modified && e.len == len => {
+                    continue
+                }
+                Some(_) => &mut changes.updated,
+                None => &mut changes.added,
+            };
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(err) => {
+                    changes.failed.push((id, err.kind()));
+                    continue;
+                }
+            };
+            list.push(id.clone());
+            let entry = self.index(id.clone(), path, &content, modified, len);
+            self.entries.insert(id, entry);
+        }
+        Ok(changes)
+    }
+
+    fn index(
+        &self,
+        id: String,
+        path: PathBuf,
+        content: &str,
+        modified: Option<SystemTime>,
+        len: u64,
+    ) -> Entry {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let result = self.parser.parse_metadata(content);
+        let valid_metadata = result.is_valid();
+        let metadata = result.into_output().unwrap_or_default();
+        Entry {
+            id,
+            path,
+            name,
+            modified,
+            len,
+            metadata,
+            valid_metadata,
+            components: OnceCell::new(),
+        }
+    }
+
+    /// Number of recipes
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if there are no recipes
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get a recipe by id
+    ///
+    /// The id is the `/` separated path relative to the root, with the
+    /// `.cook` extension. The same as in [`FsSource`](crate::resolve::FsSource).
+    pub fn get(&self, id: &str) -> Option<&Entry> {
+        self.entries.get(id)
+    }
+
+    /// Iterate over all the recipes, sorted by id
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values()
+    }
+
+    /// Recipes that match a query, sorted by id
+    ///
+    /// If the query needs the ingredients or cookware, the recipes that
+    /// match the rest of the query are fully parsed. Recipes with errors
+    /// never match these filters.
+    pub fn search(&self, query: &Query) -> Vec<&Entry> {
+        self.entries
+            .values()
+            .filter(|e| query.matches_metadata(e))
+            .filter(|e| query.matches_components(e, &self.parser))
+            .collect()
+    }
+}
+
+/// Finds all the `.cook` files
+fn walk(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, (PathBuf, std::fs::Metadata)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let fs_meta = entry.metadata()?;
+        if fs_meta.is_dir() {
+            walk(root, &path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "cook") {
+            let id = path
+                .strip_prefix(root)
+                .expect("walking inside root")
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(id, (path, fs_meta));
+        }
+    }
+    Ok(())
+}
+
+impl Entry {
+    /// Id of the recipe in the collection
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Name of the recipe, the file name without extension
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Metadata of the recipe
+    ///
+    /// If it had errors, this is empty, see [`Self::has_valid_metadata`].
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Checks if the metadata had no errors
+    pub fn has_valid_metadata(&self) -> bool {
+        self.valid_metadata
+    }
+
+    /// Total time in minutes
+    pub fn total_time(&self) -> Option<u32> {
+        self.metadata.time.map(|t| t.total())
+    }
+
+    /// Parses the recipe, the result is not cached
+    pub fn parse(&self, parser: &CooklangParser) -> std::io::Result<crate::RecipeResult> {
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(parser.parse(&content, &self.name))
+    }
+
+    fn components(&self, parser: &CooklangParser) -> Option<&Components> {
+        self.components
+            .get_or_init(|| {
+                let (recipe, _) = self.parse(parser).ok()?.into_result().ok()?;
+                let names = |names: Vec<String>| {
+                    let mut names = names.into_iter().map(|n| normalize(&n)).collect::<Vec<_>>();
+                    names.sort();
+                    names.dedup();
+                    names
+                };
+                Some(Components {
+                    ingredients: names(
+                        recipe
+                            .ingredients
+                            .iter()
+                            .map(|i| i.display_name().into_owned())
+                            .collect(),
+                    ),
+                    cookware: names(
+                        recipe
+                            .cookware
+                            .iter()
+                            .map(|c| c.display_name().to_string())
+                            .collect(),
+                    ),
+                })
+            })
+            .as_ref()
+    }
+}
+
+/// Case insensitive and without extra whitespace
+fn normalize(s: &str) -> String {
+    s.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Search filters for a [`Collection`]
+///
+/// A recipe has to match all the filters. Text comparisons are case
+/// insensitive.
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    tags: Vec<String>,
+    ingredients: Vec<String>,
+    cookware: Vec<String>,
+    author: Option<String>,
+    source: Option<String>,
+    min_time: Option<u32>,
+    max_time: Option<u32>,
+    servings: Option<u32>,
+}
+
+impl Query {
+    /// Query that matches every recipe
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Has the tag
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(normalize(tag));
+        self
+    }
+
+    /// Uses an ingredient, compared with its
+    /// [display name](crate::model::Ingredient::display_name)
+    pub fn ingredient(mut self, name: &str) -> Self {
+        self.ingredients.push(normalize(name));
+        self
+    }
+
+    /// Uses a cookware item
+    pub fn cookware(mut self, name: &str) -> Self {
+        self.cookware.push(normalize(name));
+        self
+    }
+
+    /// The author name or URL contains `author`
+    pub fn author(mut self, author: &str) -> Self {
+        self.author = Some(normalize(author));
+        self
+    }
+
+    /// The source name or URL contains `source`
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = Some(normalize(source));
+        self
+    }
+
+    /// Takes at least these minutes in total
+    pub fn min_time(mut self, minutes: u32) -> Self {
+        self.min_time = Some(minutes);
+        self
+    }
+
+    /// Takes at most these minutes in total
+    pub fn max_time(mut self, minutes: u32) -> Self {
+        self.max_time = Some(minutes);
+        self
+    }
+
+    /// Can be made for this number of servings, one of the declared ones
+    pub fn servings(mut self, servings: u32) -> Self {
+        self.servings = Some(servings);
+        self
+    }
+
+    fn matches_metadata(&self, entry: &Entry) -> bool {
+        let meta = &entry.metadata;
+
+        let tags = meta.tags.iter().map(|t| normalize(t)).collect::<Vec<_>>();
+        if !self.tags.iter().all(|t| tags.contains(t)) {
+            return false;
+        }
+
+        let name_and_url = |field: &Option<crate::metadata::NameAndUrl>, query: &str| {
+            field.as_ref().is_some_and(|f| {
+                f.name().is_some_and(|n| normalize(n).contains(query))
+                    || f.url()
+                        .is_some_and(|u| u.as_str().to_lowercase().contains(query))
+            })
+        };
+        if let Some(author) = &self.author {
+            if !name_and_url(&meta.author, author) {
+                return false;
+            }
+        }
+        if let Some(source) = &self.source {
+            if !name_and_url(&meta.source, source) {
+                return false;
+            }
+        }
+
+        if self.min_time.is_some() || self.max_time.is_some() {
+            let Some(time) = entry.total_time() else {
+                return false;
+            };
+            if self.min_time.is_some_and(|min| time < min)
+                || self.max_time.is_some_and(|max| time > max)
+            {
+                return false;
+            }
+        }
+
+        if let Some(servings) = self.servings {
+            if !meta
+                .servings
+                .as_ref()
+                .is_some_and(|s| s.contains(&servings))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn matches_components(&self, entry: &Entry, parser: &CooklangParser) -> bool {
+        if self.ingredients.is_empty() && self.cookware.is_empty() {
+            return true;
+        }
+        let Some(components) = entry.components(parser) else {
+            return false;
+        };
+        self.ingredients
+            .iter()
+            .all(|i| components.ingredients.contains(i))
+            && self
+                .cookware
+                .iter()
+                .all(|c| components.cookware.contains(c))
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Context<E, W> {
     pub errors: Vec<E>,
     pub warnings: Vec<W>,
@@ -1,13 +1,15 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{borrow::Cow, collections::HashSet, sync::Arc};
 
 use enum_map::{enum_map, EnumMap};
 use thiserror::Error;
 
 use super::{
     convert_f64,
-    units_file::{BestUnits, Extend, Precedence, SIPrefix, UnitEntry, Units, UnitsFile, SI},
-    BestConversions, BestConversionsStore, Converter, PhysicalQuantity, System, Unit, UnitIndex,
-    UnknownUnit,
+    units_file::{
+        BestUnits, DensityFile, Extend, Precedence, SIPrefix, UnitEntry, Units, UnitsFile, SI,
+    },
+    BestConversions, BestConversionsStore, Converter, Densities, Density, PhysicalQuantity, System,
+    Unit, UnitIndex, UnknownUnit,
 };
 
 /// Builder to create a custom [`Converter`]
@@ -15,6 +17,9 @@ use super::{
 /// The builder uses [`UnitsFile`] to configure the converter. More than one
 /// file can be layered. Order matters, as one file can extend the units of
 /// another added before, or be overwritten by others after.
+///
+/// Ingredient densities are configured with [`DensityFile`]s, layered in the
+/// same way.
 #[derive(Debug, Default)]
 pub struct ConverterBuilder {
     all_units: Vec<Unit>,
@@ -23,6 +28,9 @@ pub struct ConverterBuilder {
     si: SI,
     best_units: EnumMap<PhysicalQuantity, Option<BestUnits>>,
     default_system: System,
+    densities: Densities,
+    bare_units_system: Option<System>,
+    shared_keys: Vec<(Arc<str>, usize, usize)>,
 }
 
 impl ConverterBuilder {
@@ -82,10 +90,12 @@ impl ConverterBuilder {
                 Units::BySystem {
                     metric,
                     imperial,
+                    us_customary,
                     unspecified,
                 } => {
                     add_units(metric, Some(System::Metric))?;
                     add_units(imperial, Some(System::Imperial))?;
+                    add_units(us_customary, Some(System::UsCustomary))?;
                     add_units(unspecified, None)?;
                 }
             };
@@ -94,9 +104,9 @@ impl ConverterBuilder {
             if let Some(best_units) = group.best {
                 if match &best_units {
                     BestUnits::Unified(v) => v.is_empty(),
-                    BestUnits::BySystem { metric, imperial } => {
-                        metric.is_empty() || imperial.is_empty()
-                    }
+                    BestUnits::BySystem {
+                        metric, imperial, ..
+                    } => metric.is_empty() || imperial.is_empty(),
                 } {
                     return Err(ConverterBuilderError::EmptyBest {
                         reason: "empty list of units",
@@ -130,6 +140,69 @@ impl ConverterBuilder {
         Ok(self)
     }
 
+    /// Set the system the keys shared by imperial and US customary units
+    /// resolve to
+    ///
+    /// Some units, like the cup, are different in both systems. The bundled
+    /// units give both the plain names, like `cup` or `tbsp`. By default they
+    /// are the imperial units, with [`System::UsCustomary`] they are the US
+    /// ones. [`System::Metric`] is the same as the default.
+    pub fn with_bare_units_system(mut self, system: System) -> Self {
+        self.set_bare_units_system(system);
+        self
+    }
+
+    /// Set the system the keys shared by imperial and US customary units
+    /// resolve to
+    ///
+    /// See [`Self::with_bare_units_system`].
+    pub fn set_bare_units_system(&mut self, system: System) -> &mut Self {
+        self.bare_units_system = Some(system);
+        self
+    }
+
+    /// Add a [`DensityFile`] to the builder
+    pub fn with_density_file(
+        mut self,
+        densities: DensityFile,
+    ) -> Result<Self, ConverterBuilderError> {
+        self.add_density_file(densities)?;
+        Ok(self)
+    }
+
+    /// Add a [`DensityFile`] to the builder
+    pub fn add_density_file(
+        &mut self,
+        densities: DensityFile,
+    ) -> Result<&mut Self, ConverterBuilderError> {
+        if let Some(prefer) = densities.prefer {
+            if !matches!(prefer, PhysicalQuantity::Volume | PhysicalQuantity::Mass) {
+                return Err(ConverterBuilderError::InvalidDensityPreference { quantity: prefer });
+            }
+            self.densities.prefer = Some(prefer);
+        }
+
+        for (name, entry) in densities.ingredient {
+            if !entry.density.is_finite() || entry.density <= 0.0 {
+                return Err(ConverterBuilderError::InvalidDensity {
+                    ingredient: name,
+                    density: entry.density,
+                });
+            }
+            let id = self.densities.all.len();
+            self.densities.all.push(Density {
+                ingredient: name.as_str().into(),
+                value: entry.density,
+            });
+            // later entries override the keys of the ones before
+            for key in std::iter::once(&name).chain(&entry.aliases) {
+                self.densities.index.insert(key.trim().to_lowercase(), id);
+            }
+        }
+
+        Ok(self)
+    }
+
     /// Consume the builder and return the new [`Converter`]
     pub fn finish(mut self) -> Result<Converter, ConverterBuilderError> {
         // expand the stored units
@@ -145,6 +218,11 @@ impl ConverterBuilder {
             }
         }
 
+        // resolve the keys shared by imperial and US customary units before
+        // the extensions reference them
+        let bare_units_system = self.bare_units_system.unwrap_or(System::Imperial);
+        resolve_shared_keys(&mut self.unit_index, &self.shared_keys, bare_units_system);
+
         // apply the extend groups
         for extend_group in self.extend {
             let mut to_update = HashSet::new();
@@ -186,11 +264,25 @@ impl ConverterBuilder {
             }
         }
 
+        // the shared keys in the best units are the units of their system
+        let system_index = enum_map! {
+            system => {
+                let mut index = Cow::Borrowed(&self.unit_index);
+                if !self.shared_keys.is_empty() {
+                    resolve_shared_keys(index.to_mut(), &self.shared_keys, system);
+                }
+                index
+            }
+        };
+
         let best = enum_map! {
             q =>  {
                 if let Some(best_units) = &self.best_units[q] {
-                    unify_best_units_systems(best_units, &self.unit_index, &mut self.all_units)?;
-                    BestConversionsStore::new(best_units, &self.unit_index, &self.all_units)?
+                    unify_best_units_systems(best_units, &system_index, &mut self.all_units)?;
+                    BestConversionsStore::new(best_units, &system_index, &self.all_units)?
+                } else if self.all_units.iter().all(|u| u.physical_quantity != q) {
+                    // nothing to convert
+                    BestConversionsStore::default()
                 } else {
                     return Err(ConverterBuilderError::EmptyBest { reason: "no best units given", quantity: q })
                 }
@@ -211,32 +303,101 @@ impl ConverterBuilder {
             quantity_index,
             best,
             default_system: self.default_system,
+            densities: self.densities,
             temperature_regex: Default::default(),
         })
     }
 
     fn add_unit(&mut self, unit: Unit) -> Result<usize, ConverterBuilderError> {
         let id = self.all_units.len();
+        self.take_shared_keys(&unit, id);
         self.unit_index.add_unit(&unit, id)?;
         self.all_units.push(unit);
         Ok(id)
     }
+
+    /// Imperial and US customary units of the same quantity can share keys.
+    /// They are removed from the index, so the unit can be added, and resolved
+    /// in [`Self::finish`].
+    fn take_shared_keys(&mut self, unit: &Unit, id: usize) {
+        let other_system = match unit.system {
+            Some(System::Imperial) => System::UsCustomary,
+            Some(System::UsCustomary) => System::Imperial,
+            _ => return,
+        };
+        for key in unit.all_keys() {
+            let Some(&other_id) = self.unit_index.0.get(key) else {
+                continue;
+            };
+            let other = &self.all_units[other_id];
+            if other.system == Some(other_system)
+                && other.physical_quantity == unit.physical_quantity
+            {
+                self.unit_index.0.remove(key);
+                let (imperial_id, us_id) = if other_system == System::Imperial {
+                    (other_id, id)
+                } else {
+                    (id, other_id)
+                };
+                self.shared_keys.push((Arc::clone(key), imperial_id, us_id));
+            }
+        }
+    }
+}
+
+type SystemUnitIndex<'a> = EnumMap<System, Cow<'a, UnitIndex>>;
+
+/// Point the keys shared by imperial and US customary units to the ones of
+/// the given system. Metric has none, so it gets the imperial ones.
+fn resolve_shared_keys(
+    unit_index: &mut UnitIndex,
+    shared_keys: &[(Arc<str>, usize, usize)],
+    system: System,
+) {
+    for (key, imperial_id, us_id) in shared_keys {
+        let id = if system == System::UsCustomary {
+            us_id
+        } else {
+            imperial_id
+        };
+        unit_index.0.insert(Arc::clone(key), *id);
+    }
 }
 
 impl BestConversionsStore {
     fn new(
         best_units: &BestUnits,
-        unit_index: &UnitIndex,
+        system_index: &SystemUnitIndex,
         all_units: &[Unit],
     ) -> Result<Self, ConverterBuilderError> {
         let v = match best_units {
-            BestUnits::Unified(names) => {
-                Self::Unified(BestConversions::new(names, unit_index, all_units)?)
+            BestUnits::Unified(names) => Self::Unified(BestConversions::new(
+                names,
+                &system_index[System::Metric],
+                all_units,
+            )?),
+            BestUnits::BySystem {
+                metric,
+                imperial,
+                us_customary,
+            } => {
+                let imperial =
+                    BestConversions::new(imperial, &system_index[System::Imperial], all_units)?;
+                let us_customary = if us_customary.is_empty() {
+                    imperial.clone()
+                } else {
+                    BestConversions::new(
+                        us_customary,
+                        &system_index[System::UsCustomary],
+                        all_units,
+                    )?
+                };
+                Self::BySystem {
+                    metric: BestConversions::new(metric, &system_index[System::Metric], all_units)?,
+                    imperial,
+                    us_customary,
+                }
             }
-            BestUnits::BySystem { metric, imperial } => Self::BySystem {
-                metric: BestConversions::new(metric, unit_index, all_units)?,
-                imperial: BestConversions::new(imperial, unit_index, all_units)?,
-            },
         };
         Ok(v)
     }
@@ -278,14 +439,23 @@ impl BestConversions {
 
 fn unify_best_units_systems(
     best_units: &BestUnits,
-    unit_index: &UnitIndex,
+    system_index: &SystemUnitIndex,
     all_units: &mut [Unit],
 ) -> Result<(), ConverterBuilderError> {
     match best_units {
         BestUnits::Unified(_) => {}
-        BestUnits::BySystem { metric, imperial } => {
-            unify_units_systems(metric, System::Metric, unit_index, all_units)?;
-            unify_units_systems(imperial, System::Imperial, unit_index, all_units)?;
+        BestUnits::BySystem {
+            metric,
+            imperial,
+            us_customary,
+        } => {
+            for (units, system) in [
+                (metric, System::Metric),
+                (imperial, System::Imperial),
+                (us_customary, System::UsCustomary),
+            ] {
+                unify_units_systems(units, system, &system_index[system], all_units)?;
+            }
         }
     }
     Ok(())
@@ -302,6 +472,8 @@ fn unify_units_systems(
     for unit in units {
         let unit_id = unit_index.get_unit_id(unit)?;
         match all_units[unit_id].system {
+            // most imperial units are also US customary units
+            Some(System::Imperial) if system == System::UsCustomary => {}
             Some(unit_system) => {
                 if system != unit_system {
                     return Err(ConverterBuilderError::IncorrectUnitSystem {
@@ -494,6 +666,12 @@ pub enum ConverterBuilderError {
     #[error("No SI prefixes found when expandind SI on a unit")]
     EmptySIPrefixes,
 
+    #[error("Invalid density for '{ingredient}': {density}. It has to be a positive number")]
+    InvalidDensity { ingredient: String, density: f64 },
+
+    #[error("Preferred quantity for densities has to be volume or mass, found {quantity}")]
+    InvalidDensityPreference { quantity: PhysicalQuantity },
+
     #[error("Best units' unit incorrect system: in {contained} units, unit '{unit}' was {got}")]
     IncorrectUnitSystem {
         unit: Box<Unit>,
@@ -4,6 +4,7 @@
 //!     - A layered configuration system
 //!     - Conversions between systems
 //!     - Conversions to the best fit possible
+//!     - Conversions between volume and mass with ingredient densities
 
 use std::{collections::HashMap, ops::RangeInclusive, sync::Arc};
 
@@ -20,7 +21,7 @@ use crate::{
 };
 
 pub use builder::{ConverterBuilder, ConverterBuilderError};
-pub use units_file::UnitsFile;
+pub use units_file::{DensityFile, UnitsFile};
 
 use units_file::SIPrefix;
 
@@ -43,6 +44,7 @@ pub struct Converter {
     quantity_index: UnitQuantityIndex,
     best: EnumMap<PhysicalQuantity, BestConversionsStore>,
     default_system: System,
+    densities: Densities,
 
     temperature_regex: OnceCell<Regex>,
 }
@@ -68,6 +70,7 @@ impl Converter {
             quantity_index: Default::default(),
             best: Default::default(),
             default_system: Default::default(),
+            densities: Default::default(),
             temperature_regex: Default::default(),
         }
     }
@@ -126,14 +129,29 @@ impl Converter {
             .expect("unit not found");
         let mut iter = match &self.best[unit.physical_quantity] {
             BestConversionsStore::Unified(u) => u.0.iter(),
-            BestConversionsStore::BySystem { metric, imperial } => match unit.system {
+            BestConversionsStore::BySystem {
+                metric,
+                imperial,
+                us_customary,
+            } => match unit.system {
                 Some(System::Metric) => metric.0.iter(),
                 Some(System::Imperial) => imperial.0.iter(),
+                Some(System::UsCustomary) => us_customary.0.iter(),
                 None => return false,
             },
         };
         iter.any(|&(_, id)| id == unit_id)
     }
+
+    /// Get the density of an ingredient by name or alias
+    ///
+    /// The lookup is case insensitive.
+    pub fn density(&self, ingredient: &str) -> Option<&Density> {
+        self.densities
+            .index
+            .get(&ingredient.trim().to_lowercase())
+            .map(|&id| &self.densities.all[id])
+    }
 }
 
 #[cfg(not(feature = "bundled_units"))]
@@ -157,11 +175,32 @@ impl PartialEq for Converter {
             && self.quantity_index == other.quantity_index
             && self.best == other.best
             && self.default_system == other.default_system
+            && self.densities == other.densities
         // temperature_regex ignored, it should be the same if the rest is the
         // the same
     }
 }
 
+/// Density of an ingredient
+///
+/// Used to convert between volume and mass. See
+/// [`DensityFile`] to configure them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Density {
+    /// Name of the ingredient the density was declared for
+    pub ingredient: Arc<str>,
+    /// Grams per millilitre
+    pub value: f64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Densities {
+    pub(crate) all: Vec<Density>,
+    /// lowercase name or alias to index in `all`
+    pub(crate) index: HashMap<String, usize>,
+    pub(crate) prefer: Option<PhysicalQuantity>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub(crate) struct UnitIndex(HashMap<Arc<str>, usize>);
 
@@ -243,6 +282,7 @@ enum BestConversionsStore {
     BySystem {
         metric: BestConversions,
         imperial: BestConversions,
+        us_customary: BestConversions,
     },
 }
 
@@ -277,6 +317,8 @@ pub enum PhysicalQuantity {
     Length,
     Temperature,
     Time,
+    /// Number of pieces, like a `dozen`
+    Count,
 }
 
 impl Converter {
@@ -289,10 +331,55 @@ impl Converter {
         to: impl Into<ConvertTo<'t>>,
     ) -> Result<Quantity, ConvertError> {
         let to = to.into();
-        self.convert_(from, to)
+        self.convert_(from, to, None, None).map(|(q, _)| q)
+    }
+
+    /// Convert a [Quantity] of an ingredient
+    ///
+    /// Same as [`Self::convert`], but if the ingredient has a known
+    /// [`Density`], it can convert between volume and mass. The density is
+    /// returned when it was used.
+    ///
+    /// ```
+    /// # use cooklang::{Converter, Quantity, QuantityValue, convert::DensityFile};
+    /// let densities: DensityFile = toml::from_str(r#"
+    ///     [ingredient.flour]
+    ///     density = 0.5
+    /// "#).unwrap();
+    /// let converter = Converter::builder()
+    ///     .with_bundled_units()?
+    ///     .with_density_file(densities)?
+    ///     .finish()?;
+    ///
+    /// let litre = Quantity::new(QuantityValue::Fixed { value: 1.0.into() }, Some("l".into()));
+    /// let (grams, density) = converter.convert_ingredient(&litre, "g", "Flour")?;
+    /// assert_eq!(grams.to_string(), "500 g");
+    /// assert_eq!(density.unwrap().value, 0.5);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert_ingredient<'t>(
+        &self,
+        from: &Quantity,
+        to: impl Into<ConvertTo<'t>>,
+        ingredient: &str,
+    ) -> Result<(Quantity, Option<Density>), ConvertError> {
+        let density = self.density(ingredient);
+        let (q, used) = self.convert_(from, to.into(), density, None)?;
+        Ok((q, density.filter(|_| used).cloned()))
     }
 
-    fn convert_(&self, from: &Quantity, to: ConvertTo) -> Result<Quantity, ConvertError> {
+    /// Converts a quantity, crossing between volume and mass with `density`
+    /// if the target, or `prefer` when the target is not a unit, is the other
+    /// physical quantity.
+    ///
+    /// Returns if the density was used.
+    fn convert_(
+        &self,
+        from: &Quantity,
+        to: ConvertTo,
+        density: Option<&Density>,
+        prefer: Option<PhysicalQuantity>,
+    ) -> Result<(Quantity, bool), ConvertError> {
         let unit_info = from.unit().map(|u| u.unit_info_or_parse(self));
         let unit = match unit_info {
             Some(UnitInfo::Known(ref u)) => ConvertUnit::Unit(u),
@@ -304,27 +391,49 @@ impl Converter {
             None => return Err(ConvertError::NoUnit(from.clone())),
         };
 
+        let mut used_density = false;
+        let mut convert = |original: &Value| -> Result<(Value, Arc<Unit>), ConvertError> {
+            let value = original.try_into()?;
+            let (value, to_unit, used) = match density {
+                Some(density) => {
+                    let (value, from_unit, used) =
+                        self.cross_density(value, unit, to, density, prefer)?;
+                    let (value, to_unit) =
+                        self.convert2(value, ConvertUnit::Unit(from_unit), to)?;
+                    (value, to_unit, used)
+                }
+                None => {
+                    let (value, to_unit) = self.convert2(value, unit, to)?;
+                    (value, to_unit, false)
+                }
+            };
+            used_density |= used;
+            // keep exact fractions when the unit does not change
+            if let ConvertUnit::Unit(from_unit) = unit {
+                if !used && Arc::ptr_eq(from_unit, &to_unit) {
+                    return Ok((original.clone(), to_unit));
+                }
+            }
+            Ok((value.into(), to_unit))
+        };
+
         let (value, unit) = match &from.value {
             QuantityValue::Fixed { value } => {
-                let (value, unit) = self.convert2(value.try_into()?, unit, to)?;
-                let q_value = QuantityValue::Fixed {
-                    value: value.into(),
-                };
+                let (value, unit) = convert(value)?;
+                let q_value = QuantityValue::Fixed { value };
                 (q_value, unit)
             }
             QuantityValue::Linear { value } => {
-                let (value, unit) = self.convert2(value.try_into()?, unit, to)?;
-                let q_value = QuantityValue::Linear {
-                    value: value.into(),
-                };
+                let (value, unit) = convert(value)?;
+                let q_value = QuantityValue::Linear { value };
                 (q_value, unit)
             }
             QuantityValue::ByServings { values } => {
                 let mut new_values = Vec::with_capacity(values.len());
                 let mut new_unit = None;
                 for v in values {
-                    let (value, unit) = self.convert2(v.try_into()?, unit, to)?;
-                    new_values.push(value.into());
+                    let (value, unit) = convert(v)?;
+                    new_values.push(value);
                     new_unit = Some(unit);
                 }
                 let q_value = QuantityValue::ByServings { values: new_values };
@@ -333,11 +442,39 @@ impl Converter {
             }
         };
 
-        Ok(Quantity::with_known_unit(
-            value,
-            unit.to_string(),
-            Some(unit),
-        ))
+        let mut quantity = Quantity::with_known_unit(value, unit.to_string(), Some(unit));
+        quantity.span = from.span;
+        Ok((quantity, used_density))
+    }
+
+    /// If needed, converts a volume to grams or a mass to millilitres
+    fn cross_density<'a>(
+        &'a self,
+        value: ConvertValue,
+        unit: ConvertUnit<'a>,
+        to: ConvertTo,
+        density: &Density,
+        prefer: Option<PhysicalQuantity>,
+    ) -> Result<(ConvertValue, &'a Arc<Unit>, bool), ConvertError> {
+        let unit = self.get_unit(&unit)?;
+        let target = match to {
+            ConvertTo::Unit(target) => Some(self.get_unit(&target)?.physical_quantity),
+            ConvertTo::Best(_) | ConvertTo::SameSystem => prefer,
+        };
+        let (cross_from, cross_to, factor) = match (unit.physical_quantity, target) {
+            (PhysicalQuantity::Volume, Some(PhysicalQuantity::Mass)) => ("ml", "g", density.value),
+            (PhysicalQuantity::Mass, Some(PhysicalQuantity::Volume)) => {
+                ("g", "ml", density.value.recip())
+            }
+            _ => return Ok((value, unit, false)),
+        };
+        let cross_from = self.get_unit(&ConvertUnit::Key(cross_from))?;
+        let cross_to = self.get_unit(&ConvertUnit::Key(cross_to))?;
+        let value = match self.convert_value(value, unit, cross_from) {
+            ConvertValue::Number(n) => ConvertValue::Number(n * factor),
+            ConvertValue::Range(r) => ConvertValue::Range(r.start() * factor..=r.end() * factor),
+        };
+        Ok((value, cross_to, true))
     }
 
     /// Perform a conversion
@@ -386,9 +523,14 @@ impl Converter {
     ) -> Result<(ConvertValue, Arc<Unit>), ConvertError> {
         let conversions = match &self.best[unit.physical_quantity] {
             BestConversionsStore::Unified(u) => u,
-            BestConversionsStore::BySystem { metric, imperial } => match system {
+            BestConversionsStore::BySystem {
+                metric,
+                imperial,
+                us_customary,
+            } => match system {
                 System::Metric => metric,
                 System::Imperial => imperial,
+                System::UsCustomary => us_customary,
             },
         };
 
@@ -423,7 +565,7 @@ impl Converter {
 
     pub(crate) fn get_unit<'a>(
         &'a self,
-        unit: &'a ConvertUnit,
+        unit: &ConvertUnit<'a>,
     ) -> Result<&'a Arc<Unit>, UnknownUnit> {
         let unit = match unit {
             ConvertUnit::Unit(u) => u,
@@ -537,7 +679,18 @@ pub enum ConvertTo<'a> {
 pub enum System {
     #[default]
     Metric,
+    /// British imperial units
     Imperial,
+    /// United States customary units
+    ///
+    /// Some, like the cup, are different from the imperial ones. Others, like
+    /// the ounce or the inch, are the same.
+    ///
+    /// In the bundled units, the US customary ones have a `US` prefix, like
+    /// `US cup` or `US tbsp`. The plain names, like `cup` or `tbsp`, are the
+    /// imperial units unless [`ConverterBuilder::with_bare_units_system`]
+    /// says otherwise.
+    UsCustomary,
 }
 
 impl<'a> From<&'a str> for ConvertUnit<'a> {
@@ -584,6 +737,7 @@ impl TryFrom<&Value> for ConvertValue {
     fn try_from(value: &Value) -> Result<Self, Self::Error> {
         let value = match value {
             Value::Number { value: n } => ConvertValue::Number(*n),
+            Value::Fraction { value: f } => ConvertValue::Number(f.to_f64()),
             Value::Range { value: r } => ConvertValue::Range(r.clone()),
             Value::Text { value: t } => return Err(ConvertError::TextValue(t.to_string())),
         };
@@ -700,6 +854,17 @@ impl UnitCount {
     }
 }
 
+/// Result of [`Recipe::convert_with_densities`]
+#[derive(Debug, Default)]
+pub struct RecipeConversion {
+    /// All the errors while converting. These usually are missing units,
+    /// unknown units or text values.
+    pub errors: Vec<ConvertError>,
+    /// Ingredients converted between volume and mass, by index, and the
+    /// density used
+    pub densities: Vec<(usize, Density)>,
+}
+
 impl<D> Recipe<D> {
     /// Convert a [Recipe] to another [System] in place.
     ///
@@ -707,20 +872,47 @@ impl<D> Recipe<D> {
     ///
     /// Returns all the errors while converting. These usually are missing units,
     /// unknown units or text values.
+    ///
+    /// Ingredients with a known [`Density`] are converted like in
+    /// [`Self::convert_with_densities`].
     pub fn convert(&mut self, to: System, converter: &Converter) -> Vec<ConvertError> {
+        self.convert_with_densities(to, converter).errors
+    }
+
+    /// Same as [`Self::convert`], but also returns the densities used
+    ///
+    /// If the converter has a preferred physical quantity for ingredients with
+    /// a known [`Density`] (see [`DensityFile::prefer`]), their volumes or
+    /// masses are converted to it.
+    pub fn convert_with_densities(
+        &mut self,
+        to: System,
+        converter: &Converter,
+    ) -> RecipeConversion {
         let mut errors = Vec::new();
+        let mut densities = Vec::new();
+
+        for (index, igr) in self.ingredients.iter_mut().enumerate() {
+            let Some(q) = &mut igr.quantity else {
+                continue;
+            };
+            let density = converter.density(&igr.name);
+            match converter.convert_(q, to.into(), density, converter.densities.prefer) {
+                Ok((cq, used)) => {
+                    *q = cq;
+                    if let Some(density) = density.filter(|_| used) {
+                        densities.push((index, density.clone()));
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
 
         let mut conv = |q: &mut Quantity| match converter.convert(q, to) {
             Ok(cq) => *q = cq,
             Err(e) => errors.push(e),
         };
 
-        for igr in &mut self.ingredients {
-            if let Some(q) = &mut igr.quantity {
-                conv(q);
-            }
-        }
-
         // cookware can't have units
 
         for timer in &mut self.timers {
@@ -733,6 +925,6 @@ impl<D> Recipe<D> {
             conv(q);
         }
 
-        errors
+        RecipeConversion { errors, densities }
     }
 }
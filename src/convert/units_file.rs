@@ -3,7 +3,11 @@
 use enum_map::EnumMap;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::Arc,
+};
 
 use super::{PhysicalQuantity, System};
 
@@ -134,6 +138,7 @@ pub struct QuantityGroup {
     ///
     /// This is optional by at least one [`QuantityGroup`] of the quantity in
     /// any [`UnitsFile`] in a converter has to define it and not be empty.
+    /// Unless there are no units of the quantity at all.
     ///
     /// **This will always replace the configuration from [`UnitsFile`] before**
     #[serde(default)]
@@ -157,9 +162,15 @@ pub enum BestUnits {
     /// List without system information
     Unified(Vec<String>),
     /// A list per system
+    ///
+    /// US customary is optional, when empty the imperial units are used.
+    /// Its list can include imperial units, as many are the same in both
+    /// systems.
     BySystem {
         metric: Vec<String>,
         imperial: Vec<String>,
+        #[serde(default)]
+        us_customary: Vec<String>,
     },
 }
 
@@ -181,6 +192,8 @@ pub enum Units {
         #[serde(default)]
         imperial: Vec<UnitEntry>,
         #[serde(default)]
+        us_customary: Vec<UnitEntry>,
+        #[serde(default)]
         unspecified: Vec<UnitEntry>,
     },
 }
@@ -231,6 +244,50 @@ pub struct UnitEntry {
     pub expand_si: bool,
 }
 
+/// Configuration struct for ingredient densities used in
+/// [`ConverterBuilder`](super::ConverterBuilder)
+///
+/// Densities allow converting between volume and mass when the ingredient is
+/// known. Like [`UnitsFile`], this is designed for deserializing
+/// [TOML](https://toml.io/en/):
+///
+/// ```toml
+/// # convert volumes of known ingredients to mass in `Recipe::convert`
+/// prefer = "mass"
+///
+/// [ingredient.flour]
+/// aliases = ["all purpose flour"]
+/// density = 0.53
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DensityFile {
+    /// Physical quantity the ingredients with a known density are converted
+    /// to in [`Recipe::convert`](crate::Recipe::convert)
+    ///
+    /// It has to be [`PhysicalQuantity::Volume`] or [`PhysicalQuantity::Mass`].
+    /// When not set, recipe conversions keep the physical quantity.
+    #[serde(default)]
+    pub prefer: Option<PhysicalQuantity>,
+    /// Densities by ingredient name
+    ///
+    /// Names and aliases are case insensitive. Entries in later files
+    /// override the ones before.
+    #[serde(default)]
+    pub ingredient: BTreeMap<String, DensityEntry>,
+}
+
+/// Density of an ingredient in a [`DensityFile`]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DensityEntry {
+    /// Other names of the ingredient
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Grams per millilitre
+    pub density: f64,
+}
+
 #[cfg(feature = "bundled_units")]
 impl UnitsFile {
     /// Get the bundled units file
@@ -0,0 +1,229 @@
+//! Lossless Concrete Syntax Tree representation of a cooklang recipe
+//!
+//! The [`Cst`] is generated by [`parse_cst`](crate::parser::parse_cst). Unlike
+//! the [`Ast`], every token of the input is in the tree, including comments,
+//! whitespace and line breaks (the *trivia*). Concatenating the text of all the
+//! tokens gives back the exact input. This makes it useful for tools that need
+//! to edit a recipe without destroying the author's layout.
+//!
+//! The tree is shallow:
+//! - The [`NodeKind::Recipe`] root contains the lines: [`NodeKind::Metadata`],
+//!   [`NodeKind::Section`] and [`NodeKind::Step`] nodes. Blank lines,
+//!   comment only lines and line breaks are tokens of the root.
+//! - A [`NodeKind::Step`] contains [`NodeKind::Text`] and component nodes. The
+//!   leading and trailing trivia of every text run, the text step marker and
+//!   the line breaks of a multiline step are tokens of the step.
+//! - Everything else are tokens.
+//!
+//! ```
+//! # use cooklang::{parser::parse_cst, cst::NodeKind, Extensions};
+//! let input = "-- a comment\nAdd @salt. [- another -]\n";
+//! let cst = parse_cst(input, Extensions::all()).into_output().unwrap();
+//! assert_eq!(cst.to_string(), input);
+//!
+//! let step = cst.root().nodes().next().unwrap();
+//! assert_eq!(step.kind(), NodeKind::Step);
+//! assert_eq!(step.comments().count(), 1);
+//!
+//! let ast = cst.to_ast().into_output().unwrap();
+//! assert_eq!(ast.lines.len(), 1);
+//! ```
+//!
+//! [`Ast`]: crate::ast::Ast
+
+use serde::Serialize;
+
+use crate::{
+    ast::Ast,
+    error::PassResult,
+    lexer::T,
+    parser::{ParserError, ParserWarning},
+    span::Span,
+    Extensions,
+};
+
+pub use crate::lexer::TokenKind;
+pub use crate::parser::token_stream::Token;
+
+/// Lossless concrete syntax tree of a cooklang file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cst<'input> {
+    #[serde(skip)]
+    pub(crate) input: &'input str,
+    pub(crate) extensions: Extensions,
+    pub(crate) root: Node,
+}
+
+/// A node of the [`Cst`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Node {
+    pub(crate) kind: NodeKind,
+    pub(crate) span: Span,
+    pub(crate) children: Vec<Element>,
+}
+
+/// What a [`Node`] represents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum NodeKind {
+    /// The whole input
+    Recipe,
+    /// Metadata entry line
+    Metadata,
+    /// Section divider line
+    Section,
+    /// Recipe step. May be more than one line with
+    /// [`MULTILINE_STEPS`](crate::Extensions::MULTILINE_STEPS).
+    Step,
+    /// Text inside a step
+    Text,
+    /// Ingredient component
+    Ingredient,
+    /// Cookware component
+    Cookware,
+    /// Timer component
+    Timer,
+}
+
+/// Child of a [`Node`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Element {
+    /// A nested node, like a component inside a step
+    Node(Node),
+    /// A single token, including trivia
+    Token(Token),
+}
+
+impl<'input> Cst<'input> {
+    /// Input the tree was built from
+    pub fn input(&self) -> &'input str {
+        self.input
+    }
+
+    /// Extensions used to build the tree
+    pub fn extensions(&self) -> Extensions {
+        self.extensions
+    }
+
+    /// Root node, of kind [`NodeKind::Recipe`]
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Gets the text of a node, token or any span of the input
+    pub fn text(&self, span: Span) -> &'input str {
+        &self.input[span.range()]
+    }
+
+    /// Converts the tree into an [`Ast`]
+    ///
+    /// The result is the same as parsing the input with
+    /// [`parse`](crate::parser::parse).
+    pub fn to_ast(&self) -> PassResult<Ast<'input>, ParserError, ParserWarning> {
+        let tokens = self.root.tokens().copied();
+        let parser =
+            crate::parser::Parser::new_from_token_iter(self.input, self.extensions, tokens);
+        crate::parser::parse_lines(parser, |_, _| {})
+    }
+}
+
+impl std::fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.root
+            .tokens()
+            .try_for_each(|t| f.write_str(self.text(t.span)))
+    }
+}
+
+impl Node {
+    pub(crate) fn new(kind: NodeKind, children: Vec<Element>) -> Self {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => Span::new(first.span().start(), last.span().end()),
+            _ => Span::pos(0),
+        };
+        Self {
+            kind,
+            span,
+            children,
+        }
+    }
+
+    /// What the node represents
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// Location of the node in the input
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Direct children
+    pub fn children(&self) -> &[Element] {
+        &self.children
+    }
+
+    /// Direct children that are nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(Element::as_node)
+    }
+
+    /// All the tokens of the node and its descendants, in order
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &Token> + '_> {
+        Box::new(self.children.iter().flat_map(|e| match e {
+            Element::Node(n) => n.tokens(),
+            Element::Token(t) => Box::new(std::iter::once(t)),
+        }))
+    }
+
+    /// Trivia tokens before the first non trivia child
+    pub fn leading_trivia(&self) -> impl Iterator<Item = &Token> {
+        self.children
+            .iter()
+            .map_while(|e| e.as_token().filter(|t| t.kind.is_trivia()))
+    }
+
+    /// Trivia tokens after the last non trivia child
+    ///
+    /// Empty if all the children are trivia, they are only
+    /// [leading](Self::leading_trivia).
+    pub fn trailing_trivia(&self) -> impl Iterator<Item = &Token> {
+        let start = self
+            .children
+            .iter()
+            .rposition(|e| !e.as_token().is_some_and(|t| t.kind.is_trivia()))
+            .map_or(self.children.len(), |p| p + 1);
+        self.children[start..].iter().filter_map(Element::as_token)
+    }
+
+    /// All the comment tokens of the node and its descendants
+    pub fn comments(&self) -> impl Iterator<Item = &Token> {
+        self.tokens()
+            .filter(|t| matches!(t.kind, T![line comment] | T![block comment]))
+    }
+}
+
+impl Element {
+    /// Location in the input
+    pub fn span(&self) -> Span {
+        match self {
+            Element::Node(n) => n.span,
+            Element::Token(t) => t.span,
+        }
+    }
+
+    /// Gets the node if this is one
+    pub fn as_node(&self) -> Option<&Node> {
+        match self {
+            Element::Node(n) => Some(n),
+            Element::Token(_) => None,
+        }
+    }
+
+    /// Gets the token if this is one
+    pub fn as_token(&self) -> Option<&Token> {
+        match self {
+            Element::Node(_) => None,
+            Element::Token(t) => Some(t),
+        }
+    }
+}
@@ -363,7 +363,7 @@ pub trait RichError: std::error::Error {
     fn code(&self) -> Option<&'static str> {
         None
     }
-    fn kind(&self) -> ariadne::ReportKind {
+    fn kind(&self) -> ariadne::ReportKind<'_> {
         ariadne::ReportKind::Error
     }
 }
@@ -569,7 +569,7 @@ impl RichError for CooklangWarning {
         }
     }
 
-    fn kind(&self) -> ariadne::ReportKind {
+    fn kind(&self) -> ariadne::ReportKind<'_> {
         ariadne::ReportKind::Warning
     }
 }
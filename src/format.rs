@@ -0,0 +1,509 @@
+//! Canonical formatter for cooklang source
+//!
+//! The formatter works on the lossless [`Cst`], so comments are kept. The
+//! output is deterministic and formatting it again gives the same result.
+//!
+//! The rules are:
+//! - Metadata entries go first, in the same order. The special `[key]`
+//!   entries stay where they are because they depend on their position.
+//! - Metadata is written as `>> key: value`.
+//! - Sections are written as `= Name`.
+//! - Lines are separated by a blank line, except consecutive metadata entries
+//!   and a section and its first step. The line breaks inside a step (with
+//!   [`MULTILINE_STEPS`](crate::Extensions::MULTILINE_STEPS)) are kept.
+//! - Comment lines stay attached to the line below them if there was no blank
+//!   line in between.
+//! - Quantities always use `%` to separate the unit, `{1 kg}` is written as
+//!   `{1%kg}`.
+//! - Whitespace inside components is removed and the braces of components
+//!   with a single word name and no quantity are removed.
+//! - Trailing whitespace is removed and the output ends with a line break.
+//!   The indentation of the first line of a step is kept, because it is part
+//!   of the step text.
+//!
+//! Components with comments inside are left as they are.
+//!
+//! ```
+//! # use cooklang::{format, Extensions};
+//! let input = "Add @salt{ }  and @ olive oil { 1 % tbsp }.\n== Dressing ==\n>> source: me";
+//! let formatted = format::format(input, Extensions::all()).into_output().unwrap();
+//! assert_eq!(
+//!     formatted,
+//!     ">> source: me\n\nAdd @salt  and @olive oil{1%tbsp}.\n\n= Dressing\n"
+//! );
+//! assert!(format::check(&formatted, Extensions::all()).into_output().unwrap());
+//! ```
+
+use crate::{
+    ast,
+    cst::{Cst, Element, Node, NodeKind, Token},
+    error::PassResult,
+    lexer::T,
+    located::Located,
+    parser::{parse_cst, ParserError, ParserWarning},
+    Extensions,
+};
+
+/// Formats a recipe
+///
+/// If the input has errors, it is not formatted and there is no output.
+pub fn format(
+    input: &str,
+    extensions: Extensions,
+) -> PassResult<String, ParserError, ParserWarning> {
+    let (cst, warnings, errors) = parse_cst(input, extensions).into_tuple();
+    let output = match cst {
+        Some(cst) if errors.is_empty() => Some(Formatter::new(&cst).format()),
+        _ => None,
+    };
+    PassResult::new(output, warnings, errors)
+}
+
+/// Checks if a recipe is already formatted
+///
+/// If the input has errors, there is no output.
+pub fn check(input: &str, extensions: Extensions) -> PassResult<bool, ParserError, ParserWarning> {
+    format(input, extensions).map(|formatted| formatted == input)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Metadata,
+    SpecialMetadata,
+    Section,
+    Step,
+    Comment,
+}
+
+struct Block {
+    kind: BlockKind,
+    text: String,
+    /// The next block was in the next line, without a blank line in between
+    glued: bool,
+}
+
+struct Formatter<'c, 'i> {
+    cst: &'c Cst<'i>,
+    ast: ast::Ast<'i>,
+}
+
+impl<'c, 'i> Formatter<'c, 'i> {
+    fn new(cst: &'c Cst<'i>) -> Self {
+        let ast = cst
+            .to_ast()
+            .into_output()
+            .expect("parser always generates an ast");
+        Self { cst, ast }
+    }
+
+    fn raw(&self, token: &Token) -> &'i str {
+        self.cst.text(token.span)
+    }
+
+    fn format(&self) -> String {
+        let blocks = self.blocks();
+
+        // metadata first, with the comments attached to them
+        let mut metadata = Vec::new();
+        let mut rest = Vec::new();
+        let mut blocks = blocks.into_iter().peekable();
+        while let Some(block) = blocks.next() {
+            let attached_to_meta = block.kind == BlockKind::Comment
+                && block.glued
+                && blocks
+                    .peek()
+                    .is_some_and(|next| next.kind == BlockKind::Metadata);
+            if block.kind == BlockKind::Metadata || attached_to_meta {
+                metadata.push(block);
+            } else {
+                rest.push(block);
+            }
+        }
+
+        let mut out = String::new();
+        let mut prev: Option<&Block> = None;
+        for block in metadata.iter().chain(&rest) {
+            if let Some(prev) = prev {
+                let is_meta = |k| matches!(k, BlockKind::Metadata | BlockKind::SpecialMetadata);
+                let same_line = (prev.kind == BlockKind::Comment && prev.glued)
+                    || (is_meta(prev.kind) && is_meta(block.kind))
+                    || (prev.kind == BlockKind::Section && block.kind == BlockKind::Step);
+                out.push_str(if same_line { "\n" } else { "\n\n" });
+            }
+            out.push_str(&block.text);
+            prev = Some(block);
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Splits the root in blocks, one per line, merging consecutive comment lines
+    fn blocks(&self) -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut ast_lines = self.ast.lines.iter();
+        let mut comment_line = String::new();
+        let mut pending_ws = "";
+        let mut line_empty = true;
+
+        for element in self.cst.root().children() {
+            match element {
+                Element::Node(node) => {
+                    let line = ast_lines.next().expect("ast line for cst node");
+                    let block = self.line(node, line);
+                    blocks.push(block);
+                    line_empty = false;
+                }
+                Element::Token(t) => match t.kind {
+                    T![newline] | T![eof] => {
+                        if !comment_line.is_empty() {
+                            match blocks.last_mut() {
+                                Some(last) if last.kind == BlockKind::Comment && last.glued => {
+                                    last.text.push('\n');
+                                    last.text.push_str(&comment_line);
+                                }
+                                _ => blocks.push(Block {
+                                    kind: BlockKind::Comment,
+                                    text: std::mem::take(&mut comment_line),
+                                    glued: true,
+                                }),
+                            }
+                            comment_line.clear();
+                        } else if line_empty {
+                            if let Some(last) = blocks.last_mut() {
+                                last.glued = false;
+                            }
+                        }
+                        pending_ws = "";
+                        line_empty = true;
+                    }
+                    T![ws] => pending_ws = self.raw(t),
+                    _ => {
+                        if !comment_line.is_empty() {
+                            comment_line.push_str(pending_ws);
+                        }
+                        comment_line.push_str(self.raw(t));
+                        pending_ws = "";
+                    }
+                },
+            }
+        }
+        if !comment_line.is_empty() {
+            blocks.push(Block {
+                kind: BlockKind::Comment,
+                text: comment_line,
+                glued: false,
+            });
+        }
+        if let Some(last) = blocks.last_mut() {
+            last.glued = false;
+        }
+        blocks
+    }
+
+    fn line(&self, node: &Node, line: &ast::Line) -> Block {
+        let (kind, text) = match (node.kind(), line) {
+            (NodeKind::Metadata, _) => self.metadata(node),
+            (NodeKind::Section, ast::Line::Section { name }) => {
+                (BlockKind::Section, self.section(node, name.as_ref()))
+            }
+            (NodeKind::Step, ast::Line::Step { items, .. }) => {
+                (BlockKind::Step, self.step(node, items))
+            }
+            _ => panic!("cst and ast do not match"),
+        };
+        Block {
+            kind,
+            text,
+            glued: true,
+        }
+    }
+
+    fn metadata(&self, node: &Node) -> (BlockKind, String) {
+        let mut tokens = node.tokens().skip_while(|t| t.kind != T![meta]).skip(1);
+        let key: Vec<_> = tokens.by_ref().take_while(|t| t.kind != T![:]).collect();
+        let value: Vec<_> = tokens.collect();
+
+        let mut comments = Vec::new();
+        let key = self.join_trimmed(key, &mut comments);
+        let value = self.join_trimmed(value, &mut comments);
+
+        let kind = if key.starts_with('[') && key.ends_with(']') {
+            BlockKind::SpecialMetadata
+        } else {
+            BlockKind::Metadata
+        };
+        let mut out = format!(">> {key}:");
+        if !value.is_empty() {
+            out.push(' ');
+            out.push_str(&value);
+        }
+        self.push_comments(&mut out, &comments);
+        (kind, out)
+    }
+
+    fn section(&self, node: &Node, name: Option<&ast::Text>) -> String {
+        let mut out = String::from("=");
+        if let Some(name) = name {
+            let span = name.span();
+            let name_tokens = node
+                .tokens()
+                .filter(|t| span.start() <= t.span.start() && t.span.end() <= span.end());
+            let name = self.join_trimmed(name_tokens, &mut Vec::new());
+            if !name.is_empty() {
+                out.push(' ');
+                out.push_str(&name);
+            }
+        }
+        let comments = node.comments().map(|t| self.raw(t)).collect::<Vec<_>>();
+        self.push_comments(&mut out, &comments);
+        out
+    }
+
+    fn step(&self, node: &Node, items: &[ast::Item]) -> String {
+        let mut components = items.iter().filter_map(|i| match i {
+            ast::Item::Component(c) => Some(c.as_ref()),
+            ast::Item::Text(_) => None,
+        });
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        let mut pending_ws = "";
+        let mut after_marker = false;
+
+        let children = node.children();
+        for (index, child) in children.iter().enumerate() {
+            let content = match child {
+                Element::Token(t) => match t.kind {
+                    T![newline] => {
+                        lines.push(std::mem::take(&mut line));
+                        pending_ws = "";
+                        continue;
+                    }
+                    T![ws] => {
+                        if line.is_empty() && lines.is_empty() {
+                            // the indentation of the first line is part of the text
+                            line.push_str(self.raw(t));
+                        } else if !line.is_empty() && !after_marker {
+                            pending_ws = self.raw(t);
+                        }
+                        continue;
+                    }
+                    T![>] if line.is_empty() => {
+                        line.push('>');
+                        after_marker = true;
+                        continue;
+                    }
+                    _ => self.raw(t).to_string(),
+                },
+                Element::Node(n) => match n.kind() {
+                    NodeKind::Ingredient | NodeKind::Cookware | NodeKind::Timer => {
+                        let component = components.next().expect("ast component for cst node");
+                        self.component(n, component, &children[index + 1..])
+                    }
+                    _ => n.tokens().map(|t| self.raw(t)).collect(),
+                },
+            };
+            if after_marker {
+                line.push(' ');
+                after_marker = false;
+            } else if line.is_empty() {
+                // the indentation of the next lines is not part of the text,
+                // without it, this would change the kind of line
+                let first = match child {
+                    Element::Token(t) => Some(t.kind),
+                    Element::Node(n) => n.tokens().next().map(|t| t.kind),
+                };
+                if matches!(first, Some(T![>>] | T![>] | T![=])) {
+                    line.push('\\');
+                }
+            }
+            line.push_str(pending_ws);
+            pending_ws = "";
+            line.push_str(&content);
+        }
+        lines.push(line);
+        lines.join("\n")
+    }
+
+    fn component(
+        &self,
+        node: &Node,
+        component: &Located<ast::Component>,
+        rest: &[Element],
+    ) -> String {
+        let raw = || node.tokens().map(|t| self.raw(t)).collect::<String>();
+        if node.comments().next().is_some() {
+            return raw();
+        }
+        let start = component.span().start();
+        let input = self.cst.input();
+
+        let (name, alias, quantity, note) = match component.value() {
+            ast::Component::Ingredient(i) => (
+                Some(&i.name),
+                i.alias.as_ref(),
+                i.quantity
+                    .as_ref()
+                    .map(|q| self.quantity(&q.value, q.unit.as_ref())),
+                i.note.as_ref(),
+            ),
+            ast::Component::Cookware(c) => (
+                Some(&c.name),
+                c.alias.as_ref(),
+                c.quantity.as_ref().map(|q| self.quantity(q, None)),
+                c.note.as_ref(),
+            ),
+            ast::Component::Timer(t) => (
+                t.name.as_ref(),
+                None,
+                t.quantity
+                    .as_ref()
+                    .map(|q| self.quantity(&q.value, q.unit.as_ref())),
+                None,
+            ),
+        };
+
+        let name_start = name.map_or(start + 1, |n| n.span().start());
+        let mut out = input[start..name_start].to_string();
+        if let Some(name) = name {
+            out.push_str(trim(&input[name.span().range()]));
+        }
+        if let Some(alias) = alias {
+            out.push('|');
+            out.push_str(trim(&input[alias.span().range()]));
+        }
+
+        let single_word = name.is_some_and(|name| {
+            let span = name.span();
+            let tokens = node
+                .tokens()
+                .filter(|t| span.start() <= t.span.start() && t.span.end() <= span.end())
+                .collect::<Vec<_>>();
+            let start = tokens.iter().position(|t| t.kind != T![ws]);
+            let end = tokens.iter().rposition(|t| t.kind != T![ws]);
+            match (start, end) {
+                (Some(start), Some(end)) => tokens[start..=end]
+                    .iter()
+                    .all(|t| matches!(t.kind, T![word] | T![int] | T![float])),
+                _ => false,
+            }
+        });
+        let body_end = note.map_or(component.span().end(), |n| n.span().start());
+        let had_braces = node
+            .tokens()
+            .any(|t| t.kind == T!['{'] && t.span.end() <= body_end);
+        let next_is_safe = match rest.first() {
+            None => true,
+            Some(Element::Token(t)) => matches!(
+                t.kind,
+                T![ws] | T![newline] | T![line comment] | T![block comment] | T![punctuation]
+            ),
+            Some(Element::Node(n)) => n
+                .tokens()
+                .next()
+                .is_none_or(|t| matches!(t.kind, T![punctuation])),
+        };
+        // Without braces, a name extends to the next '{' in the line, unless
+        // there's another component before it.
+        let brace_follows = rest
+            .iter()
+            .flat_map(|e| match e {
+                Element::Node(n) => n.tokens(),
+                Element::Token(t) => Box::new(std::iter::once(t)),
+            })
+            .take_while(|t| t.kind != T![newline])
+            .find(|t| matches!(t.kind, T!['{'] | T![@] | T![#] | T![~]))
+            .is_some_and(|t| t.kind == T!['{']);
+        let braces = had_braces
+            && (quantity.is_some()
+                || alias.is_some()
+                || !single_word
+                || !next_is_safe
+                || brace_follows);
+        if braces {
+            out.push('{');
+            if let Some(quantity) = quantity {
+                out.push_str(&quantity);
+            }
+            out.push('}');
+        }
+
+        if let Some(note) = note {
+            out.push('(');
+            out.push_str(trim(&input[note.span().range()]));
+            out.push(')');
+        }
+        out
+    }
+
+    fn quantity(&self, value: &ast::QuantityValue, unit: Option<&ast::Text>) -> String {
+        let input = self.cst.input();
+        let mut out = match value {
+            ast::QuantityValue::Single { value, auto_scale } => {
+                let mut s = trim(&input[value.span().range()]).to_string();
+                if auto_scale.is_some() {
+                    s.push('*');
+                }
+                s
+            }
+            ast::QuantityValue::Many(values) => values
+                .iter()
+                .map(|v| trim(&input[v.span().range()]))
+                .collect::<Vec<_>>()
+                .join("|"),
+        };
+        if let Some(unit) = unit {
+            out.push('%');
+            out.push_str(trim(&input[unit.span().range()]));
+        }
+        out
+    }
+
+    /// Joins the text of the tokens, without the whitespace at the start and
+    /// end. Comments are not joined, they are added to `comments`.
+    fn join_trimmed<'t>(
+        &self,
+        tokens: impl IntoIterator<Item = &'t Token>,
+        comments: &mut Vec<&'i str>,
+    ) -> String {
+        let mut out = String::new();
+        let mut pending_ws = "";
+        for t in tokens {
+            match t.kind {
+                T![line comment] | T![block comment] => comments.push(self.raw(t)),
+                T![ws] => {
+                    if !out.is_empty() {
+                        pending_ws = self.raw(t);
+                    }
+                }
+                _ => {
+                    out.push_str(pending_ws);
+                    pending_ws = "";
+                    out.push_str(self.raw(t));
+                }
+            }
+        }
+        out
+    }
+
+    fn push_comments(&self, out: &mut String, comments: &[&str]) {
+        for c in comments {
+            out.push(' ');
+            out.push_str(c);
+        }
+    }
+}
+
+/// Trims whitespace except if it is escaped
+fn trim(s: &str) -> &str {
+    let s = s.trim_start();
+    let trimmed = s.trim_end();
+    if trimmed.ends_with('\\') && trimmed.len() < s.len() {
+        let c = s[trimmed.len()..].chars().next().unwrap();
+        &s[..trimmed.len() + c.len_utf8()]
+    } else {
+        trimmed
+    }
+}
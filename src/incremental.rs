@@ -0,0 +1,167 @@
+//! Incremental parsing for editors
+//!
+//! Parsing a big recipe again on every keystroke is wasteful. An
+//! [`IncrementalParse`] keeps the input split in the lines the
+//! [parser](crate::parser) works with. After a [`TextEdit`], only the lines
+//! touched by it are lexed and parsed again. The rest are reused, with their
+//! locations moved.
+//!
+//! The analysis pass resolves references, modes, step numbers and units
+//! with what the previous lines define, so a line can change the result of
+//! all the lines after it, but never of the ones before. Its state is saved
+//! every few lines, and after an edit it's resumed from the last one before
+//! the first changed line. The result is the same as a full
+//! [`CooklangParser::parse`] of the new input, including the warnings, errors
+//! and [`Span`](crate::Span)s.
+//!
+//! ```
+//! # use cooklang::{CooklangParser, incremental::TextEdit};
+//! let parser = CooklangParser::default();
+//! let mut doc = parser.parse_incremental("Add @salt.\nMix.\n", "Example");
+//! parser.reparse(&mut doc, [TextEdit::new(5..9, "pepper")]);
+//! assert_eq!(doc.input(), "Add @pepper.\nMix.\n");
+//!
+//! let recipe = doc.result().output().unwrap();
+//! assert_eq!(recipe.ingredients[0].name, "pepper");
+//! ```
+
+use std::ops::Range;
+
+use crate::{
+    analysis::{self, Checkpoints},
+    parser::incremental::LineCache,
+    CooklangParser, RecipeResult,
+};
+
+/// A recipe parsed with [`CooklangParser::parse_incremental`]
+#[derive(Debug)]
+pub struct IncrementalParse {
+    input: String,
+    recipe_name: String,
+    cache: LineCache,
+    /// Parser the checkpoints were made with
+    parser: CooklangParser,
+    checkpoints: Checkpoints,
+    result: RecipeResult,
+}
+
+/// Replacement of a range of the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Byte range of the input to replace. Empty to insert.
+    pub range: Range<usize>,
+    /// Text to put in the range. Empty to delete.
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+impl IncrementalParse {
+    /// Current input, with all the edits applied
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Name given to the recipe
+    pub fn recipe_name(&self) -> &str {
+        &self.recipe_name
+    }
+
+    /// Result of parsing the current input
+    pub fn result(&self) -> &RecipeResult {
+        &self.result
+    }
+
+    /// Consumes and gets the result of parsing the current input
+    pub fn into_result(self) -> RecipeResult {
+        self.result
+    }
+}
+
+impl CooklangParser {
+    /// Parse a recipe that will be edited
+    ///
+    /// The result is the same as [`Self::parse`]. Use [`Self::reparse`] to
+    /// update it after the input changes.
+    #[tracing::instrument(level = "debug", skip_all, fields(len = input.len()))]
+    pub fn parse_incremental(&self, input: &str, recipe_name: &str) -> IncrementalParse {
+        let mut cache = LineCache::new(input, self.extensions);
+        let mut checkpoints = Checkpoints::default();
+        let result = self.analyze_cached(input, recipe_name, &mut cache, &mut checkpoints);
+        IncrementalParse {
+            input: input.to_string(),
+            recipe_name: recipe_name.to_string(),
+            cache,
+            parser: self.clone(),
+            checkpoints,
+            result,
+        }
+    }
+
+    /// Applies the edits to an [`IncrementalParse`] and updates the result
+    ///
+    /// The edits are applied in order, so the range of every edit refers to
+    /// the input after the previous ones.
+    ///
+    /// If `parse` was created by a parser with other extensions, the whole
+    /// input is parsed again. With any other configuration change, the whole
+    /// input is analysed again.
+    ///
+    /// The analysis still has to go over every line after the first one
+    /// edited, so editing the start of a recipe costs more than editing the
+    /// end.
+    ///
+    /// # Panics
+    /// If a range is out of bounds or does not lie on [`char`] boundaries,
+    /// like [`String::replace_range`].
+    #[tracing::instrument(level = "debug", skip_all, fields(len = parse.input.len()))]
+    pub fn reparse(&self, parse: &mut IncrementalParse, edits: impl IntoIterator<Item = TextEdit>) {
+        for edit in edits {
+            parse.input.replace_range(edit.range.clone(), &edit.text);
+            parse.cache.edit(&parse.input, edit.range, edit.text.len());
+        }
+        if parse.cache.extensions() != self.extensions {
+            parse.cache = LineCache::new(&parse.input, self.extensions);
+        }
+        if parse.parser != *self {
+            parse.parser = self.clone();
+            parse.checkpoints.clear();
+        }
+        parse.result = self.analyze_cached(
+            &parse.input,
+            &parse.recipe_name,
+            &mut parse.cache,
+            &mut parse.checkpoints,
+        );
+    }
+
+    /// Parses and analyses `input` reusing what is still valid from the last
+    /// time
+    fn analyze_cached(
+        &self,
+        input: &str,
+        recipe_name: &str,
+        cache: &mut LineCache,
+        checkpoints: &mut Checkpoints,
+    ) -> RecipeResult {
+        let ast = cache.parse(input);
+        // here, as the analysis does not run if the ast has errors
+        checkpoints.invalidate(cache.changed_from());
+        self.analyze(ast, recipe_name, |ast| {
+            analysis::parse_ast_from_checkpoint(
+                ast,
+                checkpoints,
+                self.extensions,
+                &self.converter,
+                self.schema.as_deref(),
+            )
+        })
+    }
+}
@@ -0,0 +1,409 @@
+//! Parse free text ingredient lines, like `2 1/2 cups flour, sifted`
+//!
+//! This is useful to import recipes from other formats, where the
+//! ingredients are just text. The line is split in:
+//!
+//! - A value at the start, with the same syntax as the quantities in
+//!   cooklang: numbers, fractions, mixed numbers and ranges. Unicode
+//!   fractions like `½` or `1½` are also understood.
+//! - A unit after the value, if the [`Converter`] knows it and it is a mass,
+//!   volume or count unit. Units with more than one word, like `fl oz`, are
+//!   found too.
+//! - The name.
+//! - The note: the text in parentheses and everything after the first comma.
+//!
+//! The result has the [`Span`] of each part and a confidence score, so a UI
+//! can highlight the parts and ask the user to correct the dubious ones.
+//!
+//! ```
+//! # use cooklang::{ingredient_line, Converter};
+//! let converter = Converter::default();
+//! let line = "2 1/2 cups all-purpose flour, sifted";
+//! let parsed = ingredient_line::parse(line, &converter).unwrap();
+//! assert_eq!(parsed.name, "all-purpose flour");
+//! assert_eq!(parsed.quantity.unwrap().to_string(), "2 1/2 cups");
+//! assert_eq!(parsed.note.as_deref(), Some("sifted"));
+//! assert_eq!(parsed.confidence, 1.0);
+//!
+//! let spans = parsed.spans;
+//! assert_eq!(&line[spans.value.unwrap().range()], "2 1/2");
+//! assert_eq!(&line[spans.unit.unwrap().range()], "cups");
+//! assert_eq!(&line[spans.name.range()], "all-purpose flour");
+//! assert_eq!(&line[spans.notes[0].range()], "sifted");
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert::{Converter, PhysicalQuantity},
+    parser::parse_leading_value,
+    quantity::{format::unicode_fraction, Fraction, Quantity, QuantityValue, UnitInfo, Value},
+    span::Span,
+    Extensions,
+};
+
+/// Max words in a unit, like `fl oz`
+const MAX_UNIT_WORDS: usize = 3;
+
+/// A parsed ingredient line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngredientLine {
+    /// Name of the ingredient
+    pub name: String,
+    /// Quantity, if the line starts with a value
+    ///
+    /// The value is [`QuantityValue::Linear`], because the amounts of a
+    /// recipe usually scale.
+    pub quantity: Option<Quantity>,
+    /// Note, the parts are joined with `, `
+    pub note: Option<String>,
+    /// How sure the parser is about the result, from 0 to 1
+    ///
+    /// It starts at 1 and goes down when:
+    /// - There is no value.
+    /// - There is a value but no known unit and the name has more than one
+    ///   word, so the first one may be an unknown unit.
+    /// - The name has numbers.
+    /// - The name is long, so it may be a sentence.
+    /// - The parentheses are not balanced.
+    pub confidence: f32,
+    /// Location of each part in the line
+    pub spans: IngredientLineSpans,
+}
+
+/// Location of the parts of an [`IngredientLine`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngredientLineSpans {
+    /// Value of the quantity
+    pub value: Option<Span>,
+    /// Unit of the quantity
+    pub unit: Option<Span>,
+    /// From the first to the last character of the name
+    ///
+    /// If there is a note in parentheses in the middle of the name, it's
+    /// inside this span.
+    pub name: Span,
+    /// Each part of the note
+    pub notes: Vec<Span>,
+}
+
+/// Parses an ingredient line
+///
+/// Returns [`None`] if the line has no name, like an empty line or just a
+/// quantity.
+pub fn parse(line: &str, converter: &Converter) -> Option<IngredientLine> {
+    let mut confidence = 1.0;
+    let mut pos = line.len() - line.trim_start().len();
+
+    let value = leading_value(line, pos);
+    let mut unit = None;
+    if let Some((_, span)) = &value {
+        pos = skip_whitespace(line, span.end());
+        unit = leading_unit(line, pos, converter);
+        if let Some((_, span)) = &unit {
+            pos = skip_whitespace(line, span.end());
+        }
+        if line[pos..].starts_with("of ") {
+            pos = skip_whitespace(line, pos + 2);
+        }
+    } else {
+        confidence -= 0.3;
+    }
+
+    let parts = split_name(line, pos);
+    let name = collapse_whitespace(&parts.name);
+    if name.is_empty() {
+        return None;
+    }
+    let notes = parts
+        .notes
+        .iter()
+        .map(|span| collapse_whitespace(&line[span.range()]))
+        .collect::<Vec<_>>();
+    let note = notes.join(", ");
+
+    let words = name.split(' ').count();
+    if value.is_some() && unit.is_none() && words > 1 {
+        confidence -= 0.15;
+    }
+    if name.chars().any(|c| c.is_ascii_digit()) {
+        confidence -= 0.3;
+    }
+    if words > 5 {
+        confidence -= 0.2;
+    }
+    if parts.unbalanced {
+        confidence -= 0.3;
+    }
+
+    let spans = IngredientLineSpans {
+        value: value.as_ref().map(|(_, span)| *span),
+        unit: unit.as_ref().map(|(_, span)| *span),
+        name: parts.name_span,
+        notes: parts.notes,
+    };
+    let quantity = value.map(|(value, _)| {
+        Quantity::new_and_parse(
+            QuantityValue::Linear { value },
+            unit.map(|(unit, _)| unit),
+            converter,
+        )
+    });
+
+    Some(IngredientLine {
+        name,
+        quantity,
+        note: (!note.is_empty()).then_some(note),
+        confidence: f32::max(confidence, 0.0),
+        spans,
+    })
+}
+
+/// Value at `pos`, with unicode fractions
+fn leading_value(line: &str, pos: usize) -> Option<(Value, Span)> {
+    let rest = &line[pos..];
+    let Some((value, end)) = parse_leading_value(rest, Extensions::all()) else {
+        let c = rest.chars().next()?;
+        let value = vulgar_fraction(c)?;
+        let span = Span::new(pos, pos + c.len_utf8());
+        return Some((Value::Fraction { value }, span));
+    };
+
+    // a whole number followed by a unicode fraction, like `1½` or `1 ½`
+    let after = skip_whitespace(line, pos + end);
+    if let (Value::Number { value: n }, Some(c)) = (&value, line[after..].chars().next()) {
+        let mixed = vulgar_fraction(c).and_then(|f| {
+            if n.fract() != 0.0 || *n < 0.0 || *n > u32::MAX as f64 {
+                return None;
+            }
+            Fraction::new(*n as u32, 1)?.checked_add(&f)
+        });
+        if let Some(value) = mixed {
+            let span = Span::new(pos, after + c.len_utf8());
+            return Some((Value::Fraction { value }, span));
+        }
+    }
+    Some((value, Span::new(pos, pos + end)))
+}
+
+fn vulgar_fraction(c: char) -> Option<Fraction> {
+    (2..=10)
+        .flat_map(|den| (1..den).map(move |num| (num, den)))
+        .find(|&(num, den)| unicode_fraction(num, den) == Some(c))
+        .and_then(|(num, den)| Fraction::new(num, den))
+}
+
+/// Longest known unit of up to [`MAX_UNIT_WORDS`] words at `pos`
+///
+/// Only units for amounts of an ingredient are taken, so `1 m onion` is not
+/// one metre of onion.
+fn leading_unit(line: &str, pos: usize, converter: &Converter) -> Option<(String, Span)> {
+    let mut words = Vec::with_capacity(MAX_UNIT_WORDS);
+    let mut start = pos;
+    for _ in 0..MAX_UNIT_WORDS {
+        let len = line[start..]
+            .find(|c: char| c.is_whitespace() || c == ',' || c == '(')
+            .unwrap_or(line.len() - start);
+        if len == 0 {
+            break;
+        }
+        words.push(start + len);
+        start = skip_whitespace(line, start + len);
+    }
+
+    words.into_iter().rev().find_map(|end| {
+        let text = collapse_whitespace(&line[pos..end]);
+        let trimmed = text.trim_end_matches('.');
+        let candidates = [
+            trimmed.to_string(),
+            text.clone(),
+            trimmed.to_lowercase(),
+            text.to_lowercase(),
+        ];
+        candidates
+            .into_iter()
+            .filter(|c| !c.is_empty())
+            .find(|c| {
+                matches!(
+                    UnitInfo::new(c, converter),
+                    UnitInfo::Known(u) if matches!(
+                        u.physical_quantity,
+                        PhysicalQuantity::Mass | PhysicalQuantity::Volume | PhysicalQuantity::Count
+                    )
+                )
+            })
+            .map(|unit| (unit, Span::new(pos, end)))
+    })
+}
+
+struct NameParts {
+    name: String,
+    name_span: Span,
+    notes: Vec<Span>,
+    unbalanced: bool,
+}
+
+/// Splits the rest of the line, from `pos`, in the name and the notes
+///
+/// The notes are the text in parentheses and everything after the first
+/// comma outside them.
+fn split_name(line: &str, pos: usize) -> NameParts {
+    let mut name = String::new();
+    let mut name_range: Option<(usize, usize)> = None;
+    let mut notes = Vec::new();
+    let mut unbalanced = false;
+    let mut depth = 0;
+    let mut note_start = 0;
+
+    for (i, c) in line[pos..].char_indices() {
+        let i = pos + i;
+        match c {
+            '(' => {
+                if depth == 0 {
+                    note_start = i + 1;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    notes.extend(trimmed_span(line, note_start, i));
+                }
+            }
+            ',' if depth == 0 => {
+                notes.extend(trimmed_span(line, i + 1, line.len()));
+                unbalanced |= line[i..].matches('(').count() != line[i..].matches(')').count();
+                return NameParts {
+                    name,
+                    name_span: name_span(name_range, pos),
+                    notes,
+                    unbalanced,
+                };
+            }
+            _ if depth == 0 => {
+                unbalanced |= c == ')';
+                name.push(c);
+                if !c.is_whitespace() {
+                    let start = name_range.map_or(i, |(start, _)| start);
+                    name_range = Some((start, i + c.len_utf8()));
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        unbalanced = true;
+        notes.extend(trimmed_span(line, note_start, line.len()));
+    }
+    NameParts {
+        name,
+        name_span: name_span(name_range, pos),
+        notes,
+        unbalanced,
+    }
+}
+
+fn name_span(range: Option<(usize, usize)>, pos: usize) -> Span {
+    range.map_or(Span::pos(pos), |(start, end)| Span::new(start, end))
+}
+
+/// Span of `line[start..end]` without the whitespace around, if not empty
+fn trimmed_span(line: &str, start: usize, end: usize) -> Option<Span> {
+    let s = &line[start..end];
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let start = start + (s.len() - s.trim_start().len());
+    Some(Span::new(start, start + trimmed.len()))
+}
+
+fn skip_whitespace(line: &str, pos: usize) -> usize {
+    line.len() - line[pos..].trim_start().len()
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn parse_str(line: &str) -> (String, Option<String>, Option<String>) {
+        let parsed = parse(line, &Converter::default()).unwrap();
+        (
+            parsed.name,
+            parsed.quantity.map(|q| q.to_string()),
+            parsed.note,
+        )
+    }
+
+    fn owned(
+        (name, quantity, note): (&str, Option<&str>, Option<&str>),
+    ) -> (String, Option<String>, Option<String>) {
+        (
+            name.to_string(),
+            quantity.map(str::to_string),
+            note.map(str::to_string),
+        )
+    }
+
+    #[test_case("2 1/2 cups all-purpose flour, sifted" => owned(("all-purpose flour", Some("2 1/2 cups"), Some("sifted"))); "mixed number and note")]
+    #[test_case("3 large eggs" => owned(("large eggs", Some("3"), None)); "no unit")]
+    #[test_case("1-2 tsp of salt" => owned(("salt", Some("1-2 tsp"), None)); "range and of")]
+    #[test_case("200 g butter (cold), cubed" => owned(("butter", Some("200 g"), Some("cold, cubed"))); "parentheses")]
+    #[test_case("1 tbsp. honey" => owned(("honey", Some("1 tbsp"), None)); "unit with dot")]
+    #[test_case("Salt to taste" => owned(("Salt to taste", None, None)); "no quantity")]
+    #[test_case("1/2 lemon" => owned(("lemon", Some("1/2"), None)); "fraction")]
+    #[test_case("8 fl oz milk" => owned(("milk", Some("8 fl oz"), None)); "multi word unit")]
+    #[test_case("2 Cups sugar" => owned(("sugar", Some("2 cups"), None)); "uppercase unit")]
+    #[test_case("½ cup water" => owned(("water", Some("1/2 cup"), None)); "unicode fraction")]
+    #[test_case("1½ cups rice" => owned(("rice", Some("1 1/2 cups"), None)); "unicode mixed number")]
+    #[test_case("2 ¼ kg potatoes" => owned(("potatoes", Some("2 1/4 kg"), None)); "unicode mixed number with space")]
+    #[test_case("1 (14 oz) can tomatoes" => owned(("can tomatoes", Some("1"), Some("14 oz"))); "note in the middle")]
+    #[test_case("flour, sifted (optional)" => owned(("flour", None, Some("sifted (optional)"))); "parentheses after comma")]
+    #[test_case("1 C sugar" => owned(("sugar", Some("1 c"), None)); "cup not celsius")]
+    #[test_case("1 m onion" => owned(("m onion", Some("1"), None)); "length unit")]
+    #[test_case("2 pcs garlic" => owned(("garlic", Some("2 pcs"), None)); "count unit")]
+    fn ingredient_line(line: &str) -> (String, Option<String>, Option<String>) {
+        parse_str(line)
+    }
+
+    #[test]
+    fn spans() {
+        let line = "  1 ½ fl. oz. cream (heavy) , whipped ";
+        let parsed = parse(line, &Converter::default()).unwrap();
+        let spans = parsed.spans;
+        assert_eq!(&line[spans.value.unwrap().range()], "1 ½");
+        assert_eq!(&line[spans.unit.unwrap().range()], "fl. oz.");
+        assert_eq!(&line[spans.name.range()], "cream");
+        let notes = spans
+            .notes
+            .iter()
+            .map(|s| &line[s.range()])
+            .collect::<Vec<_>>();
+        assert_eq!(notes, ["heavy", "whipped"]);
+        assert_eq!(parsed.note.as_deref(), Some("heavy, whipped"));
+    }
+
+    #[test_case("250 g flour" => 1.0; "certain")]
+    #[test_case("flour" => 0.7; "no value")]
+    #[test_case("2 large eggs" => 0.85; "maybe unknown unit")]
+    #[test_case("1 can (400 g) tomatoes 2" => 0.55; "numbers in name")]
+    #[test_case("1 kg flour (sifted" => 0.7; "unbalanced")]
+    #[test_case("1 m onion" => 0.85; "length unit")]
+    fn confidence(line: &str) -> f32 {
+        let confidence = parse(line, &Converter::default()).unwrap().confidence;
+        (confidence * 100.0).round() / 100.0
+    }
+
+    #[test]
+    fn empty() {
+        assert!(parse("", &Converter::default()).is_none());
+        assert!(parse("   ", &Converter::default()).is_none());
+        assert!(parse("(optional)", &Converter::default()).is_none());
+        assert!(parse("2 g", &Converter::default()).is_none());
+    }
+}
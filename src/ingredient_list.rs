@@ -3,10 +3,16 @@
 use std::collections::BTreeMap;
 
 use serde::Serialize;
+use thiserror::Error;
 
 use crate::{
-    aisle::AisleConf, convert::Converter, model::Ingredient, quantity::GroupedQuantity,
-    scale::ScaleOutcome, ScaledRecipe,
+    aisle::AisleConf,
+    convert::Converter,
+    model::{Ingredient, Recipe},
+    quantity::{GroupedQuantity, TotalQuantity, Value},
+    resolve::RecipeGraph,
+    scale::{target_factor, ScaleError, ScaleOutcome, ScaleRule, ScaleTarget},
+    ScaledRecipe,
 };
 
 /// Ingredient with all quantities from it's references and itself grouped
@@ -101,7 +107,38 @@ impl ScaledRecipe {
 ///
 /// Sorted by name.
 #[derive(Debug, Default)]
-pub struct IngredientList(BTreeMap<String, GroupedQuantity>);
+pub struct IngredientList {
+    ingredients: BTreeMap<String, GroupedQuantity>,
+    /// Sub-recipes each ingredient came from, see [`IngredientList::origins`]
+    origins: BTreeMap<String, Vec<String>>,
+    warnings: Vec<IngredientListWarning>,
+}
+
+/// Problem found adding a recipe to an [`IngredientList`]
+///
+/// The ingredients are still added, each variant says how.
+#[derive(Debug, Clone, Error)]
+pub enum IngredientListWarning {
+    /// The ingredient had an error when the recipe was scaled. It's added
+    /// with the quantity it has in the recipe.
+    #[error("Error scaling ingredient '{ingredient}': {source}")]
+    IngredientNotScaled {
+        ingredient: String,
+        source: ScaleError,
+    },
+
+    /// The referenced recipe can't be scaled to the quantity used. The
+    /// reference is added as a regular ingredient.
+    #[error("Can't scale recipe '{recipe}' to the quantity used, not expanding it")]
+    RecipeNotExpanded {
+        recipe: String,
+        /// Ingredient of the recipe that could not be scaled, if that was the
+        /// problem, and not the quantity of the reference
+        ingredient: Option<String>,
+        #[source]
+        source: Option<ScaleError>,
+    },
+}
 
 impl IngredientList {
     /// Empty list
@@ -124,8 +161,7 @@ impl IngredientList {
     /// Only ingredients for which [should_be_listed](crate::ast::Modifiers::should_be_listed)
     /// is true are added.
     ///
-    /// Scaling outcomes are ignored, but logged with [tracing] if they are an
-    /// error.
+    /// Scaling errors are added to the [`warnings`](IngredientList::warnings).
     ///
     /// Ingredients are listed based on their [display_name](crate::model::Ingredient::display_name).
     pub fn add_recipe(&mut self, recipe: &ScaledRecipe, converter: &Converter) {
@@ -141,14 +177,154 @@ impl IngredientList {
                 continue;
             }
 
-            if let Some(ScaleOutcome::Error(err)) = outcome {
-                tracing::error!("Error scaling ingredient: {err}");
+            if let Some(ScaleOutcome::Error(source)) = outcome {
+                self.warnings
+                    .push(IngredientListWarning::IngredientNotScaled {
+                        ingredient: ingredient.name.clone(),
+                        source,
+                    });
             }
 
             self.add_ingredient(ingredient.display_name().into_owned(), &quantity, converter);
         }
     }
 
+    /// Same as [`IngredientList::add_recipe`] but the ingredients that
+    /// reference other recipes (`@@tomato sauce{200%ml}`) are replaced by the
+    /// ingredients of that recipe, recursively.
+    ///
+    /// `id` is the id of the recipe in the `graph`, where the referenced
+    /// recipes are taken from.
+    ///
+    /// The referenced recipe is scaled to the quantity of the reference, like
+    /// when [scaling](crate::Recipe::scale) to a number of servings, so only
+    /// values marked to scale change:
+    /// - With no unit, or `servings`, it's the number of servings. The base
+    ///   is the first of the declared servings or 1.
+    /// - With any other unit, it's compared with the
+    ///   [`yields`](crate::Metadata::yields) metadata of the referenced
+    ///   recipe, like `>> yield: 500%ml`.
+    /// - With no quantity, it's scaled the same as the recipe that references
+    ///   it.
+    ///
+    /// If a reference can't be expanded, because the recipe is not in the
+    /// graph or it can't be scaled to the quantity, it's added as a regular
+    /// ingredient. When it can't be scaled, a
+    /// [`RecipeNotExpanded`](IngredientListWarning::RecipeNotExpanded) is
+    /// added to the [`warnings`](IngredientList::warnings). The rest of
+    /// problems are reported by the [`Resolver`](crate::resolve::Resolver).
+    ///
+    /// The ingredients from a referenced recipe record its id in
+    /// [`IngredientList::origins`].
+    ///
+    /// ```
+    /// # use cooklang::{CooklangParser, ingredient_list::IngredientList, resolve::*};
+    /// # use cooklang::{Quantity, QuantityValue, TotalQuantity};
+    /// let source = MemorySource::from_iter([
+    ///     ("Pizza", "Top with @@Tomato sauce{250%ml}."),
+    ///     ("Tomato sauce", ">> yield: 500%ml\nCook @tomatoes{800*%g} with a @bay leaf{1}."),
+    /// ]);
+    /// let parser = CooklangParser::default();
+    /// let graph = Resolver::new(&parser, source).resolve(["Pizza"]);
+    /// let pizza = graph.get("Pizza").unwrap().recipe.clone().default_scale();
+    ///
+    /// let mut list = IngredientList::new();
+    /// list.add_recipe_expanded(&pizza, "Pizza", &graph, parser.converter());
+    /// let names = list.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    /// assert_eq!(names, ["bay leaf", "tomatoes"]);
+    ///
+    /// // only the values marked with `*` are scaled
+    /// let (_, quantity) = list.iter().nth(1).unwrap();
+    /// assert_eq!(
+    ///     quantity.total(),
+    ///     TotalQuantity::Single(Quantity::new(
+    ///         QuantityValue::Fixed { value: 400.0.into() },
+    ///         Some("g".into())
+    ///     ))
+    /// );
+    /// assert_eq!(list.origins("tomatoes"), ["Tomato sauce"]);
+    /// ```
+    pub fn add_recipe_expanded(
+        &mut self,
+        recipe: &ScaledRecipe,
+        id: &str,
+        graph: &RecipeGraph,
+        converter: &Converter,
+    ) {
+        self.add_expanded(
+            &recipe.ingredients,
+            id,
+            graph,
+            converter,
+            recipe.scaled_data().map_or(1.0, |d| d.target.factor()),
+            &mut vec![id.to_string()],
+        );
+    }
+
+    fn add_expanded(
+        &mut self,
+        ingredients: &[Ingredient],
+        id: &str,
+        graph: &RecipeGraph,
+        converter: &Converter,
+        factor: f64,
+        stack: &mut Vec<String>,
+    ) {
+        // the top level recipe is not an origin
+        let origin = (stack.len() > 1).then_some(id);
+        let dependencies = graph.get(id).map(|r| r.dependencies.as_slice());
+        for (index, ingredient) in ingredients.iter().enumerate() {
+            if !ingredient.relation.is_definition() || !ingredient.modifiers().should_be_listed() {
+                continue;
+            }
+            let quantity = ingredient.group_quantities(ingredients, converter);
+
+            let sub_recipe = dependencies
+                .and_then(|deps| deps.iter().find(|d| d.ingredient == index))
+                // skip cycles, they are reported when resolving
+                .filter(|dep| !stack.contains(&dep.id))
+                .and_then(|dep| Some((dep.id.as_str(), &graph.get(&dep.id)?.recipe)));
+            if let Some((sub_id, sub_recipe)) = sub_recipe {
+                let scaled = sub_recipe_scale(&quantity, sub_recipe, converter, factor)
+                    .ok_or((None, None))
+                    .and_then(|scale| {
+                        scale_ingredients(&sub_recipe.ingredients, &scale)
+                            .map(|scaled| (scaled, scale))
+                            .map_err(|(name, err)| (Some(name), Some(err)))
+                    });
+                match scaled {
+                    Ok((scaled, scale)) => {
+                        let factor = match scale {
+                            SubRecipeScale::Servings(target) => target.factor(),
+                            SubRecipeScale::Factor(factor) => factor,
+                        };
+                        stack.push(sub_id.to_string());
+                        self.add_expanded(&scaled, sub_id, graph, converter, factor, stack);
+                        stack.pop();
+                        continue;
+                    }
+                    Err((ingredient, source)) => {
+                        self.warnings
+                            .push(IngredientListWarning::RecipeNotExpanded {
+                                recipe: sub_id.to_string(),
+                                ingredient,
+                                source,
+                            })
+                    }
+                }
+            }
+
+            let name = ingredient.display_name().into_owned();
+            if let Some(origin) = origin {
+                let origins = self.origins.entry(name.clone()).or_default();
+                if !origins.iter().any(|o| o == origin) {
+                    origins.push(origin.to_string());
+                }
+            }
+            self.add_ingredient(name, &quantity, converter);
+        }
+    }
+
     /// Add an ingredient to the list.
     ///
     /// The quantity will be merged will the ingredients with the same name.
@@ -158,11 +334,31 @@ impl IngredientList {
         quantity: &GroupedQuantity,
         converter: &Converter,
     ) {
-        self.0.entry(name).or_default().merge(quantity, converter)
+        self.ingredients
+            .entry(name)
+            .or_insert_with_key(|name| GroupedQuantity::for_ingredient(name))
+            .merge(quantity, converter)
+    }
+
+    /// Ids of the sub-recipes an ingredient came from
+    ///
+    /// Only filled by [`IngredientList::add_recipe_expanded`]. Empty if the
+    /// ingredient is not in the list or it only came from the top level
+    /// recipes.
+    pub fn origins(&self, name: &str) -> &[String] {
+        self.origins
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Problems found adding the recipes
+    pub fn warnings(&self) -> &[IngredientListWarning] {
+        &self.warnings
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.ingredients.is_empty()
     }
 
     /// Split this list into different categories.
@@ -170,25 +366,27 @@ impl IngredientList {
     /// Ingredients without category will be placed in `"other"`.
     pub fn categorize(self, aisle: &AisleConf) -> CategorizedIngredientList {
         let aisle = aisle.reverse();
-        let mut categorized = CategorizedIngredientList::default();
-        for (name, quantity) in self.0 {
-            if let Some(cat) = aisle.get(name.as_str()) {
-                categorized
-                    .categories
-                    .entry(cat.to_string())
-                    .or_default()
-                    .0
-                    .insert(name, quantity);
-            } else {
-                categorized.other.0.insert(name, quantity);
+        let mut categorized = CategorizedIngredientList {
+            warnings: self.warnings,
+            ..Default::default()
+        };
+        let mut origins = self.origins;
+        for (name, quantity) in self.ingredients {
+            let list = match aisle.get(name.as_str()) {
+                Some(cat) => categorized.categories.entry(cat.to_string()).or_default(),
+                None => &mut categorized.other,
+            };
+            if let Some(o) = origins.remove(&name) {
+                list.origins.insert(name.clone(), o);
             }
+            list.ingredients.insert(name, quantity);
         }
         categorized
     }
 
     /// Iterate over all ingredients sorted by name
     pub fn iter(&self) -> impl Iterator<Item = (&String, &GroupedQuantity)> {
-        self.0.iter()
+        self.ingredients.iter()
     }
 }
 
@@ -199,8 +397,78 @@ impl IntoIterator for IngredientList {
 
     /// Iterate over all ingrediends sorted by name
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.ingredients.into_iter()
+    }
+}
+
+/// How to scale a referenced recipe
+enum SubRecipeScale {
+    Servings(ScaleTarget),
+    Factor(f64),
+}
+
+/// How to scale a referenced recipe to `quantity`
+///
+/// `factor` is the one the recipe with the reference was scaled by.
+fn sub_recipe_scale(
+    quantity: &GroupedQuantity,
+    recipe: &Recipe,
+    converter: &Converter,
+    factor: f64,
+) -> Option<SubRecipeScale> {
+    let quantity = match quantity.total() {
+        TotalQuantity::None => return Some(SubRecipeScale::Factor(factor)),
+        TotalQuantity::Single(q) => q,
+        TotalQuantity::Many(_) => return None,
+    };
+    let value = match *quantity.value.extract_value().ok()? {
+        Value::Number { value } => value,
+        Value::Fraction { value } => value.to_f64(),
+        _ => return None,
+    };
+
+    match quantity.unit_text() {
+        None | Some("serving" | "servings") => {
+            let servings = recipe.metadata.servings.as_deref().unwrap_or_default();
+            let base = servings.first().copied().unwrap_or(1);
+            if value.fract() == 0.0 && value >= 1.0 {
+                let target = ScaleTarget::new(base, value as u32, servings);
+                Some(SubRecipeScale::Servings(target))
+            } else {
+                Some(SubRecipeScale::Factor(value / base as f64))
+            }
+        }
+        Some(_) => {
+            let yields = recipe.metadata.yields.as_ref()?;
+            target_factor(&quantity, yields, |q, unit| converter.convert(q, unit).ok())
+                .ok()
+                .map(SubRecipeScale::Factor)
+        }
+    }
+}
+
+/// Ingredients with their quantities scaled
+///
+/// If one can't be scaled, returns its name and the error.
+fn scale_ingredients(
+    ingredients: &[Ingredient],
+    scale: &SubRecipeScale,
+) -> Result<Vec<Ingredient>, (String, ScaleError)> {
+    let mut ingredients = ingredients.to_vec();
+    for igr in &mut ingredients {
+        if let Some(q) = &mut igr.quantity {
+            let value = q.value.clone();
+            let scaled = match scale {
+                SubRecipeScale::Servings(target) => value.scale(target, ScaleRule::Linear, false),
+                SubRecipeScale::Factor(factor) => value.scale_by_factor(*factor),
+            };
+            match scaled {
+                Ok((value, _)) => q.value = value,
+                Err(err) => return Err((igr.name.clone(), err)),
+            }
+        }
     }
+    Ok(ingredients)
 }
 
 /// Ingredient list split into categories.
@@ -214,6 +482,9 @@ pub struct CategorizedIngredientList {
     pub categories: BTreeMap<String, IngredientList>,
     /// Ingredients with no category assigned
     pub other: IngredientList,
+    /// Warnings of the list before categorizing it, see
+    /// [`IngredientList::warnings`]
+    pub warnings: Vec<IngredientListWarning>,
 }
 
 impl CategorizedIngredientList {
@@ -0,0 +1,285 @@
+//! Stable and versioned interchange format
+//!
+//! Serializing a [`Recipe`] directly gives its in memory representation,
+//! which follows the model and may change between versions of this crate. To
+//! share recipes with other programs, wrap them in a [`Document`]. It adds a
+//! `version` field to the recipe and, when deserialized, migrates documents
+//! from older versions of the format to the current model.
+//!
+//! ```rust
+//! # use cooklang::{interchange::{self, Document}, CooklangParser, Recipe};
+//! let parser = CooklangParser::default();
+//! let recipe = parser.parse("Add @salt{1%tsp}.", "example").into_output().unwrap();
+//!
+//! let json = interchange::to_string(&recipe)?;
+//! assert!(json.starts_with(r#"{"version":1,"name":"example""#));
+//!
+//! let back: Recipe = interchange::from_str(&json)?;
+//! assert_eq!(back, recipe);
+//! // or in any other serde data structure
+//! let Document { recipe: back } = serde_json::from_str::<Document>(&json)?;
+//! assert_eq!(back, recipe);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! # Format
+//!
+//! A document is an object with a `version` number and the fields of the
+//! recipe. With the `schema` [feature](crate::_features), `json_schema`
+//! generates its JSON Schema.
+//!
+//! - Enums are objects with a `type` field naming the variant, like
+//!   `{ "type": "fixed", "value": { "type": "number", "value": 2.0 } }`. The
+//!   exceptions are [`ScaleOutcome`](crate::scale::ScaleOutcome), which is
+//!   just a string, and [`MetadataValue`](crate::metadata::schema::MetadataValue),
+//!   which has the value in a `value` field.
+//! - `data` is `null` in a [`Recipe`]. In a [`ScaledRecipe`](crate::ScaledRecipe)
+//!   it has the outcome of the scaling and the quantities before scaling.
+//! - The `relation` of an ingredient is a
+//!   [`ComponentRelation`](crate::ComponentRelation) with an extra
+//!   `reference_target` field.
+//! - Spans are offsets into the source of the recipe. They are `null` or
+//!   missing if the recipe was not parsed from a source.
+//!
+//! # Versions
+//!
+//! - `0`. The recipe serialized directly, without a `version` field, by
+//!   `cooklang` 0.5 and older. In scaled recipes, `data` is
+//!   `{ "type": "DefaultScaling" }` or `{ "type": "Scaled", ... }`. The
+//!   quantities before scaling are not there, so
+//!   [`unscale`](crate::ScaledRecipe::unscale) and
+//!   [`rescale`](crate::ScaledRecipe::rescale) take the scaled ones as the
+//!   original.
+//! - `1`. Current version.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::Recipe;
+
+/// Current version of the format
+pub const VERSION: u32 = 1;
+
+/// Recipe in the interchange format
+///
+/// See the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document<D = ()> {
+    /// The recipe
+    pub recipe: Recipe<D>,
+}
+
+impl<D> Document<D> {
+    /// Wraps a recipe
+    pub fn new(recipe: Recipe<D>) -> Self {
+        Self { recipe }
+    }
+}
+
+impl<D> From<Recipe<D>> for Document<D> {
+    fn from(recipe: Recipe<D>) -> Self {
+        Self::new(recipe)
+    }
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct DocumentRepr<'a, D> {
+    /// Version of the format
+    #[cfg_attr(feature = "schema", schemars(schema_with = "version_schema"))]
+    version: u32,
+    #[serde(flatten)]
+    recipe: &'a Recipe<D>,
+}
+
+impl<D: Serialize> Serialize for Document<D> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DocumentRepr {
+            version: VERSION,
+            recipe: &self.recipe,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, D: DeserializeOwned> Deserialize<'de> for Document<D> {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let document = Value::deserialize(deserializer)?;
+        from_value(document)
+            .map(Self::new)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Errors reading a [`Document`]
+#[derive(Debug, Error)]
+pub enum InterchangeError {
+    #[error("The document is not an object")]
+    NotAnObject,
+
+    #[error("Invalid version, expected a positive integer")]
+    InvalidVersion,
+
+    #[error("Unsupported version {version}, the latest is {VERSION}")]
+    UnsupportedVersion { version: u64 },
+
+    #[error("Error migrating from version {from}: {reason}")]
+    Migration { from: u32, reason: &'static str },
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Serializes a recipe as a JSON [`Document`]
+pub fn to_string<D: Serialize>(recipe: &Recipe<D>) -> serde_json::Result<String> {
+    serde_json::to_string(&DocumentRepr {
+        version: VERSION,
+        recipe,
+    })
+}
+
+/// Serializes a recipe as a JSON [`Document`] value
+pub fn to_value<D: Serialize>(recipe: &Recipe<D>) -> serde_json::Result<Value> {
+    serde_json::to_value(DocumentRepr {
+        version: VERSION,
+        recipe,
+    })
+}
+
+/// Deserializes a recipe from a JSON [`Document`] of any version
+pub fn from_str<D: DeserializeOwned>(s: &str) -> Result<Recipe<D>, InterchangeError> {
+    from_value(serde_json::from_str(s)?)
+}
+
+/// Deserializes a recipe from a JSON [`Document`] value of any version
+pub fn from_value<D: DeserializeOwned>(document: Value) -> Result<Recipe<D>, InterchangeError> {
+    let mut document = migrate_object(document)?;
+    document.remove("version");
+    Ok(serde_json::from_value(Value::Object(document))?)
+}
+
+/// Migrates a document of any version to the current [`VERSION`]
+///
+/// Documents without a `version` field are version 0.
+pub fn migrate(document: Value) -> Result<Value, InterchangeError> {
+    migrate_object(document).map(Value::Object)
+}
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), &'static str>;
+
+/// `MIGRATIONS[n]` migrates a document from version `n` to `n + 1`
+const MIGRATIONS: [Migration; VERSION as usize] = [v0_to_v1];
+
+fn migrate_object(document: Value) -> Result<Map<String, Value>, InterchangeError> {
+    let Value::Object(mut document) = document else {
+        return Err(InterchangeError::NotAnObject);
+    };
+    let version = match document.get("version") {
+        Some(v) => v.as_u64().ok_or(InterchangeError::InvalidVersion)?,
+        None => 0,
+    };
+    if version > VERSION as u64 {
+        return Err(InterchangeError::UnsupportedVersion { version });
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut document).map_err(|reason| InterchangeError::Migration {
+            from: from as u32,
+            reason,
+        })?;
+    }
+    document.insert("version".to_string(), VERSION.into());
+    Ok(document)
+}
+
+fn v0_to_v1(document: &mut Map<String, Value>) -> Result<(), &'static str> {
+    // an unscaled recipe is the same
+    let Some(data) = document.get("data").and_then(Value::as_object) else {
+        return Ok(());
+    };
+    let quantities = |key| -> Value {
+        let components = document.get(key).and_then(Value::as_array);
+        components
+            .into_iter()
+            .flatten()
+            .map(|c| c.get("quantity").cloned().unwrap_or_default())
+            .collect()
+    };
+    let original = json!({
+        "ingredients": quantities("ingredients"),
+        "cookware": quantities("cookware"),
+        "timers": quantities("timers"),
+        "inline_quantities": document.get("inline_quantities").cloned().unwrap_or(json!([])),
+    });
+    let data = match data.get("type").and_then(Value::as_str) {
+        Some("DefaultScaling") if data.len() == 1 => {
+            let mut data = original;
+            data["type"] = "DefaultScaling".into();
+            data
+        }
+        Some("Scaled") if !data.contains_key("original") => {
+            let target = data.get("target").ok_or("missing scale target")?;
+            let servings = |key| {
+                target
+                    .get(key)
+                    .and_then(Value::as_u64)
+                    .ok_or("invalid scale target")
+            };
+            let (base, target_servings) = (servings("base")?, servings("target")?);
+            let outcomes = |key| data.get(key).cloned().ok_or("missing scale outcomes");
+            json!({
+                "type": "Scaled",
+                "target": {
+                    "kind": { "type": "servings", "base": base, "target": target_servings },
+                    "factor": target_servings as f64 / base as f64,
+                    "index": target.get("index").cloned().unwrap_or_default(),
+                    "base": base,
+                },
+                "ingredients": outcomes("ingredients")?,
+                "cookware": outcomes("cookware")?,
+                "timers": outcomes("timers")?,
+                "inline_quantities": [],
+                "original": original,
+            })
+        }
+        // already in the current shape, serialized without a `Document`
+        Some("DefaultScaling" | "Scaled") => return Ok(()),
+        _ => return Err("unknown scaled data"),
+    };
+    document.insert("data".to_string(), data);
+    Ok(())
+}
+
+/// Generates the JSON Schema of a [`Document`]
+///
+/// Use `()` for a [`Recipe`] or [`Scaled`](crate::scale::Scaled) for a
+/// [`ScaledRecipe`](crate::ScaledRecipe).
+#[cfg(feature = "schema")]
+pub fn json_schema<D: schemars::JsonSchema>() -> schemars::schema::RootSchema {
+    let mut schema = schemars::schema_for!(Document<D>);
+    let metadata = schema.schema.metadata();
+    metadata.title = Some("Document".to_string());
+    metadata.description = Some(format!("Cooklang recipe, version {VERSION}"));
+    schema
+}
+
+#[cfg(feature = "schema")]
+impl<D: schemars::JsonSchema> schemars::JsonSchema for Document<D> {
+    fn schema_name() -> String {
+        format!("Document_for_{}", D::schema_name())
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        DocumentRepr::<D>::json_schema(gen)
+    }
+}
+
+#[cfg(feature = "schema")]
+fn version_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    schemars::schema::SchemaObject {
+        instance_type: Some(schemars::schema::InstanceType::Integer.into()),
+        const_value: Some(VERSION.into()),
+        ..Default::default()
+    }
+    .into()
+}
@@ -17,7 +17,7 @@ impl Token {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub enum TokenKind {
     /// ">>"
     MetadataStart,
@@ -82,6 +82,16 @@ pub enum TokenKind {
     Eof,
 }
 
+impl TokenKind {
+    /// Checks if the token is whitespace, a newline or a comment
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            T![ws] | T![newline] | T![line comment] | T![block comment]
+        )
+    }
+}
+
 fn is_newline(c: char, first: char) -> bool {
     c == '\n' || (c == '\r' && first == '\n')
 }
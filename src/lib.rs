@@ -7,7 +7,18 @@
 //! - Rich error report with annotated code spans.
 //! - Unit conversion.
 //! - Recipe scaling.
+//! - Human friendly [formatting](crate::quantity::format) of quantities.
 //! - A parser for cooklang aisle configuration file.
+//! - A [writer] to get the cooklang source of a recipe back.
+//! - A canonical [formatter](crate::format).
+//! - [Incremental](crate::incremental) reparsing for editors.
+//! - [Resolution](crate::resolve) of references to other recipes.
+//! - A searchable [collection] of recipes.
+//! - [Nutrition](crate::nutrition) facts.
+//! - A stable and versioned [interchange] format, with JSON Schema.
+//! - [Import and export](crate::schema_org) of schema.org JSON-LD.
+//! - A parser for free text [ingredient lines](crate::ingredient_line).
+//! - [Markdown and HTML](crate::render) renderers.
 //!
 //! # Basic usage
 //! If you just want to parse a **single** `cooklang` file, see [`parse`].
@@ -52,7 +63,7 @@ pub mod _extensions {
 
 #[cfg(doc)]
 pub mod _features {
-    //! This lib has 2 features, both enabled by default:
+    //! This lib has 6 features, the first 5 enabled by default:
     //! - `bundled_units`. Includes a units file with the most common units for
     //!   recipes in English. These units are available to load when you want
     //!   without the need to read a file. The default
@@ -60,29 +71,57 @@ pub mod _features {
     //!   enabled. [This is the bundled file](https://github.com/cooklang/cooklang-rs/blob/main/units.toml)
     //!
     //! - `aisle`. Enables the [`aisle`](crate::aisle) module.
+    //!
+    //! - `nutrition_csv`. Loads nutrition databases from CSV with
+    //!   [`NutrientDatabase::from_csv`](crate::nutrition::NutrientDatabase::from_csv).
+    //!
+    //! - `interchange`. Enables the [`interchange`](crate::interchange)
+    //!   module.
+    //!
+    //! - `schema_org`. Enables the [`schema_org`](crate::schema_org) module.
+    //!
+    //! - `schema`. Generates the JSON Schema of the
+    //!   [interchange](crate::interchange) format with
+    //!   [`schemars`](https://docs.rs/schemars). It enables `interchange`.
 }
 
 #[cfg(feature = "aisle")]
 pub mod aisle;
 pub mod ast;
+pub mod collection;
 pub mod convert;
+pub mod cst;
 pub mod error;
+pub mod format;
+pub mod incremental;
+pub mod ingredient_line;
 pub mod ingredient_list;
+#[cfg(feature = "interchange")]
+pub mod interchange;
 pub mod located;
 pub mod metadata;
 pub mod model;
+pub mod nutrition;
 pub mod parser;
 pub mod quantity;
+pub mod render;
+pub mod resolve;
 pub mod scale;
+#[cfg(feature = "schema_org")]
+pub mod schema_org;
 pub mod span;
+pub mod writer;
 
 mod analysis;
 mod context;
 mod lexer;
 
+use std::sync::Arc;
+
 use bitflags::bitflags;
 
 use error::{CooklangError, CooklangWarning, PassResult};
+use metadata::schema::MetadataSchema;
 
 pub use convert::Converter;
 pub use located::Located;
@@ -174,6 +213,7 @@ impl Default for Extensions {
 pub struct CooklangParser {
     extensions: Extensions,
     converter: Converter,
+    schema: Option<Arc<MetadataSchema>>,
 }
 
 pub type RecipeResult = PassResult<Recipe, CooklangError, CooklangWarning>;
@@ -189,9 +229,23 @@ impl CooklangParser {
         Self {
             extensions,
             converter,
+            schema: None,
         }
     }
 
+    /// Checks the metadata of every recipe against a schema
+    ///
+    /// See [`metadata::schema`] for more information.
+    pub fn with_metadata_schema(mut self, schema: MetadataSchema) -> Self {
+        self.schema = Some(Arc::new(schema));
+        self
+    }
+
+    /// Get the metadata schema, if any
+    pub fn metadata_schema(&self) -> Option<&MetadataSchema> {
+        self.schema.as_deref()
+    }
+
     /// Get the parser inner converter
     pub fn converter(&self) -> &Converter {
         &self.converter
@@ -220,12 +274,31 @@ impl CooklangParser {
         recipe_name: &str,
         recipe_ref_checker: Option<RecipeRefChecker>,
     ) -> RecipeResult {
-        let mut r = parser::parse(input, self.extensions).into_context_result();
+        let ast = parser::parse(input, self.extensions);
+        self.analyze(ast, recipe_name, |ast| {
+            analysis::parse_ast(
+                ast,
+                self.extensions,
+                &self.converter,
+                self.schema.as_deref(),
+                recipe_ref_checker,
+            )
+        })
+    }
+
+    /// Runs the `analysis` over the result of the parser, if it's valid
+    pub(crate) fn analyze<'i>(
+        &self,
+        ast: PassResult<ast::Ast<'i>, parser::ParserError, parser::ParserWarning>,
+        recipe_name: &str,
+        analysis: impl FnOnce(ast::Ast<'i>) -> analysis::AnalysisResult,
+    ) -> RecipeResult {
+        let mut r = ast.into_context_result();
         if !r.is_valid() {
             return r.discard_output();
         }
         let ast = r.take_output().unwrap();
-        analysis::parse_ast(ast, self.extensions, &self.converter, recipe_ref_checker)
+        analysis(ast)
             .into_context_result()
             .merge(r)
             .map(|c| Recipe {
@@ -250,10 +323,16 @@ impl CooklangParser {
             return r.discard_output();
         }
         let ast = r.take_output().unwrap();
-        analysis::parse_ast(ast, Extensions::empty(), &self.converter, None)
-            .into_context_result()
-            .merge(r)
-            .map(|c| c.metadata)
+        analysis::parse_ast(
+            ast,
+            Extensions::empty(),
+            &self.converter,
+            self.schema.as_deref(),
+            None,
+        )
+        .into_context_result()
+        .merge(r)
+        .map(|c| c.metadata)
     }
 }
 
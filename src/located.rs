@@ -55,6 +55,11 @@ impl<T> Located<T> {
     pub fn span(&self) -> Span {
         self.span
     }
+
+    /// Moves the location `delta` bytes
+    pub(crate) fn shift(&mut self, delta: isize) {
+        self.span.shift(delta);
+    }
 }
 
 impl<T: Clone + Copy> Copy for Located<T> {}
@@ -1,10 +1,19 @@
 //! Metadata of a recipe
 
+pub mod schema;
+
 pub use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use url::Url;
 
+use crate::{
+    parser::parse_standalone_quantity,
+    quantity::{Quantity, QuantityValue, Value},
+    span::Span,
+    Extensions,
+};
+
 /// Utility to create lazy regex
 /// from <https://docs.rs/once_cell/latest/once_cell/#lazily-compiled-regex>
 macro_rules! regex {
@@ -22,7 +31,11 @@ pub(crate) use regex;
 ///
 /// The fields on this struct are the parsed values with some special meaning.
 /// The raw key/value pairs from the recipe are in the `map` field.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+///
+/// The locations of the entries in the source are not compared in
+/// [`PartialEq`].
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Metadata {
     /// Description of the recipe
     pub description: Option<String>,
@@ -40,14 +53,70 @@ pub struct Metadata {
     pub time: Option<RecipeTime>,
     /// Servings the recipe is made for
     pub servings: Option<Vec<u32>>,
+    /// What the recipe makes, like `1 loaf`, `24 cookies` or `1.5 l`
+    ///
+    /// Parsed from the `yield` key, with the same syntax as a component
+    /// quantity. The value is always a fixed number and the unit is optional.
+    #[serde(default)]
+    pub yields: Option<Quantity>,
     /// All the raw key/value pairs from the recipe
     pub map: IndexMap<String, String>,
+    /// Parsed values of the keys in the [schema](schema::MetadataSchema)
+    ///
+    /// Use [`Self::get`] to get them with a concrete type.
+    #[serde(default)]
+    pub typed: IndexMap<String, schema::MetadataValue>,
+    /// Location of the entries of [`map`](Self::map) in the source
+    ///
+    /// Empty if the recipe was not parsed from a source. Use [`Self::span`]
+    /// to get them.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub(crate) spans: IndexMap<String, EntrySpan>,
+}
+
+impl PartialEq for Metadata {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            tags,
+            emoji,
+            author,
+            source,
+            time,
+            servings,
+            yields,
+            map,
+            typed,
+            spans: _,
+        } = self;
+        *description == other.description
+            && *tags == other.tags
+            && *emoji == other.emoji
+            && *author == other.author
+            && *source == other.source
+            && *time == other.time
+            && *servings == other.servings
+            && *yields == other.yields
+            && *map == other.map
+            && *typed == other.typed
+    }
+}
+
+/// Location of a metadata entry in the source
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EntrySpan {
+    /// Location of the key
+    pub key: Span,
+    /// Location of the value
+    pub value: Span,
 }
 
 /// Combination of name and URL.
 ///
 /// At least one of the fields is [`Some`].
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct NameAndUrl {
     name: Option<String>,
@@ -58,6 +127,7 @@ pub struct NameAndUrl {
 ///
 /// All values are in minutes.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged, deny_unknown_fields)]
 pub enum RecipeTime {
     /// Total time
@@ -74,6 +144,17 @@ pub enum RecipeTime {
 }
 
 impl Metadata {
+    /// Location of an entry of [`map`](Self::map) in the source
+    ///
+    /// [`None`] if the key is not in the map or the recipe was not parsed from
+    /// a source.
+    pub fn span(&self, key: &str) -> Option<EntrySpan> {
+        self.spans
+            .get(key)
+            .copied()
+            .filter(|_| self.map.contains_key(key))
+    }
+
     pub(crate) fn insert(&mut self, key: String, value: String) -> Result<(), MetadataError> {
         self.map.insert(key.clone(), value.clone());
         match key.as_str() {
@@ -125,17 +206,24 @@ impl Metadata {
                     .map(str::parse)
                     .collect::<Result<Vec<_>, _>>()?;
                 servings.sort_unstable();
-                let l = servings.len();
                 servings.dedup();
-                if servings.len() != l {}
                 self.servings = Some(servings)
             }
+            "yield" => self.yields = Some(parse_yield(&value)?),
             _ => {}
         }
 
         Ok(())
     }
 
+    /// Get a value parsed with the [schema](schema::MetadataSchema)
+    ///
+    /// Returns [`None`] if the key is not in the schema, the recipe does not
+    /// have it or it's not of type `T`.
+    pub fn get<T: schema::FromMetadataValue>(&self, key: &str) -> Option<T> {
+        self.typed.get(key).and_then(T::from_metadata_value)
+    }
+
     /// Returns a copy of [Self::map] but with all *special* metadata values
     /// removed
     pub fn map_filtered(&self) -> IndexMap<String, String> {
@@ -153,6 +241,7 @@ impl Metadata {
             "cook_time",
             "cook time",
             "servings",
+            "yield",
         ];
         let mut new_map = self.map.clone();
         new_map.retain(|key, _| !ALL_KNOWN_KEYS.contains(&key.as_ref()));
@@ -160,6 +249,36 @@ impl Metadata {
     }
 }
 
+/// Parses a quantity with a single positive number, like `1 loaf` or `500%ml`
+fn parse_yield(s: &str) -> Result<Quantity, MetadataError> {
+    let invalid = || MetadataError::InvalidYield {
+        value: s.to_string(),
+    };
+    let quantity = parse_standalone_quantity(s, Extensions::ADVANCED_UNITS)
+        .into_result()
+        .map_err(|_| invalid())?
+        .0;
+    let value = match QuantityValue::from_ast(quantity.value) {
+        QuantityValue::Fixed {
+            value: value @ (Value::Number { .. } | Value::Fraction { .. }),
+        } => value,
+        _ => return Err(invalid()),
+    };
+    // scaling to a yield divides by it
+    let positive = match &value {
+        Value::Number { value } => *value > 0.0,
+        Value::Fraction { value } => value.num() > 0,
+        _ => false,
+    };
+    if !positive {
+        return Err(invalid());
+    }
+    Ok(Quantity::new(
+        QuantityValue::Fixed { value },
+        quantity.unit.map(|u| u.text_trimmed().into_owned()),
+    ))
+}
+
 /// Returns minutes
 fn parse_time(s: &str) -> Result<u32, std::num::ParseIntError> {
     match humantime::parse_duration(s) {
@@ -227,12 +346,14 @@ impl RecipeTime {
     }
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Clone)]
 pub enum MetadataError {
     #[error("Value is not an emoji: {value}")]
     NotEmoji { value: String },
     #[error("Invalid tag: {tag}")]
     InvalidTag { tag: String },
+    #[error("Invalid yield, expected a positive number and an optional unit: {value}")]
+    InvalidYield { value: String },
     #[error(transparent)]
     ParseIntError(#[from] std::num::ParseIntError),
 }
@@ -257,7 +378,7 @@ pub fn is_valid_tag(tag: &str) -> bool {
 pub fn tagify(text: &str) -> String {
     let text = text
         .trim()
-        .replace(|c: char| (c.is_whitespace() || c == '_'), "-")
+        .replace(|c: char| c.is_whitespace() || c == '_', "-")
         .replace(|c: char| !(c.is_alphanumeric() || c == '-'), "")
         .trim_matches('-')
         .to_lowercase();
@@ -300,4 +421,29 @@ mod tests {
         assert_eq!(tagify("WhATever_--thiS - - is"), "whatever-this-is");
         assert_eq!(tagify("Sensible recipe name"), "sensible-recipe-name");
     }
+
+    #[test]
+    fn test_parse_yield() {
+        let q = |value: f64, unit: Option<&str>| {
+            Quantity::new(
+                QuantityValue::Fixed {
+                    value: value.into(),
+                },
+                unit.map(str::to_string),
+            )
+        };
+        assert_eq!(parse_yield("2").unwrap(), q(2.0, None));
+        assert_eq!(parse_yield("1 loaf").unwrap(), q(1.0, Some("loaf")));
+        assert_eq!(parse_yield("24 cookies").unwrap(), q(24.0, Some("cookies")));
+        assert_eq!(parse_yield("1.5%l").unwrap(), q(1.5, Some("l")));
+        assert_eq!(parse_yield("500 ml").unwrap(), q(500.0, Some("ml")));
+        assert!(parse_yield("a lot").is_err());
+        assert!(parse_yield("1|2").is_err());
+        assert!(parse_yield("2*").is_err());
+        assert!(parse_yield("").is_err());
+        assert!(parse_yield("0").is_err());
+        assert!(parse_yield("0 loaves").is_err());
+        assert!(parse_yield("0/2").is_err());
+        assert!(parse_yield("1/2 loaf").is_ok());
+    }
 }
@@ -0,0 +1,455 @@
+//! Typed metadata keys
+//!
+//! A [`MetadataSchema`] declares the type of some metadata keys, if they are
+//! required and extra validations. Give it to the parser with
+//! [`CooklangParser::with_metadata_schema`](crate::CooklangParser::with_metadata_schema)
+//! and the values are checked when parsing:
+//! - An invalid value in an optional key is a warning.
+//! - A missing or invalid required key is an error.
+//!
+//! The parsed values are available with [`Metadata::get`](crate::Metadata::get).
+//!
+//! ```
+//! # use cooklang::{CooklangParser, metadata::schema::*};
+//! let schema = MetadataSchema::new()
+//!     .key("difficulty", KeySchema::new(MetadataType::Enum(vec!["easy".into(), "hard".into()])))
+//!     .key(
+//!         "calories",
+//!         KeySchema::new(MetadataType::Integer)
+//!             .required()
+//!             .validate_with(|v| match v {
+//!                 MetadataValue::Integer(n) if *n < 0 => Err("can't be negative".into()),
+//!                 _ => Ok(()),
+//!             }),
+//!     );
+//! let parser = CooklangParser::default().with_metadata_schema(schema);
+//!
+//! let recipe = parser.parse(">> calories: 500\n>> difficulty: easy", "Soup");
+//! let (recipe, _) = recipe.into_result()?;
+//! assert_eq!(recipe.metadata.get::<i64>("calories"), Some(500));
+//! assert_eq!(recipe.metadata.get::<String>("difficulty").as_deref(), Some("easy"));
+//!
+//! let recipe = parser.parse(">> calories: -1", "Soup");
+//! assert!(recipe.into_result().is_err());
+//! # Ok::<(), cooklang::error::CooklangReport>(())
+//! ```
+
+use std::{fmt::Display, sync::Arc, time::Duration};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+/// Declared types of metadata keys
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataSchema {
+    keys: IndexMap<String, KeySchema>,
+}
+
+impl MetadataSchema {
+    /// Empty schema
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a key
+    ///
+    /// If the key was already declared, it's replaced.
+    pub fn key(mut self, key: &str, schema: KeySchema) -> Self {
+        self.keys.insert(key.to_string(), schema);
+        self
+    }
+
+    /// Get the schema of a key
+    pub fn get(&self, key: &str) -> Option<&KeySchema> {
+        self.keys.get(key)
+    }
+
+    /// Iterate over all the declared keys, in declaration order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &KeySchema)> {
+        self.keys.iter().map(|(k, s)| (k.as_str(), s))
+    }
+}
+
+/// Custom validation of a metadata value
+///
+/// Returns the reason when the value is not valid.
+pub type Validator = Arc<dyn Fn(&MetadataValue) -> Result<(), String> + Send + Sync>;
+
+/// Schema of a metadata key
+#[derive(Clone)]
+pub struct KeySchema {
+    ty: MetadataType,
+    required: bool,
+    validators: Vec<Validator>,
+}
+
+impl KeySchema {
+    /// Optional key of type `ty`
+    pub fn new(ty: MetadataType) -> Self {
+        Self {
+            ty,
+            required: false,
+            validators: Vec::new(),
+        }
+    }
+
+    /// Makes the key required
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Adds a custom validation, that runs after the value is parsed
+    pub fn validate_with(
+        mut self,
+        f: impl Fn(&MetadataValue) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validators.push(Arc::new(f));
+        self
+    }
+
+    /// Get the type
+    pub fn ty(&self) -> &MetadataType {
+        &self.ty
+    }
+
+    /// Checks if the key is required
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Parses and validates a value
+    pub fn parse(&self, value: &str) -> Result<MetadataValue, SchemaError> {
+        let value = self.ty.parse(value)?;
+        for validator in &self.validators {
+            validator(&value).map_err(|reason| SchemaError::Validation { reason })?;
+        }
+        Ok(value)
+    }
+}
+
+impl std::fmt::Debug for KeySchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeySchema")
+            .field("ty", &self.ty)
+            .field("required", &self.required)
+            .field("validators", &self.validators.len())
+            .finish()
+    }
+}
+
+impl PartialEq for KeySchema {
+    /// Validators are only equal if they are the same instance
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty
+            && self.required == other.required
+            && self.validators.len() == other.validators.len()
+            && self
+                .validators
+                .iter()
+                .zip(&other.validators)
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// Type of a metadata value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "of", rename_all = "snake_case")]
+pub enum MetadataType {
+    /// Any text
+    Text,
+    /// Integer number, like `3`
+    Integer,
+    /// Any number, like `1.5`
+    Number,
+    /// `true` or `false`
+    Bool,
+    /// Duration, like `1h 30min` or a number of minutes
+    Duration,
+    /// Absolute URL
+    Url,
+    /// Date in the `YYYY-MM-DD` format
+    Date,
+    /// One of the values, case sensitive
+    Enum(Vec<String>),
+    /// Comma separated list of values
+    List(Box<MetadataType>),
+}
+
+impl MetadataType {
+    fn parse(&self, s: &str) -> Result<MetadataValue, SchemaError> {
+        let s = s.trim();
+        let invalid = || SchemaError::InvalidValue {
+            expected: self.clone(),
+        };
+        let value = match self {
+            MetadataType::Text => MetadataValue::Text(s.to_string()),
+            MetadataType::Integer => MetadataValue::Integer(s.parse().map_err(|_| invalid())?),
+            MetadataType::Number => {
+                let n = s.parse::<f64>().map_err(|_| invalid())?;
+                if !n.is_finite() {
+                    return Err(invalid());
+                }
+                MetadataValue::Number(n)
+            }
+            MetadataType::Bool => MetadataValue::Bool(s.parse().map_err(|_| invalid())?),
+            MetadataType::Duration => {
+                let d = match humantime::parse_duration(s) {
+                    Ok(d) => d,
+                    Err(_) => {
+                        let minutes = s.parse::<u64>().map_err(|_| invalid())?;
+                        Duration::from_secs(minutes.checked_mul(60).ok_or_else(invalid)?)
+                    }
+                };
+                MetadataValue::Duration(d)
+            }
+            MetadataType::Url => MetadataValue::Url(Url::parse(s).map_err(|_| invalid())?),
+            MetadataType::Date => MetadataValue::Date(s.parse().map_err(|_| invalid())?),
+            MetadataType::Enum(values) => {
+                if !values.iter().any(|v| v == s) {
+                    return Err(invalid());
+                }
+                MetadataValue::Text(s.to_string())
+            }
+            MetadataType::List(ty) => MetadataValue::List(
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| ty.parse(s))
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        Ok(value)
+    }
+}
+
+impl Display for MetadataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataType::Text => write!(f, "text"),
+            MetadataType::Integer => write!(f, "an integer"),
+            MetadataType::Number => write!(f, "a number"),
+            MetadataType::Bool => write!(f, "true or false"),
+            MetadataType::Duration => write!(f, "a duration"),
+            MetadataType::Url => write!(f, "a URL"),
+            MetadataType::Date => write!(f, "a date (YYYY-MM-DD)"),
+            MetadataType::Enum(values) => write!(f, "one of: {}", values.join(", ")),
+            MetadataType::List(ty) => write!(f, "a comma separated list of {ty}"),
+        }
+    }
+}
+
+/// Parsed metadata value
+///
+/// [`MetadataType::Enum`] values are [`MetadataValue::Text`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum MetadataValue {
+    Text(String),
+    Integer(i64),
+    Number(f64),
+    Bool(bool),
+    Duration(Duration),
+    Url(Url),
+    Date(Date),
+    List(Vec<MetadataValue>),
+}
+
+/// Calendar date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// Creates a new date, if it exists
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        (1..=days)
+            .contains(&day)
+            .then_some(Self { year, month, day })
+    }
+
+    /// Get the year
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Get the month, from 1 to 12
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Get the day of the month, starting at 1
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+impl std::str::FromStr for Date {
+    type Err = SchemaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SchemaError::InvalidValue {
+            expected: MetadataType::Date,
+        };
+        let mut parts = s.trim().splitn(3, '-');
+        let mut next = |len: usize| {
+            parts
+                .next()
+                .filter(|p| p.len() == len && p.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|p| p.parse::<u16>().ok())
+                .ok_or_else(invalid)
+        };
+        let (year, month, day) = (next(4)?, next(2)?, next(2)?);
+        Date::new(year, month as u8, day as u8).ok_or_else(invalid)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Error validating a metadata value
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum SchemaError {
+    #[error("Expected {expected}")]
+    InvalidValue { expected: MetadataType },
+
+    #[error("Invalid value: {reason}")]
+    Validation { reason: String },
+}
+
+/// Types that can be taken out of a [`MetadataValue`]
+///
+/// Used by [`Metadata::get`](crate::Metadata::get).
+pub trait FromMetadataValue: Sized {
+    /// Converts the value, if it's the right type
+    fn from_metadata_value(value: &MetadataValue) -> Option<Self>;
+}
+
+macro_rules! from_metadata_value {
+    ($t:ty, $variant:ident, $v:ident => $conv:expr) => {
+        impl FromMetadataValue for $t {
+            fn from_metadata_value(value: &MetadataValue) -> Option<Self> {
+                match value {
+                    MetadataValue::$variant($v) => $conv,
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+from_metadata_value!(String, Text, s => Some(s.clone()));
+from_metadata_value!(i64, Integer, n => Some(*n));
+from_metadata_value!(u32, Integer, n => u32::try_from(*n).ok());
+from_metadata_value!(bool, Bool, b => Some(*b));
+from_metadata_value!(Duration, Duration, d => Some(*d));
+from_metadata_value!(Url, Url, u => Some(u.clone()));
+from_metadata_value!(Date, Date, d => Some(*d));
+
+impl FromMetadataValue for f64 {
+    /// Integers are converted too
+    fn from_metadata_value(value: &MetadataValue) -> Option<Self> {
+        match value {
+            MetadataValue::Number(n) => Some(*n),
+            MetadataValue::Integer(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromMetadataValue> FromMetadataValue for Vec<T> {
+    fn from_metadata_value(value: &MetadataValue) -> Option<Self> {
+        match value {
+            MetadataValue::List(values) => values.iter().map(T::from_metadata_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl FromMetadataValue for MetadataValue {
+    fn from_metadata_value(value: &MetadataValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(MetadataType::Integer, "42" => Ok(MetadataValue::Integer(42)); "integer value")]
+    #[test_case(MetadataType::Integer, "4.2" => matches Err(_); "not integer")]
+    #[test_case(MetadataType::Number, "4.2" => Ok(MetadataValue::Number(4.2)); "number value")]
+    #[test_case(MetadataType::Number, "inf" => matches Err(_); "infinite")]
+    #[test_case(MetadataType::Bool, "true" => Ok(MetadataValue::Bool(true)); "bool value")]
+    #[test_case(MetadataType::Duration, "1h 30min" => Ok(MetadataValue::Duration(Duration::from_secs(90 * 60))); "duration value")]
+    #[test_case(MetadataType::Duration, "15" => Ok(MetadataValue::Duration(Duration::from_secs(15 * 60))); "minutes")]
+    #[test_case(MetadataType::Duration, "400000000000000000" => matches Err(_); "minutes overflow")]
+    #[test_case(MetadataType::Url, "example.com" => matches Err(_); "relative url")]
+    #[test_case(MetadataType::Date, "2024-02-29" => Ok(MetadataValue::Date(Date::new(2024, 2, 29).unwrap())); "leap day")]
+    #[test_case(MetadataType::Date, "2023-02-29" => matches Err(_); "not leap year")]
+    #[test_case(MetadataType::Date, "2023-2-01" => matches Err(_); "short month")]
+    #[test_case(MetadataType::Enum(vec!["a".into(), "b".into()]), "b" => Ok(MetadataValue::Text("b".into())); "enum value")]
+    #[test_case(MetadataType::Enum(vec!["a".into(), "b".into()]), "c" => matches Err(_); "not in enum")]
+    #[test_case(MetadataType::List(Box::new(MetadataType::Integer)), "1, 2,3" => Ok(MetadataValue::List(vec![MetadataValue::Integer(1), MetadataValue::Integer(2), MetadataValue::Integer(3)])); "list of integers")]
+    #[test_case(MetadataType::List(Box::new(MetadataType::Integer)), "1, a" => matches Err(_); "invalid list")]
+    fn parse(ty: MetadataType, s: &str) -> Result<MetadataValue, SchemaError> {
+        KeySchema::new(ty).parse(s)
+    }
+
+    #[test]
+    fn validators() {
+        let schema = KeySchema::new(MetadataType::Integer)
+            .validate_with(|v| match v {
+                MetadataValue::Integer(n) if *n % 2 == 0 => Ok(()),
+                _ => Err("must be even".into()),
+            })
+            .validate_with(|v| match v {
+                MetadataValue::Integer(n) if *n > 0 => Ok(()),
+                _ => Err("must be positive".into()),
+            });
+        assert_eq!(schema.parse("2"), Ok(MetadataValue::Integer(2)));
+        assert_eq!(
+            schema.parse("3"),
+            Err(SchemaError::Validation {
+                reason: "must be even".into()
+            })
+        );
+        assert_eq!(
+            schema.parse("-2"),
+            Err(SchemaError::Validation {
+                reason: "must be positive".into()
+            })
+        );
+        assert_eq!(schema, schema.clone());
+        assert_ne!(schema, KeySchema::new(MetadataType::Integer));
+    }
+
+    #[test]
+    fn from_value() {
+        let list = MetadataValue::List(vec![MetadataValue::Integer(1), MetadataValue::Integer(2)]);
+        assert_eq!(Vec::<i64>::from_metadata_value(&list), Some(vec![1, 2]));
+        assert_eq!(Vec::<f64>::from_metadata_value(&list), Some(vec![1.0, 2.0]));
+        assert_eq!(Vec::<String>::from_metadata_value(&list), None);
+        assert_eq!(u32::from_metadata_value(&MetadataValue::Integer(-1)), None);
+    }
+}
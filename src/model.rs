@@ -14,16 +14,21 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     ast::Modifiers,
-    convert::Converter,
+    convert::{Converter, Density},
     metadata::Metadata,
     quantity::{Quantity, QuantityAddError, QuantityValue},
+    span::Span,
     GroupedQuantity,
 };
 
 /// A complete recipe
 ///
-/// A recipe can be [scaled](Self::scale) (only once) and [converted](Self::convert)
+/// A recipe can be [scaled](Self::scale) and [converted](Self::convert)
+///
+/// The locations in the source are not compared in [`PartialEq`], so the
+/// same recipe written in different ways is equal.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Recipe<D = ()> {
     /// Recipe name
     pub name: String,
@@ -47,24 +52,43 @@ pub struct Recipe<D = ()> {
 
 /// A recipe after being scaled
 ///
-/// Note that this doesn't implement [`Recipe::scale`]. A recipe can only be
-/// scaled once.
+/// Note that this doesn't implement [`Recipe::scale`]. To scale it again use
+/// [`ScaledRecipe::rescale`] or [`ScaledRecipe::unscale`].
 pub type ScaledRecipe = Recipe<crate::scale::Scaled>;
 
 /// A section holding steps
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Section {
     /// Name of the section
     pub name: Option<String>,
     /// Steps inside
     pub steps: Vec<Step>,
+    /// Location of the section name in the source
+    ///
+    /// [`None`] if the section has no name or the recipe was not parsed from
+    /// a source.
+    #[serde(default)]
+    pub span: Option<Span>,
+}
+
+impl PartialEq for Section {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            steps,
+            span: _,
+        } = self;
+        *name == other.name && *steps == other.steps
+    }
 }
 
 impl Section {
-    pub(crate) fn new(name: Option<String>) -> Section {
+    pub(crate) fn new(name: Option<String>, span: Option<Span>) -> Section {
         Self {
             name,
             steps: Vec::new(),
+            span,
         }
     }
 
@@ -77,7 +101,8 @@ impl Section {
 }
 
 /// A step holding step [`Item`]s
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 pub struct Step {
     /// [`Item`]s inside
@@ -89,6 +114,23 @@ pub struct Step {
     /// text step. Text steps do not have a number. If this is not a text step,
     /// it will always be [`Some`].
     pub number: Option<u32>,
+
+    /// Location of the step in the source, from the first to the last item
+    ///
+    /// [`None`] if the recipe was not parsed from a source.
+    #[serde(default)]
+    pub span: Option<Span>,
+}
+
+impl PartialEq for Step {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            items,
+            number,
+            span: _,
+        } = self;
+        *items == other.items && *number == other.number
+    }
 }
 
 impl Step {
@@ -104,6 +146,7 @@ impl Step {
 
 /// A step item
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Item {
     /// Just plain text
@@ -118,7 +161,8 @@ pub enum Item {
 }
 
 /// A recipe ingredient
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Ingredient {
     /// Name
     ///
@@ -132,6 +176,11 @@ pub struct Ingredient {
     pub note: Option<String>,
     /// How the cookware is related to others
     pub relation: IngredientRelation,
+    /// Location of the component in the source
+    ///
+    /// [`None`] if the recipe was not parsed from a source.
+    #[serde(default)]
+    pub span: Option<Span>,
     pub(crate) modifiers: Modifiers,
     // ? maybe move this into analysis?, is not needed in the model
     // ? however I will keep it here for now. Because of alignment it does
@@ -140,9 +189,31 @@ pub struct Ingredient {
     pub(crate) defined_in_step: bool,
 }
 
+impl PartialEq for Ingredient {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            alias,
+            quantity,
+            note,
+            relation,
+            span: _,
+            modifiers,
+            defined_in_step,
+        } = self;
+        *name == other.name
+            && *alias == other.alias
+            && *quantity == other.quantity
+            && *note == other.note
+            && *relation == other.relation
+            && *modifiers == other.modifiers
+            && *defined_in_step == other.defined_in_step
+    }
+}
+
 impl Ingredient {
     /// Gets the name the ingredient should be displayed with
-    pub fn display_name(&self) -> Cow<str> {
+    pub fn display_name(&self) -> Cow<'_, str> {
         let mut name = Cow::from(&self.name);
         if self.modifiers.contains(Modifiers::RECIPE) {
             if let Some(recipe_name) = std::path::Path::new(&self.name)
@@ -167,15 +238,37 @@ impl Ingredient {
         all_ingredients: &'a [Self],
         converter: &Converter,
     ) -> Result<Option<Quantity>, QuantityAddError> {
+        self.total_quantity_with_densities(all_ingredients, converter)
+            .map(|(total, _)| total)
+    }
+
+    /// Same as [`Self::total_quantity`], but also returns the densities used
+    /// to add volumes and masses together
+    ///
+    /// Each one appears once, even if it was used many times.
+    pub fn total_quantity_with_densities<'a>(
+        &'a self,
+        all_ingredients: &'a [Self],
+        converter: &Converter,
+    ) -> Result<(Option<Quantity>, Vec<Density>), QuantityAddError> {
         let mut quantities = self.all_quantities(all_ingredients);
+        let mut densities = Vec::new();
 
-        let Some(mut total) = quantities.next().cloned() else { return Ok(None); };
+        let Some(mut total) = quantities.next().cloned() else {
+            return Ok((None, densities));
+        };
         for q in quantities {
-            total = total.try_add(q, converter)?;
+            let density;
+            (total, density) = total.try_add_ingredient(q, &self.name, converter)?;
+            if let Some(density) = density {
+                if !densities.contains(&density) {
+                    densities.push(density);
+                }
+            }
         }
         let _ = total.fit(converter);
 
-        Ok(Some(total))
+        Ok((Some(total), densities))
     }
 
     /// Groups all quantities from itself and it's references (if any).
@@ -210,7 +303,7 @@ impl Ingredient {
         all_ingredients: &[Self],
         converter: &Converter,
     ) -> GroupedQuantity {
-        let mut grouped = GroupedQuantity::default();
+        let mut grouped = GroupedQuantity::for_ingredient(&self.name);
         for q in self.all_quantities(all_ingredients) {
             grouped.add(q, converter);
         }
@@ -222,7 +315,7 @@ impl Ingredient {
     pub fn all_quantities<'a>(
         &'a self,
         all_ingredients: &'a [Self],
-    ) -> impl Iterator<Item = &Quantity> {
+    ) -> impl Iterator<Item = &'a Quantity> {
         std::iter::once(self.quantity.as_ref())
             .chain(
                 self.relation
@@ -236,7 +329,8 @@ impl Ingredient {
 }
 
 /// A recipe cookware item
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Cookware {
     /// Name
     pub name: String,
@@ -250,9 +344,34 @@ pub struct Cookware {
     pub note: Option<String>,
     /// How the cookware is related to others
     pub relation: ComponentRelation,
+    /// Location of the component in the source
+    ///
+    /// [`None`] if the recipe was not parsed from a source.
+    #[serde(default)]
+    pub span: Option<Span>,
     pub(crate) modifiers: Modifiers,
 }
 
+impl PartialEq for Cookware {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            alias,
+            quantity,
+            note,
+            relation,
+            span: _,
+            modifiers,
+        } = self;
+        *name == other.name
+            && *alias == other.alias
+            && *quantity == other.quantity
+            && *note == other.note
+            && *relation == other.relation
+            && *modifiers == other.modifiers
+    }
+}
+
 impl Cookware {
     /// Gets the name the cookware item should be displayed with
     pub fn display_name(&self) -> &str {
@@ -267,6 +386,7 @@ impl Cookware {
 
 /// Relation between components
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum ComponentRelation {
     /// The component is a definition
@@ -315,6 +435,7 @@ impl ComponentRelation {
 /// Same as [`ComponentRelation`] but with the ability to reference steps and
 /// sections apart from other ingredients.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct IngredientRelation {
     #[serde(flatten)]
     relation: ComponentRelation,
@@ -325,6 +446,7 @@ pub struct IngredientRelation {
 ///
 /// This is obtained from [IngredientRelation::references_to]
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum IngredientReferenceTarget {
     /// Ingredient definition
     #[serde(rename = "ingredient")]
@@ -406,7 +528,8 @@ impl IngredientRelation {
 ///
 /// If created from parsing, at least one of the fields is guaranteed to be
 /// [`Some`].
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Timer {
     /// Name
     pub name: Option<String>,
@@ -415,15 +538,32 @@ pub struct Timer {
     /// If created from parsing the following applies:
     ///
     /// - If the [`ADVANCED_UNITS`](crate::Extensions::ADVANCED_UNITS) extension
-    /// is enabled, this is guaranteed to have a time unit.
+    ///   is enabled, this is guaranteed to have a time unit.
     ///
     /// - If the [`TIMER_REQUIRES_TIME`](crate::Extensions::TIMER_REQUIRES_TIME)
-    /// extension is enabled, this is guaranteed to be [`Some`].
+    ///   extension is enabled, this is guaranteed to be [`Some`].
     pub quantity: Option<Quantity>,
+    /// Location of the component in the source
+    ///
+    /// [`None`] if the recipe was not parsed from a source.
+    #[serde(default)]
+    pub span: Option<Span>,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            quantity,
+            span: _,
+        } = self;
+        *name == other.name && *quantity == other.quantity
+    }
 }
 
 /// A component reference
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Component {
     /// What kind of component is
     pub kind: ComponentKind,
@@ -433,6 +573,7 @@ pub struct Component {
 
 /// Component kind used in [`Component`]
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ComponentKind {
     #[serde(rename = "ingredient")]
     IngredientKind,
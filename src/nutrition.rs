@@ -0,0 +1,417 @@
+//! Nutrition facts of recipes
+//!
+//! The nutrients of each ingredient are looked up in a [`NutrientDatabase`]
+//! provided by the user, with the amount of each nutrient in 100 grams. The
+//! quantities of the ingredients are converted to grams with the
+//! [`Converter`]. Volumes can only be converted if the converter knows the
+//! [density](crate::convert::DensityFile) of the ingredient.
+//!
+//! The database is usually loaded from a [`NutritionFile`], designed for
+//! [TOML](https://toml.io/en/) like [`UnitsFile`](crate::convert::UnitsFile),
+//! or from CSV with [`NutrientDatabase::from_csv`] and the `nutrition_csv`
+//! [feature](crate::_features).
+//!
+//! ```toml
+//! [ingredient.flour]
+//! per_100g = { kcal = 364, protein = 10.3, fat = 1, carbs = 76 }
+//!
+//! [ingredient.egg]
+//! aliases = ["eggs"]
+//! # grams of one egg, used when there is no unit or with count units
+//! piece = 50
+//! per_100g = { kcal = 143, protein = 12.6, fat = 9.5, carbs = 0.7 }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    convert::{Converter, PhysicalQuantity},
+    quantity::{Quantity, Value},
+    ScaledRecipe,
+};
+
+/// Amount of each nutrient
+///
+/// The nutrients can be anything, like `kcal`, `protein`, `fat` or `carbs`,
+/// as long as the same names are used in all the database.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Nutrients(BTreeMap<String, f64>);
+
+impl Nutrients {
+    /// Empty, no nutrients
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the amount of a nutrient
+    pub fn get(&self, nutrient: &str) -> Option<f64> {
+        self.0.get(nutrient).copied()
+    }
+
+    /// Set the amount of a nutrient
+    pub fn set(&mut self, nutrient: impl Into<String>, amount: f64) {
+        self.0.insert(nutrient.into(), amount);
+    }
+
+    /// Iterate over all nutrients sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.0.iter().map(|(n, a)| (n.as_str(), *a))
+    }
+
+    /// Checks if there are no nutrients
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the amounts of `other` to these
+    pub fn add(&mut self, other: &Nutrients) {
+        for (nutrient, amount) in other.iter() {
+            *self.0.entry(nutrient.to_string()).or_default() += amount;
+        }
+    }
+
+    /// Multiplies all the amounts by `factor`
+    pub fn scaled(&self, factor: f64) -> Nutrients {
+        Nutrients(
+            self.0
+                .iter()
+                .map(|(n, a)| (n.clone(), a * factor))
+                .collect(),
+        )
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, f64)> for Nutrients {
+    fn from_iter<T: IntoIterator<Item = (S, f64)>>(iter: T) -> Self {
+        Self(iter.into_iter().map(|(n, a)| (n.into(), a)).collect())
+    }
+}
+
+/// Nutrition database file
+///
+/// This structure is designed for deserializing TOML, but you can try other
+/// formats supported by serde.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NutritionFile {
+    /// Ingredients by name
+    #[serde(default)]
+    pub ingredient: BTreeMap<String, NutrientEntry>,
+}
+
+/// An ingredient in a [`NutritionFile`]
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NutrientEntry {
+    /// Other names for the ingredient
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Grams of one piece, used when the quantity has no unit or a count unit
+    pub piece: Option<f64>,
+    /// Nutrients in 100 grams
+    pub per_100g: Nutrients,
+}
+
+/// Lookup table of the nutrients of ingredients
+///
+/// Names are case insensitive.
+#[derive(Debug, Default, Clone)]
+pub struct NutrientDatabase {
+    entries: Vec<NutrientEntry>,
+    index: HashMap<String, usize>,
+}
+
+impl NutrientDatabase {
+    /// Empty database
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Database with the ingredients of a file
+    pub fn from_file(file: NutritionFile) -> Self {
+        let mut db = Self::new();
+        for (name, entry) in file.ingredient {
+            db.insert(name, entry);
+        }
+        db
+    }
+
+    /// Parses a CSV database
+    ///
+    /// The first line is the header. The first column is the name of the
+    /// ingredient, an optional `piece` column is [`NutrientEntry::piece`]
+    /// and the rest are the nutrients in 100 grams. Empty cells and lines are
+    /// skipped. Cells can be quoted to have commas in them.
+    ///
+    /// ```
+    /// # use cooklang::nutrition::NutrientDatabase;
+    /// let db = NutrientDatabase::from_csv("\
+    /// name,piece,kcal,protein
+    /// flour,,364,10.3
+    /// egg,50,143,12.6
+    /// \"cheese, grated\",,402,25
+    /// ").unwrap();
+    /// assert_eq!(db.get("Egg").unwrap().piece, Some(50.0));
+    /// assert_eq!(db.get("flour").unwrap().per_100g.get("kcal"), Some(364.0));
+    /// assert!(db.get("cheese, grated").is_some());
+    /// ```
+    #[cfg(feature = "nutrition_csv")]
+    pub fn from_csv(csv: &str) -> Result<Self, NutritionFileError> {
+        // the position is before the empty lines the reader skips
+        let line_at = |pos: Option<&csv::Position>| {
+            let start = pos.map_or(0, |p| p.byte() as usize).min(csv.len());
+            let rest = &csv[start..];
+            let start = start + rest.len() - rest.trim_start_matches(['\r', '\n']).len();
+            csv[..start].matches('\n').count() + 1
+        };
+        let csv_error = |e: csv::Error| NutritionFileError::Csv {
+            line: line_at(e.position()),
+            message: e.to_string(),
+        };
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(csv.as_bytes());
+        let header = reader.headers().map_err(csv_error)?.clone();
+        if header.is_empty() {
+            return Ok(Self::new());
+        }
+
+        let mut db = Self::new();
+        for row in reader.records() {
+            let row = row.map_err(csv_error)?;
+            if row.iter().all(str::is_empty) {
+                continue;
+            }
+            let line = line_at(row.position());
+            if row.len() != header.len() {
+                return Err(NutritionFileError::Csv {
+                    line,
+                    message: format!("expected {} columns, found {}", header.len(), row.len()),
+                });
+            }
+            let mut entry = NutrientEntry::default();
+            for (column, cell) in header.iter().zip(&row).skip(1) {
+                if cell.is_empty() {
+                    continue;
+                }
+                let value = cell.parse::<f64>().map_err(|e| NutritionFileError::Csv {
+                    line,
+                    message: format!("invalid number in column '{column}': {e}"),
+                })?;
+                if column == "piece" {
+                    entry.piece = Some(value);
+                } else {
+                    entry.per_100g.set(column, value);
+                }
+            }
+            db.insert(row[0].to_string(), entry);
+        }
+        Ok(db)
+    }
+
+    /// Adds an ingredient, replacing any other with the same name or aliases
+    pub fn insert(&mut self, name: String, entry: NutrientEntry) {
+        let index = self.entries.len();
+        for name in std::iter::once(&name).chain(&entry.aliases) {
+            self.index.insert(name.to_lowercase(), index);
+        }
+        self.entries.push(entry);
+    }
+
+    /// Get an ingredient by name or alias
+    pub fn get(&self, name: &str) -> Option<&NutrientEntry> {
+        self.index
+            .get(&name.to_lowercase())
+            .map(|&i| &self.entries[i])
+    }
+}
+
+/// Error loading a nutrition database
+#[derive(Debug, Error)]
+pub enum NutritionFileError {
+    #[error("Error in CSV line {line}: {message}")]
+    Csv { line: usize, message: String },
+}
+
+/// Nutrition facts of a recipe
+#[derive(Debug, Clone, PartialEq)]
+pub struct NutritionFacts {
+    /// Nutrients of the whole recipe
+    pub total: Nutrients,
+    /// Nutrients of one serving, if the number of servings is known
+    pub per_serving: Option<Nutrients>,
+    /// Servings used to calculate [`Self::per_serving`]
+    pub servings: Option<u32>,
+    /// Ingredients that could not be included
+    pub warnings: Vec<NutritionWarning>,
+}
+
+/// An ingredient that could not be included in the [`NutritionFacts`]
+///
+/// `index` is the index of the ingredient in
+/// [`Recipe::ingredients`](crate::Recipe::ingredients).
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum NutritionWarning {
+    #[error("Ingredient not in the database: '{name}'")]
+    UnknownIngredient { index: usize, name: String },
+    #[error("Ingredient '{name}' has no quantity")]
+    NoQuantity { index: usize, name: String },
+    #[error("Ingredient '{name}' has a text value: '{value}'")]
+    TextValue {
+        index: usize,
+        name: String,
+        value: String,
+    },
+    #[error("Can't convert the quantity of '{name}' to grams: '{quantity}'")]
+    IncompatibleUnit {
+        index: usize,
+        name: String,
+        quantity: Quantity,
+    },
+}
+
+impl ScaledRecipe {
+    /// Calculates the nutrition facts
+    ///
+    /// The scaled quantities are used, so the facts are for the servings the
+    /// recipe was scaled to. Range values use the middle of the range.
+    ///
+    /// Ingredients are identified by their name, and references are included
+    /// with the ingredient they reference. Hidden ingredients are included
+    /// too, as they are still eaten.
+    ///
+    /// ```
+    /// # use cooklang::{CooklangParser, nutrition::*};
+    /// let file: NutritionFile = toml::from_str(r#"
+    ///     ingredient.flour = { per_100g = { kcal = 364 } }
+    ///     ingredient.egg = { piece = 50, per_100g = { kcal = 143 } }
+    /// "#).unwrap();
+    /// let db = NutrientDatabase::from_file(file);
+    /// let parser = CooklangParser::default();
+    /// let recipe = parser
+    ///     .parse(">> servings: 2\n@flour{200*%g} and @egg{1*}", "Crepes")
+    ///     .into_output()
+    ///     .unwrap()
+    ///     .scale(4, parser.converter());
+    ///
+    /// let facts = recipe.nutrition(&db, parser.converter());
+    /// assert!(facts.warnings.is_empty());
+    /// assert_eq!(facts.total.get("kcal"), Some(4.0 * 364.0 + 143.0));
+    /// assert_eq!(facts.servings, Some(4));
+    /// ```
+    pub fn nutrition(&self, db: &NutrientDatabase, converter: &Converter) -> NutritionFacts {
+        let mut total = Nutrients::new();
+        let mut warnings = Vec::new();
+
+        for (index, igr) in self.ingredients.iter().enumerate() {
+            // references to steps or sections are not new ingredients
+            if igr.relation.is_intermediate_reference() {
+                continue;
+            }
+            let name = igr.display_name().into_owned();
+
+            let Some(entry) = db.get(&name).or_else(|| db.get(&igr.name)) else {
+                // only warn once for all the references
+                if igr.relation.is_definition() {
+                    warnings.push(NutritionWarning::UnknownIngredient { index, name });
+                }
+                continue;
+            };
+            let Some(quantity) = &igr.quantity else {
+                // a reference without quantity just mentions the ingredient
+                if igr.relation.is_definition() {
+                    warnings.push(NutritionWarning::NoQuantity { index, name });
+                }
+                continue;
+            };
+
+            match grams(quantity, &igr.name, entry, converter) {
+                Ok(grams) => total.add(&entry.per_100g.scaled(grams / 100.0)),
+                Err(GramsError::Text(value)) => {
+                    warnings.push(NutritionWarning::TextValue { index, name, value })
+                }
+                Err(GramsError::Incompatible) => {
+                    warnings.push(NutritionWarning::IncompatibleUnit {
+                        index,
+                        name,
+                        quantity: quantity.clone(),
+                    })
+                }
+            }
+        }
+
+        // scaled by other target, only if it results in whole servings
+        let servings = self.scaled_servings().filter(|&s| s > 0);
+        let per_serving = servings.map(|s| total.scaled(1.0 / s as f64));
+
+        NutritionFacts {
+            total,
+            per_serving,
+            servings,
+            warnings,
+        }
+    }
+}
+
+enum GramsError {
+    Text(String),
+    Incompatible,
+}
+
+/// Converts a quantity of an ingredient to grams
+fn grams(
+    quantity: &Quantity,
+    ingredient: &str,
+    entry: &NutrientEntry,
+    converter: &Converter,
+) -> Result<f64, GramsError> {
+    let value = |value: &Value| match value {
+        Value::Number { value } => Ok(*value),
+        Value::Fraction { value } => Ok(value.to_f64()),
+        Value::Range { value } => Ok((value.start() + value.end()) / 2.0),
+        Value::Text { value } => Err(GramsError::Text(value.clone())),
+    };
+    let amount = value(
+        quantity
+            .value
+            .extract_value()
+            .map_err(|_| GramsError::Incompatible)?,
+    )?;
+
+    let Some(unit) = quantity.unit() else {
+        return entry
+            .piece
+            .map(|piece| amount * piece)
+            .ok_or(GramsError::Incompatible);
+    };
+    match unit.unit_info_or_parse(converter) {
+        crate::UnitInfo::Known(u)
+            if matches!(
+                u.physical_quantity,
+                PhysicalQuantity::Mass | PhysicalQuantity::Volume
+            ) =>
+        {
+            // volumes need a known density
+            let (converted, _) = converter
+                .convert_ingredient(quantity, "g", ingredient)
+                .map_err(|_| GramsError::Incompatible)?;
+            value(
+                converted
+                    .value
+                    .extract_value()
+                    .map_err(|_| GramsError::Incompatible)?,
+            )
+        }
+        crate::UnitInfo::Known(u) if u.physical_quantity == PhysicalQuantity::Count => {
+            let piece = entry.piece.ok_or(GramsError::Incompatible)?;
+            // the ratio of a count unit is the number of pieces in it
+            Ok(amount * u.ratio * piece)
+        }
+        _ => Err(GramsError::Incompatible),
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    ast,
+    cst::{Cst, Element, Node, NodeKind},
+    error::PassResult,
+    lexer::T,
+    span::Span,
+    Extensions,
+};
+
+use super::{mt, parse_lines, ParsedLine, Parser, ParserError, ParserWarning, Token, TokenStream};
+
+/// Parse a recipe into a lossless [`Cst`]
+///
+/// The errors and warnings are the same as [`parse`](super::parse). Even with
+/// errors, the tree is always built.
+#[tracing::instrument(level = "debug", skip_all, fields(len = input.len()))]
+pub fn parse_cst(
+    input: &str,
+    extensions: Extensions,
+) -> PassResult<Cst<'_>, ParserError, ParserWarning> {
+    let tokens: Vec<Token> = TokenStream::new(input).collect();
+
+    let mut lines = Vec::new();
+    let result = parse_lines(Parser::new(input, extensions), |line, parsed| {
+        lines.push((line.len(), parsed))
+    });
+    let (ast, warnings, errors) = result.into_tuple();
+    let ast = ast.expect("parser always generates an ast");
+
+    let mut components = ast
+        .lines
+        .iter()
+        .filter_map(|l| match l {
+            ast::Line::Step { items, .. } => Some(items),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|item| match item {
+            ast::Item::Component(c) => Some((c.span(), component_kind(c))),
+            ast::Item::Text(_) => None,
+        })
+        .peekable();
+    let mut ast_lines = ast.lines.iter();
+
+    let mut root: Vec<Element> = Vec::new();
+    let mut pos = 0;
+    for (len, parsed) in lines {
+        let line = &tokens[pos..pos + len];
+        pos += len;
+
+        match parsed {
+            ParsedLine::Empty => root.extend(line.iter().copied().map(Element::Token)),
+            ParsedLine::New => {
+                let node = match ast_lines.next().expect("ast line") {
+                    ast::Line::Metadata { .. } => Node::new(NodeKind::Metadata, as_elements(line)),
+                    ast::Line::Section { .. } => Node::new(NodeKind::Section, as_elements(line)),
+                    ast::Line::Step { .. } => {
+                        let mut children = Vec::new();
+                        step_children(line, &mut components, &mut children);
+                        Node::new(NodeKind::Step, children)
+                    }
+                };
+                root.push(Element::Node(node));
+            }
+            ParsedLine::Joined => {
+                // the line break(s) after the step are now part of it
+                let last_node = root
+                    .iter()
+                    .rposition(|e| e.as_node().is_some())
+                    .expect("joined line without a step");
+                let line_breaks = root.split_off(last_node + 1);
+                let Some(Element::Node(step)) = root.last_mut() else {
+                    unreachable!()
+                };
+                debug_assert_eq!(step.kind, NodeKind::Step);
+                let mut children = std::mem::take(&mut step.children);
+                children.extend(line_breaks);
+                step_children(line, &mut components, &mut children);
+                *step = Node::new(NodeKind::Step, children);
+            }
+        }
+
+        if let Some(&t) = tokens.get(pos) {
+            if matches!(t.kind, T![newline] | T![eof]) {
+                root.push(Element::Token(t));
+                pos += 1;
+            }
+        }
+    }
+    debug_assert_eq!(pos, tokens.len(), "tokens left out of the cst");
+
+    let mut root = Node::new(NodeKind::Recipe, root);
+    root.span = Span::new(0, input.len());
+    let cst = Cst {
+        input,
+        extensions,
+        root,
+    };
+    PassResult::new(Some(cst), warnings, errors)
+}
+
+fn component_kind(c: &ast::Component) -> NodeKind {
+    match c {
+        ast::Component::Ingredient(_) => NodeKind::Ingredient,
+        ast::Component::Cookware(_) => NodeKind::Cookware,
+        ast::Component::Timer(_) => NodeKind::Timer,
+    }
+}
+
+fn as_elements(tokens: &[Token]) -> Vec<Element> {
+    tokens.iter().copied().map(Element::Token).collect()
+}
+
+fn step_children(
+    line: &[Token],
+    components: &mut std::iter::Peekable<impl Iterator<Item = (Span, NodeKind)>>,
+    out: &mut Vec<Element>,
+) {
+    let mut line = line;
+    if let Some((marker @ mt![>], rest)) = line.split_first() {
+        out.push(Element::Token(*marker));
+        line = rest;
+    }
+
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let component = components
+            .peek()
+            .copied()
+            .filter(|(span, _)| span.start() == line[i].span.start());
+        let Some((span, kind)) = component else {
+            i += 1;
+            continue;
+        };
+        components.next();
+        text_run(&line[text_start..i], out);
+        let len = line[i..]
+            .iter()
+            .take_while(|t| t.span.end() <= span.end())
+            .count();
+        out.push(Element::Node(Node::new(
+            kind,
+            as_elements(&line[i..i + len]),
+        )));
+        i += len;
+        text_start = i;
+    }
+    text_run(&line[text_start..], out);
+}
+
+/// Pushes the leading and trailing trivia as tokens and the rest as a text node
+fn text_run(tokens: &[Token], out: &mut Vec<Element>) {
+    let start = tokens
+        .iter()
+        .position(|t| !t.kind.is_trivia())
+        .unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(|t| !t.kind.is_trivia())
+        .map_or(start, |p| p + 1);
+    out.extend(as_elements(&tokens[..start]));
+    if start < end {
+        out.push(Element::Node(Node::new(
+            NodeKind::Text,
+            as_elements(&tokens[start..end]),
+        )));
+    }
+    out.extend(as_elements(&tokens[end..]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn kinds(node: &Node) -> Vec<NodeKind> {
+        node.nodes().map(|n| n.kind()).collect()
+    }
+
+    #[test]
+    fn lossless() {
+        let input = indoc! {r#"
+            >> servings: 2 -- comment
+
+            -- comment line
+            = Section [- block -]
+            > text step
+
+            Add @salt{1%tsp}(fine) to
+            the #pot.  -- trailing
+
+            ~{10%min} [- unclosed
+        "#};
+        let cst = parse_cst(input, Extensions::all()).into_output().unwrap();
+        assert_eq!(cst.to_string(), input);
+        assert_eq!(
+            kinds(cst.root()),
+            vec![
+                NodeKind::Metadata,
+                NodeKind::Section,
+                NodeKind::Step,
+                NodeKind::Step,
+                NodeKind::Step
+            ]
+        );
+
+        let step = cst.root().nodes().nth(3).unwrap();
+        assert_eq!(
+            kinds(step),
+            vec![
+                NodeKind::Text,
+                NodeKind::Ingredient,
+                NodeKind::Text,
+                NodeKind::Text,
+                NodeKind::Cookware,
+                NodeKind::Text
+            ]
+        );
+        assert_eq!(
+            cst.text(step.span()),
+            "Add @salt{1%tsp}(fine) to\nthe #pot.  -- trailing"
+        );
+        let trailing = step
+            .trailing_trivia()
+            .map(|t| cst.text(t.span))
+            .collect::<String>();
+        assert_eq!(trailing, "  -- trailing");
+    }
+
+    #[test]
+    fn only_trivia() {
+        let input = "-- a comment\n\n[- another -]\n";
+        let cst = parse_cst(input, Extensions::all()).into_output().unwrap();
+        let root = cst.root();
+        assert_eq!(root.nodes().count(), 0);
+        assert_eq!(root.leading_trivia().count(), root.children().len());
+        assert_eq!(root.trailing_trivia().count(), 0);
+    }
+
+    #[test]
+    fn to_ast() {
+        let input = indoc! {r#"
+            >> key: value
+            A @step -- comment
+            continues.
+
+            [- only a comment -]
+            == Section ==
+            Another #step{}.
+        "#};
+        for ext in [Extensions::all(), Extensions::empty()] {
+            let cst = parse_cst(input, ext).into_output().unwrap();
+            assert_eq!(cst.to_string(), input);
+            let from_cst = cst.to_ast().into_output().unwrap();
+            let parsed = super::super::parse(input, ext).into_output().unwrap();
+            assert_eq!(from_cst.lines, parsed.lines);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Cache of the parsed lines of an input to only parse again what an edit
+//! changes.
+//!
+//! The lines are the ones [`Parser::next_line`](super::Parser::next_line)
+//! generates. The lexer has no state between lines, so after an edit only the
+//! lines from the edit until the first untouched line start have to be lexed
+//! again. The rest keep their tokens and parsed [`ast::Line`], moved to the
+//! new locations.
+//!
+//! The only thing a line needs from the previous ones to be parsed is if it
+//! continues a text step, so it's parsed again only if that changes.
+//!
+//! The parsed lines can't borrow the input, which changes with every edit, so
+//! they are kept as a [`DetachedLine`]. The text of a line that was not
+//! touched by an edit is the same in the new input, only moved, so it's
+//! borrowed from there again when building the [`Ast`](ast::Ast).
+//!
+//! The cache also tells the first line of the [`Ast`](ast::Ast) that may have
+//! changed, so the analysis can be resumed from there.
+
+use std::ops::Range;
+
+use crate::{ast, context::Context, error::PassResult, lexer::T, span::Span, Extensions};
+
+use super::{
+    add_line, joins_text_step, mt, parse_line, LineParser, ParserError, ParserWarning, Token,
+    TokenStream,
+};
+
+#[derive(Debug, Clone)]
+pub(crate) struct LineCache {
+    extensions: Extensions,
+    lines: Vec<CachedLine>,
+    /// Index of the first [`ast::Line`] that may be different in the last
+    /// [`Ast`](ast::Ast) built
+    changed_from: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CachedLine {
+    /// Tokens of the line, line break included if any
+    tokens: Vec<Token>,
+    /// [`None`] until the line is parsed
+    parsed: Option<LineResult>,
+}
+
+#[derive(Debug, Clone)]
+struct LineResult {
+    /// `force_text` used to parse the line
+    force_text: bool,
+    line: Option<DetachedLine>,
+    errors: Vec<ParserError>,
+    warnings: Vec<ParserWarning>,
+}
+
+/// A parsed line that does not borrow the input
+///
+/// All the texts in `line` are empty. Their locations are in `texts`, in the
+/// order [`ast::Line::map_text`] visits them.
+#[derive(Debug, Clone)]
+struct DetachedLine {
+    line: ast::Line<'static>,
+    texts: Vec<TextLocation>,
+}
+
+#[derive(Debug, Clone)]
+struct TextLocation {
+    offset: usize,
+    fragments: Vec<Span>,
+}
+
+impl LineCache {
+    pub(crate) fn new(input: &str, extensions: Extensions) -> Self {
+        Self {
+            extensions,
+            lines: lex_lines(input, 0).collect(),
+            changed_from: 0,
+        }
+    }
+
+    pub(crate) fn extensions(&self) -> Extensions {
+        self.extensions
+    }
+
+    /// Index of the first line of the last [`Ast`](ast::Ast) built that may
+    /// be different from the previous one. The lines before it are the same.
+    pub(crate) fn changed_from(&self) -> usize {
+        self.changed_from
+    }
+
+    /// Updates the lines after replacing `range` of the old input with
+    /// `new_len` bytes. `input` is the input after the edit.
+    pub(crate) fn edit(&mut self, input: &str, range: Range<usize>, new_len: usize) {
+        let delta = new_len as isize - range.len() as isize;
+        let edit_end = range.start + new_len;
+
+        // Start at the line before the edit, as the edit may change where
+        // the last token of that line ends
+        let first = self
+            .lines
+            .partition_point(|l| l.start() < range.start)
+            .saturating_sub(1);
+        let start = self.lines.get(first).map_or(0, CachedLine::start);
+
+        let mut new_lines = Vec::new();
+        let mut resync = self.lines.len();
+        for line in lex_lines(input, start) {
+            let end = line.end();
+            new_lines.push(line);
+            if end >= edit_end {
+                // after the edit, the input is the same, so if an old line
+                // started here, it and the rest are still valid
+                let old_end = (end as isize - delta) as usize;
+                if let Ok(i) = self.lines.binary_search_by_key(&old_end, CachedLine::start) {
+                    resync = i;
+                    break;
+                }
+            }
+        }
+
+        let mut rest = self.lines.split_off(resync);
+        rest.iter_mut().for_each(|l| l.shift(delta));
+        self.lines.truncate(first);
+        self.lines.append(&mut new_lines);
+        self.lines.append(&mut rest);
+    }
+
+    /// Builds the [`Ast`](ast::Ast), parsing the lines that need it.
+    ///
+    /// The result is the same as [`parse`](super::parse) with the `input`.
+    pub(crate) fn parse<'i>(
+        &mut self,
+        input: &'i str,
+    ) -> PassResult<ast::Ast<'i>, ParserError, ParserWarning> {
+        let mut context = Context::default();
+        let mut lines = Vec::new();
+        let mut last_empty = true;
+        let mut changed_from = None;
+        for cached in &mut self.lines {
+            let force_text = joins_text_step(&lines, last_empty, self.extensions);
+            if changed_from.is_none() && !cached.is_parsed(force_text) {
+                // if the last line is not empty, this one may be joined to it
+                let joined = !last_empty as usize;
+                changed_from = Some(lines.len() - joined);
+            }
+            let parsed = cached.parse(input, self.extensions, force_text);
+            add_line(
+                &mut lines,
+                parsed.line.as_ref().map(|l| l.attach(input)),
+                &mut last_empty,
+                self.extensions,
+            );
+            context.errors.extend(parsed.errors.iter().cloned());
+            context.warnings.extend(parsed.warnings.iter().cloned());
+        }
+        self.changed_from = changed_from.unwrap_or(lines.len());
+        context.finish(Some(ast::Ast { lines }))
+    }
+}
+
+impl CachedLine {
+    fn start(&self) -> usize {
+        self.tokens[0].span.start()
+    }
+
+    fn end(&self) -> usize {
+        self.tokens.last().unwrap().span.end()
+    }
+
+    fn shift(&mut self, delta: isize) {
+        self.tokens.iter_mut().for_each(|t| t.span.shift(delta));
+        if let Some(parsed) = &mut self.parsed {
+            if let Some(line) = &mut parsed.line {
+                line.shift(delta);
+            }
+            parsed.errors.iter_mut().for_each(|e| e.shift(delta));
+            parsed.warnings.iter_mut().for_each(|w| w.shift(delta));
+        }
+    }
+
+    /// If the line has a result for `force_text`
+    fn is_parsed(&self, force_text: bool) -> bool {
+        self.parsed
+            .as_ref()
+            .is_some_and(|p| p.force_text == force_text)
+    }
+
+    fn parse(&mut self, input: &str, extensions: Extensions, force_text: bool) -> &LineResult {
+        if !self.is_parsed(force_text) {
+            self.parsed = None;
+        }
+        let tokens = match self.tokens.split_last() {
+            Some((mt![newline | eof], rest)) => rest,
+            _ => &self.tokens,
+        };
+        let start = self.tokens[0].span.start();
+        self.parsed.get_or_insert_with(|| {
+            let mut line = LineParser::new(start, tokens, input, extensions);
+            let parsed = parse_line(&mut line, force_text).map(DetachedLine::new);
+            let context = line.finish();
+            LineResult {
+                force_text,
+                line: parsed,
+                errors: context.errors,
+                warnings: context.warnings,
+            }
+        })
+    }
+}
+
+impl DetachedLine {
+    fn new(line: ast::Line) -> Self {
+        let mut texts = Vec::new();
+        let line = line.map_text(&mut |text| {
+            texts.push(TextLocation {
+                offset: text.span().start(),
+                fragments: text.fragments().iter().map(|f| f.span()).collect(),
+            });
+            ast::Text::empty(0)
+        });
+        Self { line, texts }
+    }
+
+    /// Builds the line again borrowing the texts from `input`
+    fn attach<'i>(&self, input: &'i str) -> ast::Line<'i> {
+        let mut texts = self.texts.iter();
+        self.line.clone().map_text(&mut |_| {
+            let location = texts.next().expect("location for every text");
+            let mut text = ast::Text::empty(location.offset);
+            for span in &location.fragments {
+                text.append_str(&input[span.range()], span.start());
+            }
+            text
+        })
+    }
+
+    fn shift(&mut self, delta: isize) {
+        // the texts in the line are empty, only the rest of the locations
+        // are moved there
+        self.line.shift(delta);
+        for text in &mut self.texts {
+            text.offset = text.offset.wrapping_add_signed(delta);
+            text.fragments.iter_mut().for_each(|s| s.shift(delta));
+        }
+    }
+}
+
+/// Splits the input in lines, starting at `offset`
+fn lex_lines(input: &str, offset: usize) -> impl Iterator<Item = CachedLine> + '_ {
+    let mut tokens = TokenStream::new_at(input, offset);
+    std::iter::from_fn(move || {
+        let mut line = Vec::new();
+        for token in tokens.by_ref() {
+            line.push(token);
+            if matches!(token.kind, T![newline] | T![eof]) {
+                break;
+            }
+        }
+        (!line.is_empty()).then_some(CachedLine {
+            tokens: line,
+            parsed: None,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_lines() {
+        let input = "a @b\n\nc [- d\n-] e\n\nf #g\n";
+        let mut cache = LineCache::new(input, Extensions::all());
+        cache.parse(input);
+        assert_eq!(cache.lines.len(), 5);
+
+        // at the start of a line, the previous one is lexed again too
+        let input = "a @b\n\nx [- d\n-] e\n\nf #g\n";
+        cache.edit(input, 6..7, 1);
+        let parsed = cache
+            .lines
+            .iter()
+            .map(|l| l.parsed.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(parsed, [true, false, false, true, true]);
+        cache.parse(input);
+
+        let input = "a @b\n\nx [- y\n-] e\n\nf #g\n";
+        cache.edit(input, 11..12, 1);
+        // only the edited line
+        let parsed = cache
+            .lines
+            .iter()
+            .map(|l| l.parsed.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(parsed, [true, true, false, true, true]);
+
+        let ast = cache.parse(input).into_output().unwrap();
+        assert_eq!(
+            ast.lines,
+            super::super::parse(input, Extensions::all())
+                .into_output()
+                .unwrap()
+                .lines
+        );
+        // the first line is before an empty line, so it can't change
+        assert_eq!(cache.changed_from(), 1);
+
+        // nothing changed
+        cache.parse(input);
+        assert_eq!(cache.changed_from(), 3);
+    }
+
+    #[test]
+    fn changed_from_joined_line() {
+        let input = "a\n\nb\ncc\n";
+        let mut cache = LineCache::new(input, Extensions::all());
+        cache.parse(input);
+        assert_eq!(cache.changed_from(), 0);
+
+        // the edited line is joined to the step before it
+        let input = "a\n\nb\ncd\n";
+        cache.edit(input, 6..7, 1);
+        let ast = cache.parse(input).into_output().unwrap();
+        assert_eq!(ast.lines.len(), 2);
+        assert_eq!(cache.changed_from(), 1);
+    }
+}
@@ -52,11 +52,13 @@
 //! This is more of a guideline, there may be edge cases that this grammar does
 //! not cover but the pareser does.
 
+mod cst;
+pub(crate) mod incremental;
 mod metadata;
 mod quantity;
 mod section;
 mod step;
-mod token_stream;
+pub(crate) mod token_stream;
 
 use std::borrow::Cow;
 
@@ -75,6 +77,9 @@ use crate::{
 
 use token_stream::{Token, TokenKind, TokenStream};
 
+pub use cst::parse_cst;
+pub(crate) use quantity::{parse_leading_value, parse_standalone_quantity};
+
 #[derive(Debug)]
 pub(crate) struct Parser<'input, T>
 where
@@ -149,12 +154,37 @@ pub fn parse<'input>(
     input: &'input str,
     extensions: Extensions,
 ) -> PassResult<ast::Ast<'input>, ParserError, ParserWarning> {
-    let mut parser = Parser::new(input, extensions);
+    parse_lines(Parser::new(input, extensions), |_, _| {})
+}
 
+/// What a line of the input was parsed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParsedLine {
+    /// Only whitespace and comments
+    Empty,
+    /// A new [`ast::Line`] was added
+    New,
+    /// Joined to the previous step with
+    /// [`MULTILINE_STEPS`](Extensions::MULTILINE_STEPS)
+    Joined,
+}
+
+/// Parses all the lines of `parser`, calling `on_line` for every line with the
+/// line tokens and what they became.
+pub(crate) fn parse_lines<'input, I>(
+    mut parser: Parser<'input, I>,
+    mut on_line: impl FnMut(&[Token], ParsedLine),
+) -> PassResult<ast::Ast<'input>, ParserError, ParserWarning>
+where
+    I: Iterator<Item = Token>,
+{
     let mut last_empty = true;
     let mut lines = Vec::new();
     while let Some(mut line) = parser.next_line() {
-        parse_line(&mut line, &mut lines, &mut last_empty);
+        let force_text = joins_text_step(&lines, last_empty, line.extensions);
+        let parsed = parse_line(&mut line, force_text);
+        let parsed = add_line(&mut lines, parsed, &mut last_empty, line.extensions);
+        on_line(line.tokens(), parsed);
         let mut ctx = line.finish();
         parser.context.append(&mut ctx);
     }
@@ -163,19 +193,28 @@ pub fn parse<'input>(
     parser.context.finish(Some(ast))
 }
 
-fn parse_line<'input>(
+/// Checks if the next line, if it is a step, will be joined to a text step
+pub(crate) fn joins_text_step(
+    lines: &[ast::Line],
+    last_empty: bool,
+    extensions: Extensions,
+) -> bool {
+    !last_empty
+        && extensions.contains(Extensions::MULTILINE_STEPS)
+        && matches!(lines.last(), Some(ast::Line::Step { is_text: true, .. }))
+}
+
+/// Parses a single line on its own. Returns [`None`] if the line is empty.
+///
+/// If `force_text` is true, a step will be a text step.
+pub(crate) fn parse_line<'input>(
     line: &mut LineParser<'_, 'input>,
-    lines: &mut Vec<ast::Line<'input>>,
-    last_empty: &mut bool,
-) {
-    let is_empty = line
-        .tokens()
-        .iter()
-        .all(|t| matches!(t.kind, T![ws] | T![line comment] | T![block comment]));
+    force_text: bool,
+) -> Option<ast::Line<'input>> {
+    let is_empty = line.tokens().iter().all(|t| t.kind.is_trivia());
     if is_empty {
-        *last_empty = true;
         line.consume_rest();
-        return;
+        return None;
     }
 
     let meta_or_section = match line.peek() {
@@ -191,47 +230,74 @@ fn parse_line<'input>(
         _ => None,
     };
 
-    let ast_line = if let Some(l) = meta_or_section {
-        l
-    } else {
-        if !*last_empty && line.extension(Extensions::MULTILINE_STEPS) {
-            if let Some(ast::Line::Step { items, is_text }) = lines.last_mut() {
-                let mut parsed_step = step(line, *is_text);
-                if !parsed_step.items.is_empty() {
-                    // pos of the newline/end of last step before trimming
-                    let newline_pos = items.last().unwrap().span().end();
-                    // trim last step end
-                    if let Some(ast::Item::Text(text)) = items.last_mut() {
-                        text.trim_fragments_end();
-                        if text.fragments().is_empty() {
-                            items.pop();
-                        }
-                    }
+    let ast_line = meta_or_section.unwrap_or_else(|| {
+        let parsed_step = step(line, force_text);
+        ast::Line::Step {
+            is_text: parsed_step.is_text,
+            items: parsed_step.items,
+        }
+    });
+    Some(ast_line)
+}
+
+/// Adds a line parsed with [`parse_line`] to the lines. With
+/// [`MULTILINE_STEPS`](Extensions::MULTILINE_STEPS), a step is joined with
+/// the previous one.
+pub(crate) fn add_line<'input>(
+    lines: &mut Vec<ast::Line<'input>>,
+    line: Option<ast::Line<'input>>,
+    last_empty: &mut bool,
+    extensions: Extensions,
+) -> ParsedLine {
+    let Some(line) = line else {
+        *last_empty = true;
+        return ParsedLine::Empty;
+    };
+
+    let line = match line {
+        ast::Line::Step {
+            items: mut new_items,
+            is_text,
+        } if !*last_empty && extensions.contains(Extensions::MULTILINE_STEPS) => {
+            if let Some(ast::Line::Step { items, .. }) = lines.last_mut() {
+                if !new_items.is_empty() {
                     // trim new step begining
-                    if let ast::Item::Text(text) = &mut parsed_step.items[0] {
+                    if let ast::Item::Text(text) = &mut new_items[0] {
                         text.trim_fragments_start();
                         if text.fragments().is_empty() {
-                            parsed_step.items.remove(0);
+                            new_items.remove(0);
+                        }
+                    }
+                    // an empty text step has no items
+                    if let Some(last) = items.last() {
+                        // pos of the newline/end of last step before trimming
+                        let newline_pos = last.span().end();
+                        // trim last step end
+                        if let Some(ast::Item::Text(text)) = items.last_mut() {
+                            text.trim_fragments_end();
+                            if text.fragments().is_empty() {
+                                items.pop();
+                            }
                         }
+                        // add a space in between the 2 lines
+                        // where the last line originally ended in the input
+                        items.push(ast::Item::Text(ast::Text::from_str(" ", newline_pos)));
                     }
-                    // add a space in between the 2 lines
-                    // where the last line originally ended in the input
-                    items.push(ast::Item::Text(ast::Text::from_str(" ", newline_pos)));
-                    items.extend(parsed_step.items);
+                    items.extend(new_items);
                 }
-                return;
+                return ParsedLine::Joined;
+            }
+            ast::Line::Step {
+                items: new_items,
+                is_text,
             }
         }
-
-        let parsed_step = step(line, false);
-        ast::Line::Step {
-            is_text: parsed_step.is_text,
-            items: parsed_step.items,
-        }
+        line => line,
     };
 
     *last_empty = false;
-    lines.push(ast_line);
+    lines.push(line);
+    ParsedLine::New
 }
 
 /// Parse only the recipe metadata into an [`Ast`](ast::Ast).
@@ -514,7 +580,7 @@ macro_rules! mt {
 pub(crate) use mt;
 
 /// Errors generated by [`parse`] and [`parse_metadata`].
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum ParserError {
     #[error("A {container} is missing: {what}")]
     ComponentPartMissing {
@@ -563,7 +629,7 @@ pub enum ParserError {
 }
 
 /// Warnings generated by [`parse`] and [`parse_metadata`].
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum ParserWarning {
     #[error("Empty metadata value for key: {key}")]
     EmptyMetadataValue { key: Located<String> },
@@ -576,6 +642,38 @@ pub enum ParserWarning {
     },
 }
 
+impl ParserError {
+    /// Moves all the locations `delta` bytes
+    pub(crate) fn shift(&mut self, delta: isize) {
+        match self {
+            ParserError::ComponentPartMissing {
+                expected_pos: s, ..
+            }
+            | ParserError::ComponentPartNotAllowed { to_remove: s, .. }
+            | ParserError::DuplicateModifiers {
+                modifiers_span: s, ..
+            }
+            | ParserError::ParseInt { bad_bit: s, .. }
+            | ParserError::ParseFloat { bad_bit: s, .. }
+            | ParserError::DivisionByZero { bad_bit: s }
+            | ParserError::QuantityScalingConflict { bad_bit: s } => s.shift(delta),
+            ParserError::ComponentPartInvalid { labels, .. } => {
+                labels.iter_mut().for_each(|(s, _)| s.shift(delta))
+            }
+        }
+    }
+}
+
+impl ParserWarning {
+    /// Moves all the locations `delta` bytes
+    pub(crate) fn shift(&mut self, delta: isize) {
+        match self {
+            ParserWarning::EmptyMetadataValue { key } => key.shift(delta),
+            ParserWarning::ComponentPartIgnored { ignored, .. } => ignored.shift(delta),
+        }
+    }
+}
+
 impl RichError for ParserError {
     fn labels(&self) -> Vec<(Span, Option<Cow<'static, str>>)> {
         use crate::error::label;
@@ -643,7 +741,7 @@ impl RichError for ParserWarning {
         Some("parser")
     }
 
-    fn kind(&self) -> ariadne::ReportKind {
+    fn kind(&self) -> ariadne::ReportKind<'_> {
         ariadne::ReportKind::Warning
     }
 }
@@ -706,4 +804,23 @@ a test @step @salt{1%mg} more text
             }]
         );
     }
+
+    #[test]
+    fn multiline_empty_text_step() {
+        let (ast, warn, err) = parse(
+            ">\ncontinues here",
+            Extensions::MULTILINE_STEPS | Extensions::TEXT_STEPS,
+        )
+        .into_tuple();
+
+        assert!(warn.is_empty());
+        assert!(err.is_empty());
+        assert_eq!(
+            ast.unwrap().lines,
+            vec![Line::Step {
+                is_text: true,
+                items: vec![Item::Text(Text::from_str("continues here", 2))]
+            }]
+        );
+    }
 }
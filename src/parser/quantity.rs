@@ -3,15 +3,19 @@ use smallvec::SmallVec;
 use crate::{
     ast,
     context::{Context, Recover},
-    error::label,
+    error::{label, PassResult},
     lexer::T,
     located::Located,
-    quantity::Value,
+    quantity::{Fraction, Value},
     span::Span,
     Extensions,
 };
 
-use super::{mt, token_stream::Token, tokens_span, LineParser, ParserError, ParserWarning};
+use super::{
+    mt,
+    token_stream::{Token, TokenStream},
+    tokens_span, LineParser, ParserError, ParserWarning,
+};
 
 pub struct ParsedQuantity<'a> {
     pub quantity: Located<ast::Quantity<'a>>,
@@ -151,6 +155,41 @@ pub fn parse_quantity<'input>(
     }
 }
 
+/// Parses a quantity outside of a component, like in a metadata value
+///
+/// The input is the same as what goes inside the `{}` of a component.
+pub(crate) fn parse_standalone_quantity(
+    input: &str,
+    extensions: Extensions,
+) -> PassResult<ast::Quantity<'_>, ParserError, ParserWarning> {
+    let tokens = TokenStream::new(input).collect::<Vec<_>>();
+    let mut context = Context::default();
+    if tokens.is_empty() {
+        return context.finish(None);
+    }
+    let parsed = parse_quantity(&tokens, input, extensions, &mut context);
+    context.finish(Some(parsed.quantity.into_inner()))
+}
+
+/// Parses the numeric value at the start of `input`, like `2 1/2` in
+/// `2 1/2 cups of flour`
+///
+/// Returns the value and the offset where it ends.
+pub(crate) fn parse_leading_value(input: &str, extensions: Extensions) -> Option<(Value, usize)> {
+    let tokens = TokenStream::new(input)
+        .take_while(|t| matches!(t.kind, T![int] | T![float] | T![/] | T![-] | T![ws]))
+        .collect::<Vec<_>>();
+    let line = LineParser::new(0, &tokens, input, extensions);
+    // the longest value that parses
+    (1..=tokens.len())
+        .rev()
+        .filter(|&end| tokens[end - 1].kind != T![ws])
+        .find_map(|end| match numeric_value(&tokens[..end], &line) {
+            Some(Ok(value)) => Some((value, tokens[end - 1].span.end())),
+            _ => None,
+        })
+}
+
 fn many_values(line: &mut LineParser) -> ast::QuantityValue {
     let mut values: Vec<Located<Value>> = vec![];
     let mut auto_scale = None;
@@ -250,13 +289,9 @@ fn numeric_value(tokens: &[Token], line: &LineParser) -> Option<Result<Value, Pa
         // float
         [t @ mt![float]] => float(t, line).map(|v| Value::Number { value: v }),
         // mixed number
-        [i @ mt![int], a @ mt![int], mt![/], b @ mt![int]] => {
-            mixed_num(i, a, b, line).map(|v| Value::Number { value: v })
-        }
+        [i @ mt![int], a @ mt![int], mt![/], b @ mt![int]] => mixed_num(i, a, b, line),
         // frac
-        [a @ mt![int], mt![/], b @ mt![int]] => {
-            frac(a, b, line).map(|v| Value::Number { value: v })
-        }
+        [a @ mt![int], mt![/], b @ mt![int]] => frac(a, b, line).map(fraction_value),
         // range
         [s @ mt![int | float], mt![-], e @ mt![int | float]]
             if line.extension(Extensions::RANGE_VALUES) =>
@@ -269,22 +304,28 @@ fn numeric_value(tokens: &[Token], line: &LineParser) -> Option<Result<Value, Pa
     Some(r)
 }
 
-fn mixed_num(i: Token, a: Token, b: Token, line: &LineParser) -> Result<f64, ParserError> {
-    let i = int(i, line)?;
+fn mixed_num(i: Token, a: Token, b: Token, line: &LineParser) -> Result<Value, ParserError> {
+    let i = uint(i, line)?;
     let f = frac(a, b, line)?;
-    Ok(i + f)
+    let value = Fraction::new(i, 1)
+        .and_then(|i| i.checked_add(&f))
+        .map(|value| Value::Fraction { value })
+        .unwrap_or(Value::Number {
+            value: i as f64 + f.to_f64(),
+        });
+    Ok(value)
 }
 
-fn frac(a: Token, b: Token, line: &LineParser) -> Result<f64, ParserError> {
+fn frac(a: Token, b: Token, line: &LineParser) -> Result<Fraction, ParserError> {
     let span = Span::new(a.span.start(), b.span.end());
-    let a = int(a, line)?;
-    let b = int(b, line)?;
+    let a = uint(a, line)?;
+    let b = uint(b, line)?;
 
-    if b == 0.0 {
-        Err(ParserError::DivisionByZero { bad_bit: span })
-    } else {
-        Ok(a / b)
-    }
+    Fraction::new(a, b).ok_or(ParserError::DivisionByZero { bad_bit: span })
+}
+
+fn fraction_value(value: Fraction) -> Value {
+    Value::Fraction { value }
 }
 
 fn range(
@@ -306,10 +347,13 @@ fn num(t: Token, line: &LineParser) -> Result<f64, ParserError> {
 }
 
 fn int(tok: Token, line: &LineParser) -> Result<f64, ParserError> {
+    uint(tok, line).map(|i| i as f64)
+}
+
+fn uint(tok: Token, line: &LineParser) -> Result<u32, ParserError> {
     assert_eq!(tok.kind, T![int]);
     line.as_str(tok)
         .parse::<u32>()
-        .map(|i| i as f64)
         .map_err(|e| ParserError::ParseInt {
             bad_bit: tok.span,
             source: e,
@@ -469,4 +513,35 @@ mod tests {
         );
         assert_eq!(q.unit, None);
     }
+
+    #[test]
+    fn fraction_values() {
+        let (q, _, _) = t!("1/2%cup");
+        assert_eq!(
+            q.value,
+            QuantityValue::Single {
+                value: Located::new(
+                    Value::Fraction {
+                        value: Fraction::new(1, 2).unwrap()
+                    },
+                    0..3
+                ),
+                auto_scale: None
+            }
+        );
+
+        let (q, _, _) = t!("1 2/4%cup");
+        assert_eq!(
+            q.value,
+            QuantityValue::Single {
+                value: Located::new(
+                    Value::Fraction {
+                        value: Fraction::new(3, 2).unwrap()
+                    },
+                    0..5
+                ),
+                auto_scale: None
+            }
+        );
+    }
 }
@@ -16,6 +16,14 @@ impl<'input> TokenStream<'input> {
             consumed: 0,
         }
     }
+
+    /// Starts lexing at `offset`, that must be the start of a line.
+    pub fn new_at(input: &'input str, offset: usize) -> Self {
+        Self {
+            cursor: Cursor::new(&input[offset..]),
+            consumed: offset,
+        }
+    }
 }
 
 impl<'input> Iterator for TokenStream<'input> {
@@ -36,14 +44,15 @@ impl<'input> Iterator for TokenStream<'input> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A token of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
 }
 
 impl Token {
-    pub fn len(&self) -> usize {
+    pub(crate) fn len(&self) -> usize {
         self.span.len()
     }
 }
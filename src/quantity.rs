@@ -1,5 +1,7 @@
 //! Quantity model
 
+pub mod format;
+
 use std::{collections::HashMap, fmt::Display, ops::RangeInclusive, sync::Arc};
 
 use enum_map::EnumMap;
@@ -7,21 +9,43 @@ use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use self::format::QuantityFormatter;
 use crate::{
     ast,
-    convert::{ConvertError, Converter, PhysicalQuantity, Unit},
+    convert::{ConvertError, Converter, Density, PhysicalQuantity, Unit},
+    span::Span,
 };
 
 /// A quantity used in components
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+///
+/// The location in the source is not compared in [`PartialEq`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Quantity {
     /// Value
     pub value: QuantityValue,
     pub(crate) unit: Option<QuantityUnit>,
+    /// Location of the quantity in the source
+    ///
+    /// Only set for inline quantities parsed from a source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) span: Option<Span>,
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            value,
+            unit,
+            span: _,
+        } = self;
+        *value == other.value && *unit == other.unit
+    }
 }
 
 /// A value with scaling support
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum QuantityValue {
     /// Cannot be scaled
@@ -36,10 +60,16 @@ pub enum QuantityValue {
 ///
 /// The [`Display`] implementation round `f64` to 3 decimal places.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Value {
     /// Numeric
     Number { value: f64 },
+    /// Exact fraction, like `1/2` or `1 1/2` in the recipe
+    ///
+    /// It stays exact when added to other fractions or whole numbers, and
+    /// when scaled by a factor that is a simple fraction itself.
+    Fraction { value: Fraction },
     /// Range
     Range { value: RangeInclusive<f64> },
     /// Text
@@ -50,6 +80,7 @@ pub enum Value {
 
 /// Unit text with lazy rich information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(transparent)]
 pub struct QuantityUnit {
     text: String,
@@ -83,6 +114,124 @@ impl Value {
     }
 }
 
+/// An exact positive fraction
+///
+/// It is always reduced and its [`Display`] implementation writes mixed
+/// numbers, like `1 1/2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "FractionRepr")]
+pub struct Fraction {
+    num: u32,
+    den: u32,
+}
+
+#[derive(Deserialize)]
+struct FractionRepr {
+    num: u32,
+    den: u32,
+}
+
+impl TryFrom<FractionRepr> for Fraction {
+    type Error = &'static str;
+
+    fn try_from(value: FractionRepr) -> Result<Self, Self::Error> {
+        Fraction::new(value.num, value.den).ok_or("fraction denominator is 0")
+    }
+}
+
+impl Fraction {
+    /// Creates a new reduced fraction. Returns `None` if `den` is 0.
+    pub fn new(num: u32, den: u32) -> Option<Self> {
+        Self::reduced(num.into(), den.into())
+    }
+
+    fn reduced(num: u64, den: u64) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num, den);
+        Some(Self {
+            num: (num / g).try_into().ok()?,
+            den: (den / g).try_into().ok()?,
+        })
+    }
+
+    /// Approximates a number with a fraction
+    ///
+    /// Returns `None` if the number is negative or there is no fraction with
+    /// a denominator up to `max_den` within `1e-9` of it.
+    pub fn from_f64(n: f64, max_den: u32) -> Option<Self> {
+        if !n.is_finite() || n < 0.0 {
+            return None;
+        }
+        (1..=max_den).find_map(|den| {
+            let num = (n * den as f64).round();
+            ((num / den as f64 - n).abs() < 1e-9 && num <= u32::MAX as f64)
+                .then(|| Self::new(num as u32, den))
+                .flatten()
+        })
+    }
+
+    /// Numerator
+    pub fn num(&self) -> u32 {
+        self.num
+    }
+
+    /// Denominator
+    pub fn den(&self) -> u32 {
+        self.den
+    }
+
+    /// Whole part and the remaining fraction, so `3/2` is `(1, 1/2)`
+    pub fn split(&self) -> (u32, Fraction) {
+        (
+            self.num / self.den,
+            Fraction {
+                num: self.num % self.den,
+                den: self.den,
+            },
+        )
+    }
+
+    /// Value as a float
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Adds two fractions, `None` on overflow
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        let (a, b) = (self.num as u64, self.den as u64);
+        let (c, d) = (rhs.num as u64, rhs.den as u64);
+        Self::reduced(a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?, b * d)
+    }
+
+    /// Multiplies two fractions, `None` on overflow
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Self::reduced(
+            self.num as u64 * rhs.num as u64,
+            self.den as u64 * rhs.den as u64,
+        )
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+impl Display for Fraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.split() {
+            (whole, rem) if rem.num == 0 => write!(f, "{whole}"),
+            (0, rem) => write!(f, "{}/{}", rem.num, rem.den),
+            (whole, rem) => write!(f, "{whole} {}/{}", rem.num, rem.den),
+        }
+    }
+}
+
 impl PartialEq for QuantityUnit {
     fn eq(&self, other: &Self) -> bool {
         self.text == other.text
@@ -130,6 +279,7 @@ impl Quantity {
                 text,
                 info: OnceCell::new(),
             }),
+            span: None,
         }
     }
 
@@ -145,6 +295,7 @@ impl Quantity {
                 info: OnceCell::from(UnitInfo::new(&text, converter)),
                 text,
             }),
+            span: None,
         }
     }
 
@@ -163,6 +314,7 @@ impl Quantity {
                     None => UnitInfo::Unknown,
                 }),
             }),
+            span: None,
         }
     }
 
@@ -185,6 +337,16 @@ impl Quantity {
     pub fn unit_text(&self) -> Option<&str> {
         self.unit.as_ref().map(|u| u.text.as_ref())
     }
+
+    /// Location of the quantity in the source
+    ///
+    /// Only [inline quantities](crate::Recipe::inline_quantities) have it, the
+    /// quantities of components are inside the component
+    /// [span](crate::model::Ingredient::span). It is kept when the quantity is
+    /// scaled or converted, but not when it is added to another one.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
 }
 
 impl QuantityValue {
@@ -214,6 +376,13 @@ impl QuantityValue {
     }
 }
 
+impl Quantity {
+    /// Formats the quantity with a [`QuantityFormatter`]
+    pub fn format(&self, formatter: &QuantityFormatter, converter: &Converter) -> String {
+        formatter.quantity(self, converter)
+    }
+}
+
 impl Display for Quantity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.value)?;
@@ -246,6 +415,7 @@ impl Display for Value {
 
         match self {
             Value::Number { value: n } => write!(f, "{}", float(*n)),
+            Value::Fraction { value } => value.fmt(f),
             Value::Range { value: r } => write!(f, "{}-{}", float(*r.start()), float(*r.end())),
             Value::Text { value: t } => write!(f, "{}", t),
         }
@@ -293,7 +463,7 @@ pub enum QuantityAddError {
 }
 
 /// Error that makes quantity units incompatible to be added
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Clone)]
 pub enum IncompatibleUnits {
     #[error("Missing unit: one unit is '{found}' but the other quantity is missing an unit")]
     MissingUnit {
@@ -366,7 +536,47 @@ impl Quantity {
     pub fn try_add(&self, rhs: &Self, converter: &Converter) -> Result<Quantity, QuantityAddError> {
         // 1. Check if the units are compatible and (maybe) get a common unit
         let convert_to = self.compatible_unit(rhs, converter)?;
+        self.add_compatible(rhs, convert_to, converter)
+    }
 
+    /// Try adding two quantities of an ingredient
+    ///
+    /// Same as [`Self::try_add`], but if one is a volume, the other a mass and
+    /// the ingredient has a known [`Density`], `rhs` is converted to the unit
+    /// of `self`. The density is returned when it was used.
+    pub fn try_add_ingredient(
+        &self,
+        rhs: &Self,
+        ingredient: &str,
+        converter: &Converter,
+    ) -> Result<(Quantity, Option<Density>), QuantityAddError> {
+        match self.compatible_unit(rhs, converter) {
+            Err(IncompatibleUnits::DifferentPhysicalQuantities { a, b })
+                if is_volume_and_mass(a, b) && converter.density(ingredient).is_some() =>
+            {
+                let UnitInfo::Known(to) = self.unit.as_ref().unwrap().unit_info_or_parse(converter)
+                else {
+                    unreachable!("known unit with physical quantity")
+                };
+                let (rhs, density) = converter.convert_ingredient(rhs, &to, ingredient)?;
+                let value = self.value.try_add(&rhs.value)?;
+                let qty = Quantity {
+                    value,
+                    unit: self.unit.clone(),
+                    span: None,
+                };
+                Ok((qty, density))
+            }
+            convert_to => Ok((self.add_compatible(rhs, convert_to?, converter)?, None)),
+        }
+    }
+
+    fn add_compatible(
+        &self,
+        rhs: &Self,
+        convert_to: Option<Arc<Unit>>,
+        converter: &Converter,
+    ) -> Result<Quantity, QuantityAddError> {
         // 2. Convert rhs to the unit of the first one if needed
         let rhs = if let Some(to) = convert_to {
             converter.convert(rhs, &to)?
@@ -381,6 +591,7 @@ impl Quantity {
         let qty = Quantity {
             value,
             unit: self.unit.clone(), // unit is mantained
+            span: None,
         };
 
         Ok(qty)
@@ -403,6 +614,11 @@ impl Quantity {
     }
 }
 
+fn is_volume_and_mass(a: PhysicalQuantity, b: PhysicalQuantity) -> bool {
+    use PhysicalQuantity::{Mass, Volume};
+    matches!((a, b), (Volume, Mass) | (Mass, Volume))
+}
+
 /// Error when try to operate on a non scaled value
 #[derive(Debug, Error)]
 #[error("Tried to operate on a non scaled value: {0}")]
@@ -435,6 +651,31 @@ impl Value {
             (Value::Number { value: a }, Value::Number { value: b }) => {
                 Value::Number { value: a + b }
             }
+            (Value::Fraction { value: a }, Value::Fraction { value: b }) => {
+                match a.checked_add(b) {
+                    Some(value) => Value::Fraction { value },
+                    None => Value::Number {
+                        value: a.to_f64() + b.to_f64(),
+                    },
+                }
+            }
+            (Value::Fraction { value: a }, Value::Number { value: n })
+            | (Value::Number { value: n }, Value::Fraction { value: a }) => {
+                // whole numbers keep the fraction exact
+                match Fraction::from_f64(*n, 1).and_then(|n| a.checked_add(&n)) {
+                    Some(value) => Value::Fraction { value },
+                    None => Value::Number {
+                        value: a.to_f64() + n,
+                    },
+                }
+            }
+            (Value::Fraction { value: a }, Value::Range { value: r })
+            | (Value::Range { value: r }, Value::Fraction { value: a }) => {
+                let n = a.to_f64();
+                Value::Range {
+                    value: r.start() + n..=r.end() + n,
+                }
+            }
             (Value::Number { value: n }, Value::Range { value: r })
             | (Value::Range { value: r }, Value::Number { value: n }) => Value::Range {
                 value: r.start() + n..=r.end() + n,
@@ -457,6 +698,9 @@ impl Value {
 /// calculating the [`TotalQuantity`].
 ///
 /// This is used to create, and merge ingredients lists.
+///
+/// A group created with [`Self::for_ingredient`] adds volumes and masses
+/// together when the ingredient has a known [`Density`].
 #[derive(Default, Debug, Clone, Serialize)]
 pub struct GroupedQuantity {
     /// known units
@@ -467,6 +711,12 @@ pub struct GroupedQuantity {
     no_unit: Option<Quantity>,
     /// could not operate/add to others
     other: Vec<Quantity>,
+    /// ingredient name to look up densities
+    #[serde(skip)]
+    ingredient: Option<String>,
+    /// densities used when adding
+    #[serde(skip)]
+    densities: Vec<Density>,
 }
 
 impl GroupedQuantity {
@@ -475,6 +725,21 @@ impl GroupedQuantity {
         Self::default()
     }
 
+    /// Create a new empty group for the quantities of an ingredient
+    pub fn for_ingredient(name: &str) -> Self {
+        Self {
+            ingredient: Some(name.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Densities used to add volumes and masses together
+    ///
+    /// Each one appears once, even if it was used many times.
+    pub fn densities(&self) -> &[Density] {
+        &self.densities
+    }
+
     /// Add a new quantity to the group
     pub fn add(&mut self, q: &Quantity, converter: &Converter) {
         macro_rules! add {
@@ -508,6 +773,25 @@ impl GroupedQuantity {
             UnitInfo::Known(unit) => {
                 if let Some(stored) = &mut self.known[unit.physical_quantity] {
                     add!(stored, q, converter, self.other);
+                } else if let Some((stored, name)) = self
+                    .known
+                    .iter_mut()
+                    .find(|(pq, _)| is_volume_and_mass(*pq, unit.physical_quantity))
+                    .and_then(|(_, stored)| stored.as_mut())
+                    .zip(self.ingredient.as_deref())
+                    .filter(|(_, name)| converter.density(name).is_some())
+                {
+                    match stored.try_add_ingredient(q, name, converter) {
+                        Ok((sum, density)) => {
+                            *stored = sum;
+                            if let Some(density) = density {
+                                if !self.densities.contains(&density) {
+                                    self.densities.push(density);
+                                }
+                            }
+                        }
+                        Err(_) => self.other.push(q.clone()),
+                    }
                 } else {
                     self.known[unit.physical_quantity] = Some(q.clone());
                 }
@@ -527,6 +811,11 @@ impl GroupedQuantity {
         for q in other.all_quantities() {
             self.add(q, converter)
         }
+        for density in &other.densities {
+            if !self.densities.contains(density) {
+                self.densities.push(density.clone());
+            }
+        }
     }
 
     fn all_quantities(&self) -> impl Iterator<Item = &Quantity> + '_ {
@@ -559,8 +848,9 @@ impl GroupedQuantity {
     pub fn total(&self) -> TotalQuantity {
         let mut all = self.all_quantities().cloned().peekable();
 
-        let Some(first) = all.next()
-        else { return TotalQuantity::None; };
+        let Some(first) = all.next() else {
+            return TotalQuantity::None;
+        };
 
         if all.peek().is_none() {
             TotalQuantity::Single(first)
@@ -630,6 +920,31 @@ impl TotalQuantity {
     }
 }
 
+impl TotalQuantity {
+    /// Formats the quantities with a [`QuantityFormatter`], separated by `, `
+    pub fn format(&self, formatter: &QuantityFormatter, converter: &Converter) -> String {
+        formatter.total(self, converter)
+    }
+}
+
+impl Display for TotalQuantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TotalQuantity::None => Ok(()),
+            TotalQuantity::Single(q) => q.fmt(f),
+            TotalQuantity::Many(many) => {
+                for (i, q) in many.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    q.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 impl From<TotalQuantity> for Vec<Quantity> {
     fn from(value: TotalQuantity) -> Self {
         value.into_vec()
@@ -0,0 +1,409 @@
+//! Human friendly formatting of quantities
+//!
+//! [`Display`](std::fmt::Display) for [`Value`] always rounds to 3 decimals,
+//! so a scaled recipe may show `0.333 cup`. A [`QuantityFormatter`] chooses a
+//! [`NumberFormat`] for each unit or physical quantity, so it can show `⅓ cup`
+//! instead, or round teaspoons to the nearest quarter.
+//!
+//! ```
+//! # use cooklang::{Converter, Quantity, QuantityValue};
+//! # use cooklang::quantity::format::{NumberFormat, QuantityFormatter};
+//! let converter = Converter::bundled();
+//! let formatter = QuantityFormatter::new()
+//!     .with_default(NumberFormat::fraction(&[2, 3, 4]))
+//!     .with_unit("tsp", NumberFormat::fraction(&[2, 4]).snap(0.25));
+//!
+//! let cup = Quantity::new(QuantityValue::Fixed { value: (1.0 / 3.0).into() }, Some("cup".into()));
+//! assert_eq!(cup.format(&formatter, &converter), "⅓ cup");
+//!
+//! let tsp = Quantity::new(QuantityValue::Fixed { value: 1.3.into() }, Some("tsp".into()));
+//! assert_eq!(tsp.format(&formatter, &converter), "1 ¼ tsp");
+//! ```
+
+use std::collections::HashMap;
+
+use enum_map::EnumMap;
+
+use crate::{
+    convert::{Converter, PhysicalQuantity},
+    quantity::{Fraction, Quantity, QuantityUnit, QuantityValue, TotalQuantity, UnitInfo, Value},
+};
+
+/// How to write a number
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+    /// Maximum number of decimals when the number is written as a decimal
+    pub decimals: u8,
+    /// Denominators allowed in fractions. When empty, fractions are not used.
+    pub denominators: Vec<u32>,
+    /// Maximum difference between the number and a fraction to use it
+    pub tolerance: f64,
+    /// Round the number to the nearest multiple of this before formatting
+    pub snap: Option<f64>,
+    /// Use unicode fraction characters, like `½`, when available
+    pub unicode: bool,
+}
+
+impl NumberFormat {
+    /// Decimal numbers with up to `decimals` decimals
+    pub fn decimal(decimals: u8) -> Self {
+        Self {
+            decimals,
+            denominators: Vec::new(),
+            tolerance: 0.0,
+            snap: None,
+            unicode: true,
+        }
+    }
+
+    /// Fractions with the given denominators
+    ///
+    /// If the number is not close enough to any fraction, it is written as a
+    /// decimal with up to 2 decimals.
+    pub fn fraction(denominators: &[u32]) -> Self {
+        Self {
+            decimals: 2,
+            denominators: denominators.iter().copied().filter(|&d| d > 1).collect(),
+            tolerance: 0.02,
+            snap: None,
+            unicode: true,
+        }
+    }
+
+    /// Set [`Self::snap`]
+    pub fn snap(mut self, step: f64) -> Self {
+        self.snap = Some(step).filter(|s| *s > 0.0);
+        self
+    }
+
+    /// Set [`Self::tolerance`]
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Write fractions like `1/2` instead of `½`
+    pub fn ascii(mut self) -> Self {
+        self.unicode = false;
+        self
+    }
+
+    /// Formats a number
+    pub fn format(&self, n: f64) -> String {
+        let n = match self.snap {
+            // never snap a positive amount to nothing
+            Some(step) if n > 0.0 => ((n / step).round() * step).max(step),
+            Some(step) => (n / step).round() * step,
+            None => n,
+        };
+        self.fraction_of(n).unwrap_or_else(|| self.decimal_of(n))
+    }
+
+    /// Formats an exact fraction
+    ///
+    /// It is written exactly when one of [`Self::denominators`] can represent
+    /// it. Otherwise, or if [`Self::snap`] is set, it is formatted like any
+    /// other number.
+    pub fn format_fraction(&self, fraction: &Fraction) -> String {
+        let (whole, rem) = fraction.split();
+        let exact = rem.num() != 0 && self.denominators.iter().any(|d| d % rem.den() == 0);
+        if self.snap.is_some() || !exact {
+            return self.format(fraction.to_f64());
+        }
+        let rem = match self
+            .unicode
+            .then(|| unicode_fraction(rem.num(), rem.den()))
+            .flatten()
+        {
+            Some(c) => c.to_string(),
+            None => format!("{}/{}", rem.num(), rem.den()),
+        };
+        if whole == 0 {
+            rem
+        } else {
+            format!("{whole} {rem}")
+        }
+    }
+
+    fn decimal_of(&self, n: f64) -> String {
+        let p = 10f64.powi(self.decimals as i32);
+        let n = (n * p).round() / p;
+        // avoid "-0"
+        let n = if n == 0.0 { 0.0 } else { n };
+        n.to_string()
+    }
+
+    fn fraction_of(&self, n: f64) -> Option<String> {
+        if self.denominators.is_empty() || !n.is_finite() {
+            return None;
+        }
+        let sign = if n < 0.0 { "-" } else { "" };
+        let abs = n.abs();
+        let mut whole = abs.trunc();
+        let frac = abs - whole;
+
+        let mut best: Option<(u32, u32, f64)> = None;
+        for &den in &self.denominators {
+            let num = (frac * den as f64).round() as u32;
+            let err = (frac - num as f64 / den as f64).abs();
+            if best.is_none_or(|(_, _, e)| err < e) {
+                best = Some((num, den, err));
+            }
+        }
+        let (mut num, mut den, err) = best?;
+        if err > self.tolerance {
+            return None;
+        }
+        if num == den {
+            whole += 1.0;
+            num = 0;
+        }
+        if num == 0 {
+            // a small positive amount is not 0
+            if whole == 0.0 && abs > 0.0 {
+                return None;
+            }
+            return Some(format!("{sign}{whole}"));
+        }
+        let g = gcd(num, den);
+        num /= g;
+        den /= g;
+
+        let frac = match self.unicode.then(|| unicode_fraction(num, den)).flatten() {
+            Some(c) => c.to_string(),
+            None => format!("{num}/{den}"),
+        };
+        if whole == 0.0 {
+            Some(format!("{sign}{frac}"))
+        } else {
+            Some(format!("{sign}{whole} {frac}"))
+        }
+    }
+}
+
+impl Default for NumberFormat {
+    /// The same as [`Display`](std::fmt::Display) for [`Value`], 3 decimals
+    fn default() -> Self {
+        Self::decimal(3)
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+pub(crate) fn unicode_fraction(num: u32, den: u32) -> Option<char> {
+    let c = match (num, den) {
+        (1, 2) => '½',
+        (1, 3) => '⅓',
+        (2, 3) => '⅔',
+        (1, 4) => '¼',
+        (3, 4) => '¾',
+        (1, 5) => '⅕',
+        (2, 5) => '⅖',
+        (3, 5) => '⅗',
+        (4, 5) => '⅘',
+        (1, 6) => '⅙',
+        (5, 6) => '⅚',
+        (1, 7) => '⅐',
+        (1, 8) => '⅛',
+        (3, 8) => '⅜',
+        (5, 8) => '⅝',
+        (7, 8) => '⅞',
+        (1, 9) => '⅑',
+        (1, 10) => '⅒',
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// Formats quantities with a [`NumberFormat`] chosen by unit
+///
+/// The format of a quantity is the first found of:
+/// 1. The one set for its unit with [`Self::with_unit`].
+/// 2. The one set for the [`PhysicalQuantity`] of the unit with
+///    [`Self::with_quantity`].
+/// 3. The default one, set with [`Self::with_default`].
+#[derive(Debug, Clone, Default)]
+pub struct QuantityFormatter {
+    default: NumberFormat,
+    by_quantity: EnumMap<PhysicalQuantity, Option<NumberFormat>>,
+    by_unit: HashMap<String, NumberFormat>,
+}
+
+impl QuantityFormatter {
+    /// Formatter that writes the same as [`Display`](std::fmt::Display)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Formatter for recipes in the kitchen
+    ///
+    /// - Fractions of halves, thirds, quarters and eighths by default.
+    /// - Teaspoons and tablespoons snap to the nearest quarter.
+    /// - Grams and millilitres are whole numbers.
+    /// - Temperatures are whole numbers.
+    pub fn cooking() -> Self {
+        let spoons = NumberFormat::fraction(&[2, 4]).snap(0.25);
+        let whole = NumberFormat::decimal(0);
+        Self::new()
+            .with_default(NumberFormat::fraction(&[2, 3, 4, 8]))
+            .with_quantity(PhysicalQuantity::Temperature, whole.clone())
+            .with_unit("tsp", spoons.clone())
+            .with_unit("tbsp", spoons)
+            .with_unit("g", whole.clone())
+            .with_unit("ml", whole)
+    }
+
+    /// Set the format when nothing more specific is set
+    pub fn with_default(mut self, format: NumberFormat) -> Self {
+        self.default = format;
+        self
+    }
+
+    /// Set the format for the units of a physical quantity
+    pub fn with_quantity(mut self, quantity: PhysicalQuantity, format: NumberFormat) -> Self {
+        self.by_quantity[quantity] = Some(format);
+        self
+    }
+
+    /// Set the format for a unit
+    ///
+    /// For a known unit, any of its names, symbols or aliases work. Unknown
+    /// units have to match exactly.
+    pub fn with_unit(mut self, unit: &str, format: NumberFormat) -> Self {
+        self.by_unit.insert(unit.to_string(), format);
+        self
+    }
+
+    /// Get the format used for a unit
+    pub fn number_format(
+        &self,
+        unit: Option<&QuantityUnit>,
+        converter: &Converter,
+    ) -> &NumberFormat {
+        let Some(unit) = unit else {
+            return &self.default;
+        };
+        match unit.unit_info_or_parse(converter) {
+            UnitInfo::Known(u) => {
+                let by_unit = u
+                    .names
+                    .iter()
+                    .chain(&u.symbols)
+                    .chain(&u.aliases)
+                    .find_map(|key| self.by_unit.get(key.as_ref()));
+                by_unit
+                    .or(self.by_quantity[u.physical_quantity].as_ref())
+                    .unwrap_or(&self.default)
+            }
+            UnitInfo::Unknown => self.by_unit.get(unit.text()).unwrap_or(&self.default),
+        }
+    }
+
+    /// Formats a value
+    ///
+    /// If both ends of a range are the same after formatting, only one is
+    /// written.
+    pub fn value(&self, value: &Value, format: &NumberFormat) -> String {
+        match value {
+            Value::Number { value } => format.format(*value),
+            Value::Fraction { value } => format.format_fraction(value),
+            Value::Range { value } => {
+                let start = format.format(*value.start());
+                let end = format.format(*value.end());
+                if start == end {
+                    start
+                } else {
+                    format!("{start}-{end}")
+                }
+            }
+            Value::Text { value } => value.clone(),
+        }
+    }
+
+    /// Formats a quantity
+    pub fn quantity(&self, quantity: &Quantity, converter: &Converter) -> String {
+        let format = self.number_format(quantity.unit(), converter);
+        let mut s = match &quantity.value {
+            QuantityValue::Fixed { value } | QuantityValue::Linear { value } => {
+                self.value(value, format)
+            }
+            QuantityValue::ByServings { values } => values
+                .iter()
+                .map(|v| self.value(v, format))
+                .collect::<Vec<_>>()
+                .join("|"),
+        };
+        if let Some(unit) = quantity.unit() {
+            s.push(' ');
+            s.push_str(unit.text());
+        }
+        s
+    }
+
+    /// Formats a total quantity, separating the quantities with `, `
+    pub fn total(&self, total: &TotalQuantity, converter: &Converter) -> String {
+        match total {
+            TotalQuantity::None => String::new(),
+            TotalQuantity::Single(q) => self.quantity(q, converter),
+            TotalQuantity::Many(many) => many
+                .iter()
+                .map(|q| self.quantity(q, converter))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0.5 => "½"; "half")]
+    #[test_case(1.0 / 3.0 => "⅓"; "third")]
+    #[test_case(1.0 + 2.0 / 3.0 => "1 ⅔"; "whole and fraction")]
+    #[test_case(2.0 => "2")]
+    #[test_case(1.99 => "2")]
+    #[test_case(0.3 => "0.3"; "no close fraction")]
+    #[test_case(0.01 => "0.01"; "small not zero")]
+    #[test_case(-0.75 => "-¾"; "negative")]
+    #[test_case(2.0 / 8.0 => "¼"; "reduced")]
+    fn fraction(n: f64) -> String {
+        NumberFormat::fraction(&[2, 3, 4, 8]).format(n)
+    }
+
+    #[test_case(3.0 / 8.0 => "3/8")]
+    #[test_case(1.2 => "1 1/5")]
+    fn ascii_fraction(n: f64) -> String {
+        NumberFormat::fraction(&[5, 8]).ascii().format(n)
+    }
+
+    #[test_case(0.1 => "¼"; "minimum")]
+    #[test_case(0.6 => "½"; "half")]
+    #[test_case(1.13 => "1 ¼"; "whole and quarter")]
+    fn snap(n: f64) -> String {
+        NumberFormat::fraction(&[2, 4]).snap(0.25).format(n)
+    }
+
+    #[test_case(1, 2, NumberFormat::fraction(&[2, 4]) => "½"; "half")]
+    #[test_case(7, 3, NumberFormat::fraction(&[3]) => "2 ⅓"; "whole and third")]
+    #[test_case(1, 2, NumberFormat::fraction(&[8]) => "½"; "multiple denominator")]
+    #[test_case(1, 5, NumberFormat::fraction(&[2, 4]) => "0.2"; "no denominator")]
+    #[test_case(1, 2, NumberFormat::decimal(2) => "0.5"; "decimal")]
+    #[test_case(4, 2, NumberFormat::decimal(2) => "2"; "whole")]
+    fn exact_fraction(num: u32, den: u32, format: NumberFormat) -> String {
+        format.format_fraction(&Fraction::new(num, den).unwrap())
+    }
+
+    #[test_case(1.0 / 3.0, 3 => "0.333")]
+    #[test_case(2.5, 0 => "3")]
+    #[test_case(-0.0001, 2 => "0")]
+    fn decimal(n: f64, decimals: u8) -> String {
+        NumberFormat::decimal(decimals).format(n)
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use super::{metadata_entries, timer_text, Context, Render};
+use crate::{ingredient_list::GroupedIngredient, model::Section, Step};
+
+/// Semantic HTML renderer
+///
+/// The recipe is an `<article class="recipe">` with:
+/// - A `<header>` with the name in a `<h1>`, the description and the other
+///   metadata in a `<dl class="metadata">`.
+/// - A `<section class="ingredients">` with the ingredient list.
+/// - A `<section class="steps">` with a `<section>` for each recipe
+///   section. Steps are `<li>` inside an `<ol>` with their number as the
+///   `value` and text steps are `<p>`.
+///
+/// Inside the steps, ingredients, cookware, timers and inline quantities are
+/// `<span>`s with the classes `ingredient`, `cookware`, `timer` and
+/// `inline-quantity`, and a `data-index` attribute.
+///
+/// Only the body of the recipe is written, without `<html>` or styles.
+#[derive(Debug, Clone, Default)]
+pub struct Html {
+    /// An `<ol>` is open in the current section
+    in_list: bool,
+}
+
+impl Html {
+    fn close_list(&mut self, w: &mut String) {
+        if self.in_list {
+            w.push_str("</ol>\n");
+            self.in_list = false;
+        }
+    }
+}
+
+impl Render for Html {
+    fn header(&mut self, w: &mut String, ctx: &Context) {
+        w.push_str("<article class=\"recipe\">\n<header>\n");
+        writeln!(w, "<h1>{}</h1>", escape(&ctx.recipe.name)).unwrap();
+        if let Some(description) = &ctx.recipe.metadata.description {
+            writeln!(w, "<p class=\"description\">{}</p>", escape(description)).unwrap();
+        }
+        let entries = metadata_entries(ctx);
+        if !entries.is_empty() {
+            w.push_str("<dl class=\"metadata\">\n");
+            for (key, value) in &entries {
+                writeln!(w, "<dt>{}</dt><dd>{}</dd>", escape(key), escape(value)).unwrap();
+            }
+            w.push_str("</dl>\n");
+        }
+        w.push_str("</header>\n");
+    }
+
+    fn ingredient_list(&mut self, w: &mut String, ctx: &Context, list: &[GroupedIngredient]) {
+        if list.is_empty() {
+            return;
+        }
+        w.push_str("<section class=\"ingredients\">\n<h2>Ingredients</h2>\n<ul>\n");
+        for entry in list {
+            write!(w, "<li data-index=\"{}\">", entry.index).unwrap();
+            let quantity = ctx.total(&entry.quantity);
+            if !quantity.is_empty() {
+                write!(w, "<span class=\"quantity\">{}</span> ", escape(&quantity)).unwrap();
+            }
+            let name = entry.ingredient.display_name();
+            write!(w, "<span class=\"name\">{}</span>", escape(&name)).unwrap();
+            if let Some(note) = &entry.ingredient.note {
+                write!(w, " <span class=\"note\">{}</span>", escape(note)).unwrap();
+            }
+            w.push_str("</li>\n");
+        }
+        w.push_str("</ul>\n</section>\n");
+    }
+
+    fn instructions_start(&mut self, w: &mut String, _ctx: &Context) {
+        w.push_str("<section class=\"steps\">\n<h2>Steps</h2>\n");
+    }
+
+    fn section_start(&mut self, w: &mut String, _ctx: &Context, index: usize, section: &Section) {
+        writeln!(w, "<section data-index=\"{index}\">").unwrap();
+        if let Some(name) = &section.name {
+            writeln!(w, "<h3>{}</h3>", escape(name)).unwrap();
+        }
+    }
+
+    fn section_end(&mut self, w: &mut String, _ctx: &Context, _index: usize, _section: &Section) {
+        self.close_list(w);
+        w.push_str("</section>\n");
+    }
+
+    fn step(&mut self, w: &mut String, _ctx: &Context, step: &Step, content: &str) {
+        match step.number {
+            Some(number) => {
+                if !self.in_list {
+                    w.push_str("<ol>\n");
+                    self.in_list = true;
+                }
+                writeln!(w, "<li value=\"{number}\">{content}</li>").unwrap();
+            }
+            None => {
+                self.close_list(w);
+                writeln!(w, "<p>{content}</p>").unwrap();
+            }
+        }
+    }
+
+    fn text(&mut self, w: &mut String, _ctx: &Context, text: &str) {
+        w.push_str(&escape(text));
+    }
+
+    fn ingredient(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        let ingredient = &ctx.recipe.ingredients[index];
+        write!(
+            w,
+            "<span class=\"ingredient\" data-index=\"{index}\">{}</span>",
+            escape(&ingredient.display_name())
+        )
+        .unwrap();
+        if let Some(quantity) = &ingredient.quantity {
+            write!(
+                w,
+                " <span class=\"quantity\">({})</span>",
+                escape(&ctx.quantity(quantity))
+            )
+            .unwrap();
+        }
+    }
+
+    fn cookware(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        write!(
+            w,
+            "<span class=\"cookware\" data-index=\"{index}\">{}</span>",
+            escape(ctx.recipe.cookware[index].display_name())
+        )
+        .unwrap();
+    }
+
+    fn timer(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        write!(
+            w,
+            "<span class=\"timer\" data-index=\"{index}\">{}</span>",
+            escape(&timer_text(ctx, index))
+        )
+        .unwrap();
+    }
+
+    fn inline_quantity(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        write!(
+            w,
+            "<span class=\"inline-quantity\" data-index=\"{index}\">{}</span>",
+            escape(&ctx.quantity(&ctx.recipe.inline_quantities[index]))
+        )
+        .unwrap();
+    }
+
+    fn instructions_end(&mut self, w: &mut String, _ctx: &Context) {
+        w.push_str("</section>\n</article>\n");
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,147 @@
+use std::{borrow::Cow, fmt::Write};
+
+use super::{metadata_entries, timer_text, Context, Render};
+use crate::{ingredient_list::GroupedIngredient, model::Section, Step};
+
+/// Markdown renderer
+///
+/// - The name is a `#` heading, followed by the description and a list with
+///   the other metadata.
+/// - The ingredient list and the steps go under `##` headings and the
+///   sections are `###` headings.
+/// - Ingredients are bold, followed by their quantity in parentheses,
+///   cookware is italic, timers are bold and inline quantities are plain
+///   text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Markdown;
+
+impl Render for Markdown {
+    fn header(&mut self, w: &mut String, ctx: &Context) {
+        writeln!(w, "# {}\n", escape(&ctx.recipe.name)).unwrap();
+        if let Some(description) = &ctx.recipe.metadata.description {
+            writeln!(w, "{}\n", escape_block(&escape(description))).unwrap();
+        }
+        let entries = metadata_entries(ctx);
+        for (key, value) in &entries {
+            writeln!(w, "- {}: {}", escape_block(&escape(key)), escape(value)).unwrap();
+        }
+        if !entries.is_empty() {
+            w.push('\n');
+        }
+    }
+
+    fn ingredient_list(&mut self, w: &mut String, ctx: &Context, list: &[GroupedIngredient]) {
+        if list.is_empty() {
+            return;
+        }
+        w.push_str("## Ingredients\n\n");
+        for entry in list {
+            w.push_str("- ");
+            let quantity = ctx.total(&entry.quantity);
+            if !quantity.is_empty() {
+                write!(w, "{} ", escape(&quantity)).unwrap();
+            }
+            write!(w, "**{}**", escape(&entry.ingredient.display_name())).unwrap();
+            if let Some(note) = &entry.ingredient.note {
+                write!(w, " ({})", escape(note)).unwrap();
+            }
+            w.push('\n');
+        }
+        w.push('\n');
+    }
+
+    fn instructions_start(&mut self, w: &mut String, _ctx: &Context) {
+        w.push_str("## Steps\n\n");
+    }
+
+    fn section_start(&mut self, w: &mut String, _ctx: &Context, _index: usize, section: &Section) {
+        if let Some(name) = &section.name {
+            writeln!(w, "### {}\n", escape(name)).unwrap();
+        }
+    }
+
+    fn step(&mut self, w: &mut String, _ctx: &Context, step: &Step, content: &str) {
+        if let Some(number) = step.number {
+            write!(w, "{number}. ").unwrap();
+        }
+        writeln!(w, "{}\n", escape_block(content)).unwrap();
+    }
+
+    fn text(&mut self, w: &mut String, _ctx: &Context, text: &str) {
+        w.push_str(&escape(text));
+    }
+
+    fn ingredient(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        let ingredient = &ctx.recipe.ingredients[index];
+        write!(w, "**{}**", escape(&ingredient.display_name())).unwrap();
+        if let Some(quantity) = &ingredient.quantity {
+            write!(w, " ({})", escape(&ctx.quantity(quantity))).unwrap();
+        }
+    }
+
+    fn cookware(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        write!(w, "*{}*", escape(ctx.recipe.cookware[index].display_name())).unwrap();
+    }
+
+    fn timer(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        write!(w, "**{}**", escape(&timer_text(ctx, index))).unwrap();
+    }
+
+    fn inline_quantity(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        w.push_str(&escape(&ctx.quantity(&ctx.recipe.inline_quantities[index])));
+    }
+}
+
+/// Escapes the characters that have a meaning inline in Markdown
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes the start of a paragraph if it would be read as another block,
+/// like a heading, a list item or a quote
+fn escape_block(s: &str) -> Cow<'_, str> {
+    let digits = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (number, rest) = s.split_at(digits);
+    let is_marker = if digits == 0 {
+        rest.starts_with(['#', '-', '+', '>', '='])
+    } else {
+        rest.starts_with(['.', ')'])
+    };
+    if is_marker {
+        Cow::Owned(format!("{number}\\{rest}"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("plain text" => "plain text"; "plain")]
+    #[test_case("2 * 3_4" => r"2 \* 3\_4"; "emphasis")]
+    #[test_case("[link](url) <b>" => r"\[link\](url) \<b\>"; "link and html")]
+    fn escape_text(s: &str) -> String {
+        escape(s)
+    }
+
+    #[test_case("# not a heading" => r"\# not a heading"; "heading")]
+    #[test_case("- item" => r"\- item"; "list")]
+    #[test_case("+ item" => r"\+ item"; "plus list")]
+    #[test_case("= text" => r"\= text"; "setext")]
+    #[test_case("1. item" => r"1\. item"; "ordered list")]
+    #[test_case("10) item" => r"10\) item"; "ordered list parenthesis")]
+    #[test_case("100 g" => "100 g"; "number")]
+    #[test_case("a - b" => "a - b"; "not at the start")]
+    fn escape_block_start(s: &str) -> String {
+        escape_block(s).into_owned()
+    }
+}
@@ -0,0 +1,328 @@
+//! Render recipes as [Markdown] or [HTML](Html)
+//!
+//! The renderers go through a [`ScaledRecipe`] and write:
+//!
+//! - A header with the name and the [metadata](crate::Metadata).
+//! - The [ingredient list](ScaledRecipe::group_ingredients), without the
+//!   hidden ingredients.
+//! - The sections, with a header if they have a name, and their steps. The
+//!   steps are numbered with [`Step::number`] and text steps are just
+//!   paragraphs.
+//!
+//! Ingredients, cookware, timers and inline quantities inside the steps are
+//! marked up. In HTML, they have a `data-index` attribute with their index
+//! in the recipe.
+//!
+//! ```
+//! # use cooklang::{render, CooklangParser};
+//! let parser = CooklangParser::default();
+//! let src = "Add @salt{1%tsp} to the #pot{}.";
+//! let recipe = parser.parse(src, "Salty").into_output().unwrap();
+//! let recipe = recipe.default_scale();
+//!
+//! let md = render::markdown(&recipe, parser.converter());
+//! assert!(md.contains("1. Add **salt** (1 tsp) to the *pot*."));
+//!
+//! let html = render::html(&recipe, parser.converter());
+//! assert!(html.contains(r#"<span class="ingredient" data-index="0">salt</span>"#));
+//! ```
+//!
+//! # Customization
+//!
+//! Both renderers implement [`Render`], which has a method for each part of
+//! the recipe. All of them have a default implementation that writes plain
+//! text, so a renderer only needs to change some parts:
+//!
+//! ```
+//! # use cooklang::{render::{Context, Render}, CooklangParser};
+//! struct Shouting;
+//!
+//! impl Render for Shouting {
+//!     fn ingredient(&mut self, w: &mut String, ctx: &Context, index: usize) {
+//!         w.push_str(&ctx.recipe.ingredients[index].name.to_uppercase());
+//!     }
+//! }
+//!
+//! let parser = CooklangParser::default();
+//! let recipe = parser.parse("Add @salt.", "Salty").into_output().unwrap();
+//! let text = Shouting.render(&recipe.default_scale(), parser.converter());
+//! assert!(text.contains("1. Add SALT."));
+//! ```
+//!
+//! Quantities are written with a [`QuantityFormatter`], the default one or
+//! the one given to [`Render::render_with_formatter`].
+//!
+//! ```
+//! # use cooklang::{render::{Markdown, Render}, quantity::format::QuantityFormatter, CooklangParser};
+//! let parser = CooklangParser::default();
+//! let recipe = parser.parse("Add @milk{0.5%cup}.", "Milky").into_output().unwrap();
+//! let md = Markdown.render_with_formatter(
+//!     &recipe.default_scale(),
+//!     parser.converter(),
+//!     &QuantityFormatter::cooking(),
+//! );
+//! assert!(md.contains("1. Add **milk** (½ cup)."));
+//! ```
+
+mod html;
+mod markdown;
+
+pub use html::Html;
+pub use markdown::Markdown;
+
+use std::fmt::Write;
+
+use crate::{
+    convert::Converter,
+    ingredient_list::GroupedIngredient,
+    model::{ComponentKind, Item, Section, Step},
+    quantity::{format::QuantityFormatter, GroupedQuantity, Quantity},
+    ScaledRecipe,
+};
+
+/// Renders a recipe as Markdown
+///
+/// Shorthand for [`Markdown::render`](Render::render).
+pub fn markdown(recipe: &ScaledRecipe, converter: &Converter) -> String {
+    Markdown.render(recipe, converter)
+}
+
+/// Renders a recipe as HTML
+///
+/// Shorthand for [`Html::render`](Render::render).
+pub fn html(recipe: &ScaledRecipe, converter: &Converter) -> String {
+    Html::default().render(recipe, converter)
+}
+
+/// The recipe being rendered and how to write its quantities
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    /// The recipe
+    pub recipe: &'a ScaledRecipe,
+    /// Converter used to format the quantities
+    pub converter: &'a Converter,
+    /// Formatter of the quantities
+    pub formatter: &'a QuantityFormatter,
+}
+
+impl Context<'_> {
+    /// Formats a quantity with [`Self::formatter`]
+    pub fn quantity(&self, quantity: &Quantity) -> String {
+        quantity.format(self.formatter, self.converter)
+    }
+
+    /// Formats the total of a grouped quantity with [`Self::formatter`]
+    pub fn total(&self, quantity: &GroupedQuantity) -> String {
+        quantity.total().format(self.formatter, self.converter)
+    }
+}
+
+/// A recipe renderer
+///
+/// [`Render::render`] calls the other methods in order. Each method writes
+/// its part of the output to `w`. By default, they write plain text.
+///
+/// Steps are rendered first to a separate string, with [`Render::text`] and
+/// the component methods, and then given to [`Render::step`].
+pub trait Render {
+    /// Name and metadata of the recipe
+    fn header(&mut self, w: &mut String, ctx: &Context) {
+        writeln!(w, "{}\n", ctx.recipe.name).unwrap();
+        if let Some(description) = &ctx.recipe.metadata.description {
+            writeln!(w, "{description}\n").unwrap();
+        }
+        let entries = metadata_entries(ctx);
+        for (key, value) in &entries {
+            writeln!(w, "{key}: {value}").unwrap();
+        }
+        if !entries.is_empty() {
+            w.push('\n');
+        }
+    }
+
+    /// The ingredient list
+    ///
+    /// Called even if the list is empty.
+    fn ingredient_list(&mut self, w: &mut String, ctx: &Context, list: &[GroupedIngredient]) {
+        if list.is_empty() {
+            return;
+        }
+        w.push_str("Ingredients:\n");
+        for entry in list {
+            w.push_str("- ");
+            let quantity = ctx.total(&entry.quantity);
+            if !quantity.is_empty() {
+                write!(w, "{quantity} ").unwrap();
+            }
+            w.push_str(&entry.ingredient.display_name());
+            if let Some(note) = &entry.ingredient.note {
+                write!(w, " ({note})").unwrap();
+            }
+            w.push('\n');
+        }
+        w.push('\n');
+    }
+
+    /// Before the sections
+    fn instructions_start(&mut self, w: &mut String, ctx: &Context) {
+        let _ = ctx;
+        w.push_str("Steps:\n\n");
+    }
+
+    /// Start of a section, before its steps
+    fn section_start(&mut self, w: &mut String, ctx: &Context, index: usize, section: &Section) {
+        let _ = (ctx, index);
+        if let Some(name) = &section.name {
+            writeln!(w, "{name}:\n").unwrap();
+        }
+    }
+
+    /// End of a section, after its steps
+    fn section_end(&mut self, w: &mut String, ctx: &Context, index: usize, section: &Section) {
+        let _ = (w, ctx, index, section);
+    }
+
+    /// A step, with its items already rendered in `content`
+    fn step(&mut self, w: &mut String, ctx: &Context, step: &Step, content: &str) {
+        let _ = ctx;
+        if let Some(number) = step.number {
+            write!(w, "{number}. ").unwrap();
+        }
+        writeln!(w, "{content}\n").unwrap();
+    }
+
+    /// Text inside a step
+    fn text(&mut self, w: &mut String, ctx: &Context, text: &str) {
+        let _ = ctx;
+        w.push_str(text);
+    }
+
+    /// Ingredient inside a step
+    fn ingredient(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        let ingredient = &ctx.recipe.ingredients[index];
+        w.push_str(&ingredient.display_name());
+        if let Some(quantity) = &ingredient.quantity {
+            write!(w, " ({})", ctx.quantity(quantity)).unwrap();
+        }
+    }
+
+    /// Cookware inside a step
+    fn cookware(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        w.push_str(ctx.recipe.cookware[index].display_name());
+    }
+
+    /// Timer inside a step
+    fn timer(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        w.push_str(&timer_text(ctx, index));
+    }
+
+    /// Inline quantity inside a step
+    fn inline_quantity(&mut self, w: &mut String, ctx: &Context, index: usize) {
+        w.push_str(&ctx.quantity(&ctx.recipe.inline_quantities[index]));
+    }
+
+    /// After the sections, the end of the recipe
+    fn instructions_end(&mut self, w: &mut String, ctx: &Context) {
+        let _ = ctx;
+        // only one line break at the end
+        w.truncate(w.trim_end().len());
+        w.push('\n');
+    }
+
+    /// Renders the whole recipe
+    ///
+    /// The quantities are written with the default [`QuantityFormatter`].
+    fn render(&mut self, recipe: &ScaledRecipe, converter: &Converter) -> String {
+        self.render_with_formatter(recipe, converter, &QuantityFormatter::default())
+    }
+
+    /// Renders the whole recipe, writing the quantities with `formatter`
+    fn render_with_formatter(
+        &mut self,
+        recipe: &ScaledRecipe,
+        converter: &Converter,
+        formatter: &QuantityFormatter,
+    ) -> String {
+        let ctx = &Context {
+            recipe,
+            converter,
+            formatter,
+        };
+        let mut w = String::new();
+        self.header(&mut w, ctx);
+
+        let list = recipe
+            .group_ingredients(converter)
+            .into_iter()
+            .filter(|entry| !entry.ingredient.modifiers().is_hidden())
+            .collect::<Vec<_>>();
+        self.ingredient_list(&mut w, ctx, &list);
+
+        self.instructions_start(&mut w, ctx);
+        for (index, section) in recipe.sections.iter().enumerate() {
+            if section.is_empty() {
+                continue;
+            }
+            self.section_start(&mut w, ctx, index, section);
+            for step in &section.steps {
+                let mut content = String::new();
+                for item in &step.items {
+                    match item {
+                        Item::Text { value } => self.text(&mut content, ctx, value),
+                        Item::ItemComponent { value } => match value.kind {
+                            ComponentKind::IngredientKind => {
+                                self.ingredient(&mut content, ctx, value.index)
+                            }
+                            ComponentKind::CookwareKind => {
+                                self.cookware(&mut content, ctx, value.index)
+                            }
+                            ComponentKind::TimerKind => self.timer(&mut content, ctx, value.index),
+                        },
+                        Item::InlineQuantity { value } => {
+                            self.inline_quantity(&mut content, ctx, *value)
+                        }
+                    }
+                }
+                self.step(&mut w, ctx, step, content.trim());
+            }
+            self.section_end(&mut w, ctx, index, section);
+        }
+
+        self.instructions_end(&mut w, ctx);
+        w
+    }
+}
+
+/// Metadata entries to show in the header
+///
+/// The description is left out and the servings and yield are the
+/// [scaled](ScaledRecipe::scaled_servings) ones.
+pub(crate) fn metadata_entries<'a>(ctx: &Context<'a>) -> Vec<(&'a str, String)> {
+    let recipe = ctx.recipe;
+    let servings = recipe.scaled_servings().map(|s| s.to_string());
+    let yields = recipe.scaled_yield().map(|q| ctx.quantity(&q));
+    recipe
+        .metadata
+        .map
+        .iter()
+        .filter(|(key, _)| key.as_str() != "description" && !key.starts_with('['))
+        .map(|(key, value)| {
+            let scaled = match key.as_str() {
+                "servings" => servings.clone(),
+                "yield" => yields.clone(),
+                _ => None,
+            };
+            (key.as_str(), scaled.unwrap_or_else(|| value.clone()))
+        })
+        .collect()
+}
+
+/// Visible text of a timer: its quantity or its name
+pub(crate) fn timer_text(ctx: &Context, index: usize) -> String {
+    let timer = &ctx.recipe.timers[index];
+    match (&timer.quantity, &timer.name) {
+        (Some(quantity), _) => ctx.quantity(quantity),
+        (None, Some(name)) => name.clone(),
+        (None, None) => String::new(),
+    }
+}
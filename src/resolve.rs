@@ -0,0 +1,609 @@
+//! Resolve recipe references across a collection of recipes
+//!
+//! Ingredients with the [`RECIPE`](crate::ast::Modifiers::RECIPE) modifier
+//! (`@@other recipe{}`) reference another recipe. A [`Resolver`] loads a
+//! recipe and, recursively, all the recipes it references from a
+//! [`RecipeSource`], building a [`RecipeGraph`].
+//!
+//! References are paths, relative to the root of the source, or to the
+//! referencing recipe if they start with `./` or `../`. The `.cook` extension
+//! is optional.
+//!
+//! ```
+//! # use cooklang::{CooklangParser, resolve::{MemorySource, Resolver}};
+//! let source = MemorySource::from_iter([
+//!     ("Pizza", ">> time: 20 min\nTop @@sauces/Tomato sauce{150%g} with @cheese."),
+//!     ("sauces/Tomato sauce", ">> time: 30 min\nCook @tomatoes{400%g} and @@./Sofrito{}."),
+//!     ("sauces/Sofrito", "Fry @onion{1}."),
+//! ]);
+//! let parser = CooklangParser::default();
+//! let graph = Resolver::new(&parser, source).resolve(["Pizza"]);
+//! assert!(graph.is_valid());
+//!
+//! // dependencies are before the recipes that use them
+//! assert_eq!(
+//!     graph.order().collect::<Vec<_>>(),
+//!     ["sauces/Sofrito", "sauces/Tomato sauce", "Pizza"]
+//! );
+//! assert_eq!(graph.total_time("Pizza"), Some(50));
+//!
+//! let tree = graph.ingredient_tree("Pizza").unwrap();
+//! let sauce = tree.ingredients[0].recipe.as_ref().unwrap();
+//! assert_eq!(sauce.ingredients[0].ingredient.name, "tomatoes");
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use indexmap::IndexMap;
+use thiserror::Error;
+
+use crate::{
+    ast::Modifiers,
+    error::{label, CooklangReport, RichError},
+    model::{Ingredient, Recipe},
+    span::Span,
+    CooklangParser,
+};
+
+/// Where to load recipes from
+///
+/// Each recipe is identified by an id given by the source.
+pub trait RecipeSource {
+    /// Finds the recipe for a `reference`
+    ///
+    /// `from` is the id of the recipe where the reference is, or [`None`] if
+    /// it's not from a recipe. Returns the id of the recipe or [`None`] if it
+    /// does not exist.
+    fn locate(&self, reference: &str, from: Option<&str>) -> Option<String>;
+
+    /// Reads the cooklang source of a recipe
+    fn read(&self, id: &str) -> std::io::Result<String>;
+
+    /// Name of a recipe
+    ///
+    /// By default, the file name of the id without the `.cook` extension.
+    fn name(&self, id: &str) -> String {
+        let file = id.rsplit('/').next().unwrap_or(id);
+        file.strip_suffix(".cook").unwrap_or(file).to_string()
+    }
+}
+
+/// Recipes in a directory
+///
+/// Ids are the `/` separated paths of the files relative to the root
+/// directory, with the `.cook` extension.
+#[derive(Debug, Clone)]
+pub struct FsSource {
+    root: PathBuf,
+}
+
+impl FsSource {
+    /// Creates a new source with the recipes inside `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.root.join(id)
+    }
+}
+
+impl RecipeSource for FsSource {
+    fn locate(&self, reference: &str, from: Option<&str>) -> Option<String> {
+        let mut id = resolve_path(reference, from)?;
+        if !id.ends_with(".cook") {
+            id.push_str(".cook");
+        }
+        self.path(&id).is_file().then_some(id)
+    }
+
+    fn read(&self, id: &str) -> std::io::Result<String> {
+        std::fs::read_to_string(self.path(id))
+    }
+}
+
+/// Recipes in memory
+///
+/// Ids are `/` separated paths without the `.cook` extension, like
+/// `sauces/Tomato sauce`.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    recipes: HashMap<String, String>,
+}
+
+impl MemorySource {
+    /// Creates an empty source
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces a recipe
+    pub fn insert(&mut self, id: impl Into<String>, content: impl Into<String>) {
+        self.recipes.insert(id.into(), content.into());
+    }
+
+    /// Removes a recipe
+    pub fn remove(&mut self, id: &str) -> Option<String> {
+        self.recipes.remove(id)
+    }
+}
+
+impl<I: Into<String>, C: Into<String>> FromIterator<(I, C)> for MemorySource {
+    fn from_iter<T: IntoIterator<Item = (I, C)>>(iter: T) -> Self {
+        let mut source = Self::new();
+        for (id, content) in iter {
+            source.insert(id, content);
+        }
+        source
+    }
+}
+
+impl RecipeSource for MemorySource {
+    fn locate(&self, reference: &str, from: Option<&str>) -> Option<String> {
+        let path = resolve_path(reference, from)?;
+        let id = path.strip_suffix(".cook").unwrap_or(&path);
+        self.recipes.contains_key(id).then(|| id.to_string())
+    }
+
+    fn read(&self, id: &str) -> std::io::Result<String> {
+        self.recipes
+            .get(id)
+            .cloned()
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }
+}
+
+/// Joins a reference with the directory of `from` if it's relative and
+/// normalizes the path
+///
+/// [`None`] if the path goes outside the root.
+fn resolve_path(reference: &str, from: Option<&str>) -> Option<String> {
+    let mut parts = Vec::new();
+    if reference.starts_with("./") || reference.starts_with("../") {
+        if let Some((dir, _)) = from.and_then(|f| f.rsplit_once('/')) {
+            parts.extend(dir.split('/'));
+        }
+    }
+    for part in reference.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Loads recipes and the recipes they reference
+#[derive(Debug)]
+pub struct Resolver<'p, S> {
+    parser: &'p CooklangParser,
+    source: S,
+}
+
+impl<'p, S: RecipeSource> Resolver<'p, S> {
+    /// Creates a new resolver
+    pub fn new(parser: &'p CooklangParser, source: S) -> Self {
+        Self { parser, source }
+    }
+
+    /// Get the source
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Loads the recipes with the given ids and all the recipes they
+    /// reference, recursively
+    ///
+    /// This never fails, the errors found are in [`RecipeGraph::errors`] and
+    /// the recipes with errors are not in the graph.
+    pub fn resolve<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> RecipeGraph {
+        let mut graph = RecipeGraph::default();
+        let mut pending = ids.into_iter().map(String::from).collect::<Vec<_>>();
+        pending.reverse();
+
+        while let Some(id) = pending.pop() {
+            if graph.recipes.contains_key(&id) || graph.failed.contains(&id) {
+                continue;
+            }
+            let input = match self.source.read(&id) {
+                Ok(input) => input,
+                Err(source) => {
+                    graph.fail(ResolveError::Io { id, source });
+                    continue;
+                }
+            };
+            let name = self.source.name(&id);
+            let recipe = match self.parser.parse(&input, &name).into_result() {
+                Ok((recipe, _)) => recipe,
+                Err(report) => {
+                    graph.fail(ResolveError::Parse { id, input, report });
+                    continue;
+                }
+            };
+
+            let mut dependencies = Vec::new();
+            for (index, igr) in recipe.ingredients.iter().enumerate() {
+                if !is_recipe_dependency(igr) {
+                    continue;
+                }
+                match self.source.locate(&igr.name, Some(&id)) {
+                    Some(dep) => {
+                        pending.push(dep.clone());
+                        dependencies.push(Dependency {
+                            ingredient: index,
+                            id: dep,
+                        });
+                    }
+                    None => graph.errors.push(ResolveError::NotFound {
+                        from: id.clone(),
+                        reference: igr.name.clone(),
+                        span: igr.span,
+                    }),
+                }
+            }
+            // keep the order of the references
+            let first_new = pending.len() - dependencies.len();
+            pending[first_new..].reverse();
+
+            graph.recipes.insert(
+                id,
+                ResolvedRecipe {
+                    recipe,
+                    input,
+                    dependencies,
+                },
+            );
+        }
+
+        graph.sort();
+        graph
+    }
+}
+
+fn is_recipe_dependency(igr: &Ingredient) -> bool {
+    igr.modifiers().contains(Modifiers::RECIPE) && igr.relation.is_definition()
+}
+
+/// Recipes and the references between them
+#[derive(Debug, Default)]
+pub struct RecipeGraph {
+    recipes: IndexMap<String, ResolvedRecipe>,
+    /// ids of the recipes that could not be loaded, to not retry them
+    failed: HashSet<String>,
+    errors: Vec<ResolveError>,
+}
+
+/// A recipe in a [`RecipeGraph`]
+#[derive(Debug)]
+pub struct ResolvedRecipe {
+    /// The parsed recipe
+    pub recipe: Recipe,
+    /// The cooklang source of the recipe
+    pub input: String,
+    /// Recipes referenced, in order
+    ///
+    /// A reference to a recipe that could not be loaded is not here.
+    pub dependencies: Vec<Dependency>,
+}
+
+/// A reference to another recipe
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// Index of the ingredient in [`Recipe::ingredients`]
+    pub ingredient: usize,
+    /// Id of the referenced recipe
+    pub id: String,
+}
+
+impl RecipeGraph {
+    fn fail(&mut self, err: ResolveError) {
+        self.failed.insert(err.recipe().to_string());
+        self.errors.push(err);
+    }
+
+    /// Sorts the recipes so dependencies go first and checks for cycles
+    fn sort(&mut self) {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Visiting,
+            Done,
+        }
+
+        let mut state = vec![State::New; self.recipes.len()];
+        let mut order = Vec::with_capacity(self.recipes.len());
+        for root in 0..self.recipes.len() {
+            if state[root] != State::New {
+                continue;
+            }
+            // (recipe, next dependency to visit)
+            let mut stack = vec![(root, 0)];
+            state[root] = State::Visiting;
+            while let Some((node, next)) = stack.last_mut() {
+                let deps = &self.recipes[*node].dependencies;
+                let Some(dep) = deps.get(*next) else {
+                    state[*node] = State::Done;
+                    order.push(*node);
+                    stack.pop();
+                    continue;
+                };
+                *next += 1;
+                let from = *node;
+                let Some(target) = self.recipes.get_index_of(&dep.id) else {
+                    continue;
+                };
+                match state[target] {
+                    State::New => {
+                        state[target] = State::Visiting;
+                        stack.push((target, 0));
+                    }
+                    State::Visiting => {
+                        let start = stack.iter().position(|&(n, _)| n == target).unwrap();
+                        let mut path = stack[start..]
+                            .iter()
+                            .map(|&(n, _)| self.recipes.get_index(n).unwrap().0.clone())
+                            .collect::<Vec<_>>();
+                        path.push(dep.id.clone());
+                        let igr = &self.recipes[from].recipe.ingredients[dep.ingredient];
+                        self.errors.push(ResolveError::Cycle {
+                            path,
+                            span: igr.span,
+                        });
+                    }
+                    State::Done => {}
+                }
+            }
+        }
+
+        let mut recipes = std::mem::take(&mut self.recipes)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.recipes = order
+            .into_iter()
+            .map(|i| recipes[i].take().unwrap())
+            .collect();
+    }
+
+    /// Errors found while resolving
+    pub fn errors(&self) -> &[ResolveError] {
+        &self.errors
+    }
+
+    /// Checks that every recipe and reference was resolved without errors
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Get a recipe
+    pub fn get(&self, id: &str) -> Option<&ResolvedRecipe> {
+        self.recipes.get(id)
+    }
+
+    /// Iterate over all the recipes, dependencies before the recipes that use
+    /// them
+    ///
+    /// If there are cycles, the order inside the cycle is not specified.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ResolvedRecipe)> {
+        self.recipes.iter().map(|(id, r)| (id.as_str(), r))
+    }
+
+    /// Ids of all the recipes, in the order of [`Self::iter`]
+    pub fn order(&self) -> impl Iterator<Item = &str> {
+        self.recipes.keys().map(String::as_str)
+    }
+
+    /// Ids of the recipes that reference `id`
+    pub fn dependents<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a str> {
+        self.iter()
+            .filter(move |(_, r)| r.dependencies.iter().any(|d| d.id == id))
+            .map(|(id, _)| id)
+    }
+
+    /// Ids of all the recipes needed to make `id`, excluding itself
+    pub fn all_dependencies(&self, id: &str) -> Vec<&str> {
+        let mut found = Vec::new();
+        let mut pending = vec![id];
+        while let Some(current) = pending.pop() {
+            let Some(recipe) = self.recipes.get(current) else {
+                continue;
+            };
+            for dep in &recipe.dependencies {
+                if dep.id != id && !found.contains(&dep.id.as_str()) {
+                    found.push(dep.id.as_str());
+                    pending.push(&dep.id);
+                }
+            }
+        }
+        found
+    }
+
+    /// Total time in minutes to make a recipe and all the recipes it needs
+    ///
+    /// Every needed recipe is counted once, even if it's referenced many
+    /// times. [`None`] if no recipe has a time.
+    pub fn total_time(&self, id: &str) -> Option<u32> {
+        std::iter::once(id)
+            .chain(self.all_dependencies(id))
+            .filter_map(|id| self.recipes.get(id)?.recipe.metadata.time)
+            .map(|t| t.total())
+            .reduce(|a, b| a + b)
+    }
+
+    /// Ingredients of a recipe, with the ingredients of the referenced
+    /// recipes nested
+    pub fn ingredient_tree(&self, id: &str) -> Option<IngredientTree<'_>> {
+        self.tree(id, &mut Vec::new())
+    }
+
+    fn tree<'a>(&'a self, id: &str, stack: &mut Vec<&'a str>) -> Option<IngredientTree<'a>> {
+        let (_, id, resolved) = self.recipes.get_full(id)?;
+        // a cycle, the error is already reported
+        if stack.contains(&id.as_str()) {
+            return None;
+        }
+        stack.push(id);
+        let ingredients = resolved
+            .recipe
+            .ingredients
+            .iter()
+            .enumerate()
+            .filter(|(_, igr)| igr.relation.is_definition())
+            .map(|(index, ingredient)| IngredientNode {
+                index,
+                ingredient,
+                recipe: resolved
+                    .dependencies
+                    .iter()
+                    .find(|d| d.ingredient == index)
+                    .and_then(|d| self.tree(&d.id, stack)),
+            })
+            .collect();
+        stack.pop();
+        Some(IngredientTree {
+            id,
+            recipe: &resolved.recipe,
+            ingredients,
+        })
+    }
+}
+
+/// Ingredients of a recipe, see [`RecipeGraph::ingredient_tree`]
+#[derive(Debug, Clone)]
+pub struct IngredientTree<'a> {
+    /// Id of the recipe
+    pub id: &'a str,
+    /// The recipe
+    pub recipe: &'a Recipe,
+    /// Ingredient definitions of the recipe, in order
+    pub ingredients: Vec<IngredientNode<'a>>,
+}
+
+/// An ingredient in an [`IngredientTree`]
+#[derive(Debug, Clone)]
+pub struct IngredientNode<'a> {
+    /// Index of the ingredient in [`Recipe::ingredients`]
+    pub index: usize,
+    /// The ingredient
+    pub ingredient: &'a Ingredient,
+    /// Ingredients of the recipe, if the ingredient is a resolved recipe
+    /// reference
+    pub recipe: Option<IngredientTree<'a>>,
+}
+
+impl IngredientTree<'_> {
+    /// All the ingredients that are not recipes, in depth first order
+    pub fn leaves(&self) -> Vec<&IngredientNode<'_>> {
+        let mut leaves = Vec::new();
+        for node in &self.ingredients {
+            match &node.recipe {
+                Some(tree) => leaves.extend(tree.leaves()),
+                None => leaves.push(node),
+            }
+        }
+        leaves
+    }
+}
+
+/// Error generated by [`Resolver::resolve`]
+///
+/// The locations of the labels are in the source of [`Self::recipe`].
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("Recipe not found: '{reference}'")]
+    NotFound {
+        /// Id of the recipe with the reference
+        from: String,
+        /// The reference
+        reference: String,
+        /// Location of the reference
+        span: Option<Span>,
+    },
+    #[error("Error reading recipe '{id}'")]
+    Io { id: String, source: std::io::Error },
+    #[error("Error parsing recipe '{id}'")]
+    Parse {
+        id: String,
+        /// The cooklang source of the recipe
+        input: String,
+        /// Parser errors and warnings
+        report: CooklangReport,
+    },
+    #[error("Recipe reference cycle: {}", path.join(" -> "))]
+    Cycle {
+        /// Ids of the recipes in the cycle, the first and last are the same
+        path: Vec<String>,
+        /// Location of the reference that closes the cycle
+        span: Option<Span>,
+    },
+}
+
+impl ResolveError {
+    /// Id of the recipe the error is in
+    pub fn recipe(&self) -> &str {
+        match self {
+            ResolveError::NotFound { from, .. } => from,
+            ResolveError::Io { id, .. } | ResolveError::Parse { id, .. } => id,
+            ResolveError::Cycle { path, .. } => &path[path.len() - 2],
+        }
+    }
+}
+
+impl RichError for ResolveError {
+    fn labels(&self) -> Vec<(Span, Option<Cow<'static, str>>)> {
+        match self {
+            ResolveError::NotFound { span: Some(s), .. } => vec![label!(s)],
+            ResolveError::Cycle {
+                span: Some(s),
+                path,
+            } => {
+                vec![label!(s, format!("this references '{}'", path[0]))]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn help(&self) -> Option<Cow<'static, str>> {
+        match self {
+            ResolveError::NotFound { .. } => Some(
+                "References are relative to the collection, or to the recipe if they start with './' or '../'".into(),
+            ),
+            ResolveError::Cycle { .. } => {
+                Some("A recipe cannot be an ingredient of itself, not even indirectly".into())
+            }
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        Some("resolve")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("Sauce", None => Some("Sauce".into()); "root")]
+    #[test_case("Sauce", Some("a/b/Pizza") => Some("Sauce".into()); "not relative")]
+    #[test_case("./Sauce", Some("a/b/Pizza") => Some("a/b/Sauce".into()); "same dir")]
+    #[test_case("../Sauce", Some("a/b/Pizza") => Some("a/Sauce".into()); "parent")]
+    #[test_case("./x/../Sauce", Some("Pizza") => Some("Sauce".into()); "normalize")]
+    #[test_case("../Sauce", Some("Pizza") => None; "outside")]
+    fn paths(reference: &str, from: Option<&str>) -> Option<String> {
+        resolve_path(reference, from)
+    }
+}
@@ -5,16 +5,41 @@ use thiserror::Error;
 
 use crate::{
     convert::Converter,
-    quantity::{QuantityValue, TextValueError, Value},
+    quantity::{Fraction, Quantity, QuantityValue, TextValueError, Value},
     Recipe, ScaledRecipe,
 };
 
 /// Configures the scaling target
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ScaleTarget {
-    base: u32,
-    target: u32,
+    kind: ScaleTargetKind,
+    factor: f64,
     index: Option<usize>,
+    #[serde(default = "default_base")]
+    base: u32,
+}
+
+fn default_base() -> u32 {
+    1
+}
+
+/// What a recipe was scaled to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScaleTargetKind {
+    /// A number of servings
+    Servings { base: u32, target: u32 },
+    /// An arbitrary factor
+    Factor,
+    /// A quantity of an ingredient
+    Ingredient {
+        /// Index of the ingredient in the recipe
+        index: usize,
+    },
+    /// A quantity of what the recipe makes, the `yield` metadata
+    Yield,
 }
 
 impl ScaleTarget {
@@ -30,15 +55,125 @@ impl ScaleTarget {
     /// scaling process.
     pub fn new(base: u32, target: u32, declared_servings: &[u32]) -> Self {
         ScaleTarget {
-            base,
-            target,
+            kind: ScaleTargetKind::Servings { base, target },
+            factor: target as f64 / base as f64,
             index: declared_servings.iter().position(|&s| s == target),
+            base,
         }
     }
 
+    /// Scale by an arbitrary factor
+    ///
+    /// Values with the scaling defined for each number of servings are only
+    /// scalable if the factor results in one of the declared servings.
+    pub fn by_factor(factor: f64) -> Self {
+        ScaleTarget {
+            kind: ScaleTargetKind::Factor,
+            factor,
+            index: None,
+            base: 1,
+        }
+    }
+
+    /// Scale so the ingredient at `index` has the wanted `quantity`
+    ///
+    /// The quantity is converted to the unit of the ingredient, crossing
+    /// between volume and mass if the [`Converter`] knows the ingredient
+    /// density. The quantity of the ingredient has to be scalable.
+    ///
+    /// ```
+    /// # use cooklang::{CooklangParser, Quantity, QuantityValue, scale::ScaleTarget};
+    /// let parser = CooklangParser::default();
+    /// let recipe = parser
+    ///     .parse("Mix @flour{500*%g} and @water{300*%ml}.", "Bread")
+    ///     .into_output()
+    ///     .unwrap();
+    /// let flour = Quantity::new(
+    ///     QuantityValue::Fixed { value: 750.0.into() },
+    ///     Some("g".into()),
+    /// );
+    /// let target = ScaleTarget::ingredient(&recipe, 0, flour, parser.converter())?;
+    /// assert_eq!(target.factor(), 1.5);
+    /// let scaled = recipe.scale_to(target, parser.converter());
+    /// assert_eq!(scaled.ingredients[1].quantity.as_ref().unwrap().to_string(), "450 ml");
+    /// # Ok::<(), cooklang::scale::ScaleError>(())
+    /// ```
+    pub fn ingredient(
+        recipe: &Recipe,
+        index: usize,
+        quantity: Quantity,
+        converter: &Converter,
+    ) -> Result<Self, ScaleError> {
+        let igr = recipe
+            .ingredients
+            .get(index)
+            .ok_or(ScaleError::InvalidTarget {
+                reason: "ingredient index out of bounds",
+            })?;
+        let reference = igr.quantity.as_ref().ok_or(ScaleError::InvalidTarget {
+            reason: "the ingredient has no quantity",
+        })?;
+        if matches!(reference.value, QuantityValue::Fixed { .. }) {
+            return Err(ScaleError::InvalidTarget {
+                reason: "the ingredient quantity is not scalable",
+            });
+        }
+        let reference = Quantity::new(
+            QuantityValue::Fixed {
+                value: first_value(&reference.value).clone(),
+            },
+            reference.unit_text().map(str::to_string),
+        );
+        let factor = target_factor(&quantity, &reference, |q, unit| {
+            converter
+                .convert_ingredient(q, unit, &igr.name)
+                .ok()
+                .map(|(q, _)| q)
+        })?;
+        Ok(ScaleTarget {
+            kind: ScaleTargetKind::Ingredient { index },
+            factor,
+            index: None,
+            base: 1,
+        })
+    }
+
+    /// Scale so the recipe makes the wanted `quantity`
+    ///
+    /// The recipe needs the [`yields`](crate::Metadata::yields) metadata, with
+    /// or without a unit, like `>> yield: 2 loaves` or `>> yield: 1.5 l`. If
+    /// both have a unit, the quantity is converted to the unit of the yield.
+    pub fn for_yield(
+        recipe: &Recipe,
+        quantity: Quantity,
+        converter: &Converter,
+    ) -> Result<Self, ScaleError> {
+        let reference = recipe
+            .metadata
+            .yields
+            .as_ref()
+            .ok_or(ScaleError::InvalidTarget {
+                reason: "the recipe has no yield",
+            })?;
+        let factor = target_factor(&quantity, reference, |q, unit| {
+            converter.convert(q, unit).ok()
+        })?;
+        Ok(ScaleTarget {
+            kind: ScaleTargetKind::Yield,
+            factor,
+            index: None,
+            base: 1,
+        })
+    }
+
+    /// Get what the recipe was scaled to
+    pub fn kind(&self) -> &ScaleTargetKind {
+        &self.kind
+    }
+
     /// Get the calculated scaling factor
     pub fn factor(&self) -> f64 {
-        self.target as f64 / self.base as f64
+        self.factor
     }
 
     /// Get the index into a [`QuantityValue::ByServings`]
@@ -47,26 +182,240 @@ impl ScaleTarget {
     }
 
     /// Get the target servings
+    ///
+    /// When not scaling to a number of servings, this is the base servings
+    /// scaled by the factor and rounded. See [`Self::requested_servings`].
     pub fn target_servings(&self) -> u32 {
-        self.target
+        match self.kind {
+            ScaleTargetKind::Servings { target, .. } => target,
+            _ => (self.base as f64 * self.factor).round() as u32,
+        }
+    }
+
+    /// Get the target servings, only when scaling to a number of servings
+    pub fn requested_servings(&self) -> Option<u32> {
+        match self.kind {
+            ScaleTargetKind::Servings { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+}
+
+/// Factor to go from `reference` to `target`, converting `target` to the
+/// unit of `reference` with `convert` if needed
+pub(crate) fn target_factor(
+    target: &Quantity,
+    reference: &Quantity,
+    convert: impl FnOnce(&Quantity, &str) -> Option<Quantity>,
+) -> Result<f64, ScaleError> {
+    let incompatible = || ScaleError::IncompatibleTarget {
+        target: Box::new(target.clone()),
+        reference: Box::new(reference.clone()),
+    };
+    let value = match (target.unit_text(), reference.unit_text()) {
+        (None, None) => number(first_value(&target.value)),
+        (Some(a), Some(b)) if a == b => number(first_value(&target.value)),
+        (Some(_), Some(unit)) => {
+            let converted = convert(target, unit).ok_or_else(incompatible)?;
+            number(first_value(&converted.value))
+        }
+        _ => return Err(incompatible()),
+    };
+    let base = number(first_value(&reference.value));
+    match (value, base) {
+        (Some(value), Some(base)) if base > 0.0 => {
+            let factor = value / base;
+            if factor.is_finite() && factor > 0.0 {
+                Ok(factor)
+            } else {
+                Err(ScaleError::InvalidTarget {
+                    reason: "the scaling factor has to be a positive number",
+                })
+            }
+        }
+        _ => Err(incompatible()),
+    }
+}
+
+fn first_value(value: &QuantityValue) -> &Value {
+    match value {
+        QuantityValue::Fixed { value } | QuantityValue::Linear { value } => value,
+        QuantityValue::ByServings { values } => {
+            values.first().expect("scalable value servings list empty")
+        }
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number { value } => Some(*value),
+        Value::Fraction { value } => Some(value.to_f64()),
+        Value::Range { .. } | Value::Text { .. } => None,
     }
 }
 
+/// Which components are scaled and how
+///
+/// The [`Default`] policy scales linearly the values marked as scalable,
+/// never scales inline quantities and only fits the ingredients. This is what
+/// [`Recipe::scale`] uses.
+///
+/// ```
+/// # use cooklang::scale::{ComponentPolicy, ScalePolicy, ScaleRule};
+/// let policy = ScalePolicy {
+///     // doubling a recipe does not double the time in the oven
+///     timers: ComponentPolicy {
+///         rule: ScaleRule::Power { exponent: 0.5 },
+///         scale_fixed: true,
+///         fit: true,
+///     },
+///     inline_quantities: ComponentPolicy {
+///         rule: ScaleRule::Never,
+///         scale_fixed: false,
+///         fit: true,
+///     },
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScalePolicy {
+    /// Quantities of the ingredients
+    pub ingredients: ComponentPolicy,
+    /// Quantities of the cookware
+    pub cookware: ComponentPolicy,
+    /// Durations of the timers
+    pub timers: ComponentPolicy,
+    /// Quantities found in the text, like temperatures with the
+    /// [`TEMPERATURE`](crate::Extensions::TEMPERATURE) extension.
+    pub inline_quantities: ComponentPolicy,
+}
+
+impl Default for ScalePolicy {
+    fn default() -> Self {
+        Self {
+            ingredients: ComponentPolicy {
+                rule: ScaleRule::Linear,
+                scale_fixed: false,
+                fit: true,
+            },
+            cookware: ComponentPolicy {
+                rule: ScaleRule::Linear,
+                scale_fixed: false,
+                fit: false,
+            },
+            timers: ComponentPolicy {
+                rule: ScaleRule::Linear,
+                scale_fixed: false,
+                fit: false,
+            },
+            inline_quantities: ComponentPolicy {
+                rule: ScaleRule::Never,
+                scale_fixed: false,
+                fit: false,
+            },
+        }
+    }
+}
+
+/// How to scale one kind of component
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ComponentPolicy {
+    /// How the scalable values change
+    pub rule: ScaleRule,
+    /// Also scale the values not marked as scalable
+    ///
+    /// Timers and cookware can't be marked as scalable in the recipe, so
+    /// this is the only way to scale them with a rule. Text values are
+    /// left as they are.
+    pub scale_fixed: bool,
+    /// Convert to the best unit after scaling, see [`Quantity::fit`].
+    ///
+    /// Cookware quantities have no unit, so this does nothing for them.
+    pub fit: bool,
+}
+
+/// How a scalable value changes with the scaling factor
+///
+/// This only applies to values marked as scalable, unless
+/// [`ComponentPolicy::scale_fixed`] is set. Values with the scaling defined
+/// for each number of servings use those, except with [`ScaleRule::Never`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScaleRule {
+    /// Multiplied by the factor
+    Linear,
+    /// Multiplied by the factor to the power of `exponent`
+    ///
+    /// An exponent lower than 1 makes the value grow slower than the
+    /// servings, like the time to cook a bigger pot of stew.
+    Power { exponent: f64 },
+    /// Never scaled, the default value is used
+    Never,
+}
+
 /// Possible scaled states of a recipe
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Both keep the quantities as written in the recipe, so it can be
+/// [scaled again](ScaledRecipe::rescale).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum Scaled {
     /// The recipe was scaled to its based servings
     ///
     /// This is the values without scaling or if there are many values
     /// for a component, the first one.
-    DefaultScaling,
+    DefaultScaling(OriginalQuantities),
     /// Scaled to a custom target
     Scaled(ScaledData),
 }
 
+/// Quantities of the recipe before scaling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OriginalQuantities {
+    ingredients: Vec<Option<Quantity>>,
+    cookware: Vec<Option<QuantityValue>>,
+    timers: Vec<Option<Quantity>>,
+    inline_quantities: Vec<Quantity>,
+}
+
+impl OriginalQuantities {
+    fn new<D>(recipe: &Recipe<D>) -> Self {
+        Self {
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|i| i.quantity.clone())
+                .collect(),
+            cookware: recipe.cookware.iter().map(|c| c.quantity.clone()).collect(),
+            timers: recipe.timers.iter().map(|t| t.quantity.clone()).collect(),
+            inline_quantities: recipe.inline_quantities.clone(),
+        }
+    }
+
+    fn restore<D>(self, recipe: &mut Recipe<D>) {
+        fn restore_many<T, Q>(
+            components: &mut [T],
+            original: Vec<Q>,
+            slot: impl Fn(&mut T) -> &mut Q,
+        ) {
+            for (c, q) in components.iter_mut().zip(original) {
+                *slot(c) = q;
+            }
+        }
+        restore_many(&mut recipe.ingredients, self.ingredients, |i| {
+            &mut i.quantity
+        });
+        restore_many(&mut recipe.cookware, self.cookware, |c| &mut c.quantity);
+        restore_many(&mut recipe.timers, self.timers, |t| &mut t.quantity);
+        restore_many(&mut recipe.inline_quantities, self.inline_quantities, |q| q);
+    }
+}
+
 /// Data from scaling a recipe
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ScaledData {
     /// What the target was
     pub target: ScaleTarget,
@@ -76,6 +425,12 @@ pub struct ScaledData {
     pub cookware: Vec<ScaleOutcome>,
     /// Outcome of scaling the timers. Use the same index as in the recipe.
     pub timers: Vec<ScaleOutcome>,
+    /// Outcome of scaling the inline quantities. Use the same index as in the
+    /// recipe.
+    #[serde(default)]
+    pub inline_quantities: Vec<ScaleOutcome>,
+    /// Quantities of the recipe before scaling
+    pub original: OriginalQuantities,
 }
 
 /// Possible outcomes from scaling a component
@@ -91,6 +446,27 @@ pub enum ScaleOutcome {
     Error(#[serde(skip)] ScaleError),
 }
 
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for ScaleOutcome {
+    fn schema_name() -> String {
+        "ScaleOutcome".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        // the error is not serialized, so all are just the variant name
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            enum_values: Some(
+                ["Scaled", "Fixed", "NoQuantity", "Error"]
+                    .map(Into::into)
+                    .to_vec(),
+            ),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 /// Possible errors during scaling process
 #[derive(Debug, Error, Clone, Default)]
 pub enum ScaleError {
@@ -109,6 +485,15 @@ pub enum ScaleError {
         value: QuantityValue,
     },
 
+    #[error("Invalid scale target: {reason}")]
+    InvalidTarget { reason: &'static str },
+
+    #[error("Scale target {target} is not compatible with {reference}")]
+    IncompatibleTarget {
+        target: Box<Quantity>,
+        reference: Box<Quantity>,
+    },
+
     /// There has been an error but it can't be determined
     ///
     /// This is used when deserializing, because serializing the [`ScaleOutcome`]
@@ -122,36 +507,90 @@ impl Recipe {
     /// Scale a recipe
     ///
     /// Note that this returns a [`ScaledRecipe`] wich doesn't implement this
-    /// method. A recipe can only be scaled once.
-    pub fn scale(mut self, target: u32, converter: &Converter) -> ScaledRecipe {
+    /// method. To scale it again use [`ScaledRecipe::rescale`] or
+    /// [`ScaledRecipe::unscale`].
+    ///
+    /// This uses the default [`ScalePolicy`], see [`Self::scale_with_policy`].
+    pub fn scale(self, target: u32, converter: &Converter) -> ScaledRecipe {
+        self.scale_with_policy(target, converter, &ScalePolicy::default())
+    }
+
+    /// Scale a recipe choosing how each kind of component is scaled
+    pub fn scale_with_policy(
+        self,
+        target: u32,
+        converter: &Converter,
+        policy: &ScalePolicy,
+    ) -> ScaledRecipe {
         let target = if let Some(servings) = self.metadata.servings.as_ref() {
             let base = servings.first().copied().unwrap_or(1);
             ScaleTarget::new(base, target, servings)
         } else {
             ScaleTarget::new(1, target, &[])
         };
+        self.scale_to_with_policy(target, converter, policy)
+    }
 
-        if target.index() == Some(0) {
-            return self.default_scale();
+    /// Scale a recipe to any [`ScaleTarget`], not only a number of servings
+    pub fn scale_to(self, target: ScaleTarget, converter: &Converter) -> ScaledRecipe {
+        self.scale_to_with_policy(target, converter, &ScalePolicy::default())
+    }
+
+    /// Same as [`Self::scale_to`] choosing how each kind of component is
+    /// scaled
+    pub fn scale_to_with_policy(
+        mut self,
+        mut target: ScaleTarget,
+        converter: &Converter,
+        policy: &ScalePolicy,
+    ) -> ScaledRecipe {
+        let original = OriginalQuantities::new(&self);
+        if let ScaleTargetKind::Servings { .. } = target.kind {
+            if target.index() == Some(0) {
+                return self.default_scale();
+            }
+        } else if let Some(servings) = self.metadata.servings.as_ref() {
+            // a factor may end up in one of the declared servings
+            target.base = servings.first().copied().unwrap_or(1);
+            let servings_target = target.base as f64 * target.factor;
+            target.index = servings
+                .iter()
+                .position(|&s| (s as f64 - servings_target).abs() < 1e-9);
         }
-        let ingredients = scale_many(target, &mut self.ingredients, |igr| {
+        let ingredients = scale_many(&target, policy.ingredients, &mut self.ingredients, |igr| {
             igr.quantity.as_mut().map(|q| &mut q.value)
         });
-        self.ingredients.iter_mut().for_each(|i| {
-            if let Some(q) = &mut i.quantity {
-                let _ = q.fit(converter);
-            }
+        if policy.ingredients.fit {
+            fit_many(converter, &mut self.ingredients, |igr| {
+                igr.quantity.as_mut()
+            });
+        }
+        let cookware = scale_many(&target, policy.cookware, &mut self.cookware, |ck| {
+            ck.quantity.as_mut()
         });
-        let cookware = scale_many(target, &mut self.cookware, |ck| ck.quantity.as_mut());
-        let timers = scale_many(target, &mut self.timers, |tm| {
+        let timers = scale_many(&target, policy.timers, &mut self.timers, |tm| {
             tm.quantity.as_mut().map(|q| &mut q.value)
         });
+        if policy.timers.fit {
+            fit_many(converter, &mut self.timers, |tm| tm.quantity.as_mut());
+        }
+        let inline_quantities = scale_many(
+            &target,
+            policy.inline_quantities,
+            &mut self.inline_quantities,
+            |q| Some(&mut q.value),
+        );
+        if policy.inline_quantities.fit {
+            fit_many(converter, &mut self.inline_quantities, |q| Some(q));
+        }
 
         let data = ScaledData {
             target,
             ingredients,
             cookware,
             timers,
+            inline_quantities,
+            original,
         };
 
         ScaledRecipe {
@@ -171,6 +610,7 @@ impl Recipe {
     /// The default values are the ones written in the recipe and the first one
     /// in [`QuantityValue::ByServings`].
     pub fn default_scale(mut self) -> ScaledRecipe {
+        let original = OriginalQuantities::new(&self);
         default_scale_many(&mut self.ingredients, |igr| {
             igr.quantity.as_mut().map(|q| &mut q.value)
         });
@@ -187,7 +627,7 @@ impl Recipe {
             cookware: self.cookware,
             timers: self.timers,
             inline_quantities: self.inline_quantities,
-            data: Scaled::DefaultScaling,
+            data: Scaled::DefaultScaling(original),
         }
     }
 }
@@ -197,28 +637,111 @@ impl ScaledRecipe {
     ///
     /// Returns [`None`] if it was [`default scaled`](Recipe::default_scale).
     pub fn scaled_data(&self) -> Option<&ScaledData> {
-        if let Scaled::Scaled(data) = &self.data {
-            Some(data)
-        } else {
-            None
+        match &self.data {
+            Scaled::DefaultScaling(_) => None,
+            Scaled::Scaled(data) => Some(data),
         }
     }
 
-    /// Shorthand to check if [`Self::scaled_data`] is [`Scaled::DefaultScaling`].
+    /// Shorthand to check if [`Self::scaled_data`] is [`None`].
     pub fn is_default_scaled(&self) -> bool {
-        matches!(self.data, Scaled::DefaultScaling)
+        matches!(self.data, Scaled::DefaultScaling(_))
+    }
+
+    /// Servings of the scaled recipe
+    ///
+    /// The target servings, or the first declared servings scaled by the
+    /// factor if they are a whole number.
+    ///
+    /// ```
+    /// # use cooklang::{scale::ScaleTarget, CooklangParser};
+    /// let parser = CooklangParser::default();
+    /// let recipe = parser
+    ///     .parse(">> servings: 2|4\n>> yield: 1 loaf\n@flour{500*%g}", "Bread")
+    ///     .into_output()
+    ///     .unwrap();
+    /// let scaled = recipe.scale_to(ScaleTarget::by_factor(1.5), parser.converter());
+    /// assert_eq!(scaled.scaled_servings(), Some(3));
+    /// assert_eq!(scaled.scaled_yield().unwrap().to_string(), "1.5 loaf");
+    /// ```
+    pub fn scaled_servings(&self) -> Option<u32> {
+        let servings = self.metadata.servings.as_ref();
+        let Some(target) = self.scaled_data().map(|data| &data.target) else {
+            return servings.and_then(|s| s.first().copied());
+        };
+        target.requested_servings().or_else(|| {
+            let base = *servings?.first()?;
+            let servings = base as f64 * target.factor();
+            (servings.fract() == 0.0).then_some(servings as u32)
+        })
+    }
+
+    /// The [yield](crate::Metadata::yields) of the scaled recipe
+    pub fn scaled_yield(&self) -> Option<Quantity> {
+        let yields = self.metadata.yields.clone();
+        let Some(target) = self.scaled_data().map(|data| &data.target) else {
+            return yields;
+        };
+        yields.and_then(|mut q| {
+            (q.value, _) = q.value.scale(target, ScaleRule::Linear, true).ok()?;
+            Some(q)
+        })
+    }
+
+    /// Get the recipe back with the quantities as written
+    ///
+    /// Any change made to the quantities after scaling, like a
+    /// [conversion](Recipe::convert), is lost. Components added after
+    /// scaling are kept as they are.
+    ///
+    /// ```
+    /// # use cooklang::CooklangParser;
+    /// let parser = CooklangParser::default();
+    /// let recipe = parser
+    ///     .parse(">> servings: 2\n@flour{500*%g}", "Bread")
+    ///     .into_output()
+    ///     .unwrap();
+    /// let scaled = recipe.clone().scale(4, parser.converter());
+    /// assert_eq!(scaled.unscale(), recipe);
+    /// ```
+    pub fn unscale(self) -> Recipe {
+        let mut recipe = Recipe {
+            name: self.name,
+            metadata: self.metadata,
+            sections: self.sections,
+            ingredients: self.ingredients,
+            cookware: self.cookware,
+            timers: self.timers,
+            inline_quantities: self.inline_quantities,
+            data: (),
+        };
+        let (Scaled::DefaultScaling(original) | Scaled::Scaled(ScaledData { original, .. })) =
+            self.data;
+        original.restore(&mut recipe);
+        recipe
+    }
+
+    /// Scale the recipe again, starting from the quantities as written
+    ///
+    /// This is the same as [`Self::unscale`] followed by [`Recipe::scale`].
+    /// To go back to the default values, use [`Recipe::default_scale`] after
+    /// unscaling. For other targets or policies, unscale and scale it as
+    /// usual.
+    pub fn rescale(self, target: u32, converter: &Converter) -> ScaledRecipe {
+        self.unscale().scale(target, converter)
     }
 }
 
 fn scale_many<'a, T: 'a>(
-    target: ScaleTarget,
+    target: &ScaleTarget,
+    policy: ComponentPolicy,
     components: &mut [T],
     extract: impl Fn(&mut T) -> Option<&mut QuantityValue>,
 ) -> Vec<ScaleOutcome> {
     let mut outcomes = Vec::with_capacity(components.len());
     for c in components {
         if let Some(value) = extract(c) {
-            match value.clone().scale(target) {
+            match value.clone().scale(target, policy.rule, policy.scale_fixed) {
                 // ? Unnecesary clone maybe
                 Ok((v, o)) => {
                     *value = v;
@@ -233,6 +756,19 @@ fn scale_many<'a, T: 'a>(
     outcomes
 }
 
+fn fit_many<'a, T: 'a>(
+    converter: &Converter,
+    components: &mut [T],
+    extract: impl Fn(&mut T) -> Option<&mut Quantity>,
+) {
+    for c in components {
+        if let Some(q) = extract(c) {
+            // if it can't be fitted the scaled value is still valid
+            let _ = q.fit(converter);
+        }
+    }
+}
+
 fn default_scale_many<'a, T: 'a>(
     components: &mut [T],
     extract: impl Fn(&mut T) -> Option<&mut QuantityValue>,
@@ -245,14 +781,30 @@ fn default_scale_many<'a, T: 'a>(
 }
 
 impl QuantityValue {
-    fn scale(self, target: ScaleTarget) -> Result<(QuantityValue, ScaleOutcome), ScaleError> {
+    pub(crate) fn scale(
+        self,
+        target: &ScaleTarget,
+        rule: ScaleRule,
+        scale_fixed: bool,
+    ) -> Result<(QuantityValue, ScaleOutcome), ScaleError> {
+        let factor = match rule {
+            ScaleRule::Linear => target.factor(),
+            ScaleRule::Power { exponent } => target.factor().powf(exponent),
+            ScaleRule::Never => return Ok((self.default_scale(), ScaleOutcome::Fixed)),
+        };
         let (value, outcome) = match self {
+            Self::Fixed { value } if scale_fixed && !value.is_text() => {
+                (value.scale(factor)?, ScaleOutcome::Scaled)
+            }
             Self::Fixed { value } => (value, ScaleOutcome::Fixed),
-            Self::Linear { value } => (value.scale(target.factor())?, ScaleOutcome::Scaled),
+            Self::Linear { value } => (value.scale(factor)?, ScaleOutcome::Scaled),
             Self::ByServings { ref values } => {
                 if let Some(index) = target.index {
                     let Some(value) = values.get(index) else {
-                        return Err(ScaleError::NotDefined { target, value: self });
+                        return Err(ScaleError::NotDefined {
+                            target: *target,
+                            value: self,
+                        });
                     };
                     (value.clone(), ScaleOutcome::Scaled)
                 } else {
@@ -266,6 +818,29 @@ impl QuantityValue {
         Ok((Self::Fixed { value }, outcome))
     }
 
+    /// Scales by a factor instead of to a number of servings
+    ///
+    /// [`Self::ByServings`] can't be scaled this way, unless the factor is 1.
+    pub(crate) fn scale_by_factor(
+        self,
+        factor: f64,
+    ) -> Result<(QuantityValue, ScaleOutcome), ScaleError> {
+        if factor == 1.0 {
+            return Ok((self.default_scale(), ScaleOutcome::Scaled));
+        }
+        let (value, outcome) = match self {
+            Self::Fixed { value } => (value, ScaleOutcome::Fixed),
+            Self::Linear { value } => (value.scale(factor)?, ScaleOutcome::Scaled),
+            Self::ByServings { .. } => {
+                return Err(ScaleError::NotScalable {
+                    value: self,
+                    reason: "tried to scale a value by a factor when it has the scaling defined",
+                })
+            }
+        };
+        Ok((Self::Fixed { value }, outcome))
+    }
+
     fn default_scale(self) -> Self {
         match self {
             v @ Self::Fixed { .. } => v,
@@ -284,6 +859,17 @@ impl Value {
     fn scale(&self, factor: f64) -> Result<Value, ScaleError> {
         match self.clone() {
             Value::Number { value: n } => Ok(Value::Number { value: n * factor }),
+            Value::Fraction { value: f } => {
+                // keep it exact if the factor is a simple fraction, like 3/2
+                let exact =
+                    Fraction::from_f64(factor, 100).and_then(|factor| f.checked_mul(&factor));
+                match exact {
+                    Some(value) => Ok(Value::Fraction { value }),
+                    None => Ok(Value::Number {
+                        value: f.to_f64() * factor,
+                    }),
+                }
+            }
             Value::Range { value: r } => Ok(Value::Range {
                 value: r.start() * factor..=r.end() * factor,
             }),
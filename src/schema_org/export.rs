@@ -0,0 +1,227 @@
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    convert::Converter,
+    metadata::{NameAndUrl, RecipeTime},
+    model::{Component, ComponentKind, Item, Section, Step},
+    quantity::format::QuantityFormatter,
+    render::{timer_text, Context},
+    ScaledRecipe,
+};
+
+/// Exports a recipe as JSON-LD
+///
+/// The result is a single `Recipe` node with its `@context`, ready to be
+/// embedded in a `<script type="application/ld+json">` element. Serialize it
+/// with [`serde_json::to_string`].
+///
+/// - `recipeIngredient` are the lines of the
+///   [ingredient list](ScaledRecipe::group_ingredients), without the hidden
+///   ones, like `200 g flour, sifted`.
+/// - `recipeInstructions` are `HowToStep`s, grouped in `HowToSection`s if
+///   any section has a name. Text steps are `HowToTip`s.
+/// - `recipeYield` are the servings and the `yield` metadata, scaled.
+/// - `author`, `isBasedOn`, `keywords`, `description` and the times come
+///   from the [metadata](crate::Metadata).
+///
+/// The quantities are written with the default [`QuantityFormatter`].
+pub fn to_json_ld(recipe: &ScaledRecipe, converter: &Converter) -> Json {
+    to_json_ld_with_formatter(recipe, converter, &QuantityFormatter::default())
+}
+
+/// Same as [`to_json_ld`], writing the quantities with `formatter`
+pub fn to_json_ld_with_formatter(
+    recipe: &ScaledRecipe,
+    converter: &Converter,
+    formatter: &QuantityFormatter,
+) -> Json {
+    let ctx = &Context {
+        recipe,
+        converter,
+        formatter,
+    };
+    let mut node = Map::new();
+    node.insert("@context".into(), "https://schema.org".into());
+    node.insert("@type".into(), "Recipe".into());
+    node.insert("name".into(), recipe.name.clone().into());
+
+    let meta = &recipe.metadata;
+    if let Some(description) = &meta.description {
+        node.insert("description".into(), description.clone().into());
+    }
+    if let Some(author) = meta.author.as_ref().and_then(person) {
+        node.insert("author".into(), author);
+    }
+    if let Some(source) = &meta.source {
+        let source = match (source.url(), source.name()) {
+            (Some(url), _) => url.as_str().into(),
+            (None, Some(name)) => name.into(),
+            (None, None) => Json::Null,
+        };
+        if !source.is_null() {
+            node.insert("isBasedOn".into(), source);
+        }
+    }
+    if !meta.tags.is_empty() {
+        node.insert("keywords".into(), meta.tags.join(", ").into());
+    }
+    let yields = yields(ctx);
+    if !yields.is_empty() {
+        node.insert("recipeYield".into(), yields.into());
+    }
+    if let Some(time) = meta.time {
+        if let RecipeTime::Composed {
+            prep_time,
+            cook_time,
+        } = time
+        {
+            if let Some(prep) = prep_time {
+                node.insert("prepTime".into(), iso8601_duration(prep).into());
+            }
+            if let Some(cook) = cook_time {
+                node.insert("cookTime".into(), iso8601_duration(cook).into());
+            }
+        }
+        node.insert("totalTime".into(), iso8601_duration(time.total()).into());
+    }
+
+    let ingredients = recipe
+        .group_ingredients(converter)
+        .into_iter()
+        .filter(|entry| !entry.ingredient.modifiers().is_hidden())
+        .map(|entry| {
+            let mut line = ctx.total(&entry.quantity);
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &entry.ingredient.display_name();
+            if let Some(note) = &entry.ingredient.note {
+                line += ", ";
+                line += note;
+            }
+            Json::from(line)
+        })
+        .collect::<Vec<_>>();
+    node.insert("recipeIngredient".into(), ingredients.into());
+    node.insert("recipeInstructions".into(), instructions(ctx).into());
+
+    Json::Object(node)
+}
+
+fn person(author: &NameAndUrl) -> Option<Json> {
+    let mut person = Map::new();
+    person.insert("@type".into(), "Person".into());
+    if let Some(name) = author.name() {
+        person.insert("name".into(), name.into());
+    }
+    if let Some(url) = author.url() {
+        person.insert("url".into(), url.as_str().into());
+    }
+    (person.len() > 1).then_some(Json::Object(person))
+}
+
+fn yields(ctx: &Context) -> Vec<Json> {
+    ctx.recipe
+        .scaled_servings()
+        .map(|s| s.to_string())
+        .into_iter()
+        .chain(ctx.recipe.scaled_yield().map(|q| ctx.quantity(&q)))
+        .map(Json::from)
+        .collect()
+}
+
+fn instructions(ctx: &Context) -> Vec<Json> {
+    let sections = ctx
+        .recipe
+        .sections
+        .iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    if sections.iter().all(|s| s.name.is_none()) {
+        return sections
+            .into_iter()
+            .flat_map(|s| &s.steps)
+            .map(|step| how_to_step(ctx, step))
+            .collect();
+    }
+    sections
+        .into_iter()
+        .map(|section| how_to_section(ctx, section))
+        .collect()
+}
+
+fn how_to_section(ctx: &Context, section: &Section) -> Json {
+    let steps = section
+        .steps
+        .iter()
+        .map(|step| how_to_step(ctx, step))
+        .collect::<Vec<_>>();
+    let mut node = json!({ "@type": "HowToSection" });
+    if let Some(name) = &section.name {
+        node["name"] = name.clone().into();
+    }
+    node["itemListElement"] = steps.into();
+    node
+}
+
+fn how_to_step(ctx: &Context, step: &Step) -> Json {
+    let kind = if step.is_text() {
+        "HowToTip"
+    } else {
+        "HowToStep"
+    };
+    json!({ "@type": kind, "text": step_text(ctx, step) })
+}
+
+/// Text of a step, with the name of the components and the inline quantities
+fn step_text(ctx: &Context, step: &Step) -> String {
+    let recipe = ctx.recipe;
+    let mut text = String::new();
+    for item in &step.items {
+        match item {
+            Item::Text { value } => text += value,
+            Item::ItemComponent {
+                value: Component { kind, index },
+            } => match kind {
+                ComponentKind::IngredientKind => {
+                    text += &recipe.ingredients[*index].display_name();
+                }
+                ComponentKind::CookwareKind => {
+                    text += recipe.cookware[*index].display_name();
+                }
+                ComponentKind::TimerKind => text += &timer_text(ctx, *index),
+            },
+            Item::InlineQuantity { value } => {
+                text += &ctx.quantity(&recipe.inline_quantities[*value]);
+            }
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Formats minutes as an ISO 8601 duration, like `PT1H30M`
+fn iso8601_duration(minutes: u32) -> String {
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    let mut s = String::from("PT");
+    if hours > 0 {
+        s += &format!("{hours}H");
+    }
+    if minutes > 0 || hours == 0 {
+        s += &format!("{minutes}M");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0 => "PT0M"; "zero")]
+    #[test_case(45 => "PT45M"; "minutes")]
+    #[test_case(120 => "PT2H"; "hours")]
+    #[test_case(95 => "PT1H35M"; "both")]
+    fn iso8601(minutes: u32) -> String {
+        iso8601_duration(minutes)
+    }
+}
@@ -0,0 +1,379 @@
+use serde_json::Value as Json;
+use thiserror::Error;
+
+use crate::{
+    ast::Modifiers,
+    convert::Converter,
+    ingredient_line,
+    metadata::{is_valid_tag, regex, tagify, Metadata, NameAndUrl},
+    model::{IngredientRelation, Item, Section, Step},
+    Ingredient, Recipe,
+};
+
+/// Errors importing a recipe
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("No schema.org Recipe found")]
+    NoRecipe,
+}
+
+/// Imports a recipe from JSON-LD
+///
+/// The JSON can be the recipe itself, an array or a `@graph` containing it.
+pub fn from_json_ld(json: &str, converter: &Converter) -> Result<Recipe, ImportError> {
+    from_value(&serde_json::from_str(json)?, converter)
+}
+
+/// Imports a recipe from a JSON-LD value
+///
+/// See [`from_json_ld`].
+pub fn from_value(json: &Json, converter: &Converter) -> Result<Recipe, ImportError> {
+    let node = find_recipe(json).ok_or(ImportError::NoRecipe)?;
+    Ok(Importer::new(converter).recipe(node))
+}
+
+/// Imports a recipe from the JSON-LD embedded in an HTML page
+///
+/// The JSON-LD is in `<script type="application/ld+json">` elements.
+pub fn from_html(html: &str, converter: &Converter) -> Result<Recipe, ImportError> {
+    let re =
+        regex!(r#"(?is)<script[^>]*type\s*=\s*["']application/ld\+json["'][^>]*>(.*?)</script>"#);
+    re.captures_iter(html)
+        .filter_map(|c| serde_json::from_str::<Json>(&c[1]).ok())
+        .find_map(|json| from_value(&json, converter).ok())
+        .ok_or(ImportError::NoRecipe)
+}
+
+fn find_recipe(json: &Json) -> Option<&Json> {
+    match json {
+        Json::Array(nodes) => nodes.iter().find_map(find_recipe),
+        Json::Object(node) => {
+            let is_recipe = |t: &Json| {
+                t.as_str().is_some_and(|t| {
+                    t == "Recipe" || t.ends_with("/Recipe") || t.ends_with(":Recipe")
+                })
+            };
+            match node.get("@type") {
+                Some(Json::Array(types)) if types.iter().any(is_recipe) => return Some(json),
+                Some(t) if is_recipe(t) => return Some(json),
+                _ => {}
+            }
+            ["@graph", "mainEntity"]
+                .into_iter()
+                .filter_map(|key| node.get(key))
+                .find_map(find_recipe)
+        }
+        _ => None,
+    }
+}
+
+struct Importer<'c> {
+    converter: &'c Converter,
+    metadata: Metadata,
+}
+
+impl<'c> Importer<'c> {
+    fn new(converter: &'c Converter) -> Self {
+        Self {
+            converter,
+            metadata: Metadata::default(),
+        }
+    }
+
+    fn recipe(mut self, node: &Json) -> Recipe {
+        let name = node.get("name").and_then(text).unwrap_or_default();
+
+        if let Some(description) = node.get("description").and_then(text) {
+            self.meta("description", description);
+        }
+        if let Some(author) = node.get("author").and_then(name_and_url) {
+            self.meta("author", author);
+        }
+        if let Some(url) = node.get("url").and_then(text) {
+            self.meta("source", url);
+        }
+        if let Some(keywords) = node.get("keywords") {
+            self.keywords(keywords);
+        }
+        if let Some(yields) = node.get("recipeYield") {
+            self.yields(yields);
+        }
+        self.times(node);
+
+        let ingredients = node
+            .get("recipeIngredient")
+            .or_else(|| node.get("ingredients"))
+            .map(texts)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|line| self.ingredient(line))
+            .collect();
+
+        let mut sections = vec![Section::default()];
+        if let Some(instructions) = node.get("recipeInstructions") {
+            instructions_into(instructions, &mut sections);
+        }
+        if sections.len() > 1 && sections[0].is_empty() {
+            sections.remove(0);
+        }
+
+        Recipe {
+            name,
+            metadata: self.metadata,
+            sections,
+            ingredients,
+            cookware: Vec::new(),
+            timers: Vec::new(),
+            inline_quantities: Vec::new(),
+            data: (),
+        }
+    }
+
+    /// Inserts a metadata entry only if it's valid
+    fn meta(&mut self, key: &str, value: String) {
+        if self.metadata.insert(key.to_string(), value).is_err() {
+            self.metadata.map.shift_remove(key);
+        }
+    }
+
+    fn keywords(&mut self, keywords: &Json) {
+        let mut tags = Vec::new();
+        for keyword in texts(keywords).iter().flat_map(|k| k.split(',')) {
+            let tag = tagify(keyword);
+            if is_valid_tag(&tag) && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if !tags.is_empty() {
+            self.meta("tags", tags.join(", "));
+        }
+    }
+
+    fn yields(&mut self, yields: &Json) {
+        let re = regex!(
+            r"(?i)^(?:serves\s+|makes\s+)?(\d+)(?:\s+(?:servings?|portions?|people|persons?))?$"
+        );
+        for value in texts(yields) {
+            if self.metadata.servings.is_none() {
+                if let Some(servings) = re.captures(&value) {
+                    self.meta("servings", servings[1].to_string());
+                    continue;
+                }
+            }
+            if self.metadata.yields.is_none() {
+                self.meta("yield", value);
+            }
+        }
+    }
+
+    fn times(&mut self, node: &Json) {
+        let time = |key| {
+            node.get(key)
+                .and_then(text)
+                .and_then(|t| iso8601_minutes(&t))
+                .filter(|&m| m > 0)
+        };
+        match (time("totalTime"), time("prepTime"), time("cookTime")) {
+            (_, Some(prep), Some(cook)) => {
+                self.meta("prep time", format_minutes(prep));
+                self.meta("cook time", format_minutes(cook));
+            }
+            (Some(total), _, _) => {
+                self.meta("time", format_minutes(total));
+            }
+            (None, prep, cook) => {
+                if let Some(prep) = prep {
+                    self.meta("prep time", format_minutes(prep));
+                }
+                if let Some(cook) = cook {
+                    self.meta("cook time", format_minutes(cook));
+                }
+            }
+        }
+    }
+
+    fn ingredient(&self, line: &str) -> Option<Ingredient> {
+        let line = ingredient_line::parse(line, self.converter)?;
+        Some(Ingredient {
+            name: line.name,
+            alias: None,
+            quantity: line.quantity,
+            note: line.note,
+            relation: IngredientRelation::definition(Vec::new()),
+            span: None,
+            modifiers: Modifiers::empty(),
+            defined_in_step: false,
+        })
+    }
+}
+
+fn instructions_into(instructions: &Json, sections: &mut Vec<Section>) {
+    let push_step = |sections: &mut Vec<Section>, text: String| {
+        let section = sections.last_mut().unwrap();
+        let number = section.steps.len() as u32 + 1;
+        section.steps.push(Step {
+            items: vec![Item::Text { value: text }],
+            number: Some(number),
+            span: None,
+        });
+    };
+
+    match instructions {
+        Json::String(s) => s
+            .lines()
+            .filter_map(clean)
+            .for_each(|step| push_step(sections, step)),
+        Json::Array(items) => items.iter().for_each(|i| instructions_into(i, sections)),
+        Json::Object(node) => {
+            let types = texts(node.get("@type").unwrap_or(&Json::Null));
+            if types.iter().any(|t| t == "HowToSection") {
+                let name = node.get("name").and_then(text);
+                sections.push(Section::new(name, None));
+            }
+            if let Some(items) = node.get("itemListElement") {
+                instructions_into(items, sections);
+            } else if let Some(step) = node.get("text").or_else(|| node.get("name")) {
+                instructions_into(step, sections);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Text of a value, or of the first value of an array
+fn text(json: &Json) -> Option<String> {
+    match json {
+        Json::String(s) => clean(s),
+        Json::Number(n) => Some(n.to_string()),
+        Json::Array(a) => a.iter().find_map(text),
+        Json::Object(o) => o.get("@value").and_then(text),
+        _ => None,
+    }
+}
+
+/// Text of all the values
+fn texts(json: &Json) -> Vec<String> {
+    match json {
+        Json::Array(a) => a.iter().filter_map(text).collect(),
+        _ => text(json).into_iter().collect(),
+    }
+}
+
+fn name_and_url(json: &Json) -> Option<String> {
+    let Json::Object(node) = json else {
+        return match json {
+            Json::Array(a) => a.iter().find_map(name_and_url),
+            _ => text(json),
+        };
+    };
+    let name = node.get("name").and_then(text);
+    let url = node.get("url").and_then(text);
+    match (name, url) {
+        (Some(name), Some(url)) => {
+            // only names that the metadata can tell apart from the url
+            let both = format!("{name} <{url}>");
+            if NameAndUrl::parse(&both).name() == Some(name.as_str()) {
+                Some(both)
+            } else {
+                Some(name)
+            }
+        }
+        (name, url) => name.or(url),
+    }
+}
+
+/// Removes HTML tags and entities and collapses whitespace
+fn clean(s: &str) -> Option<String> {
+    let s = regex!(r"<[^>]*>").replace_all(s, " ");
+    let s = regex!(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").replace_all(
+        &s,
+        |c: &regex::Captures| {
+            let entity = &c[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| c[0].to_string(), String::from)
+        },
+    );
+    let s = collapse_whitespace(&s);
+    (!s.is_empty()).then_some(s)
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parses an ISO 8601 duration, like `PT1H30M`, in minutes
+fn iso8601_minutes(s: &str) -> Option<u32> {
+    let re = regex!(
+        r"(?i)^P(?:(\d+(?:\.\d+)?)W)?(?:(\d+(?:\.\d+)?)D)?(?:T(?:(\d+(?:\.\d+)?)H)?(?:(\d+(?:\.\d+)?)M)?(?:(\d+(?:\.\d+)?)S)?)?$"
+    );
+    let captures = re.captures(s.trim())?;
+    const MINUTES: [f64; 5] = [7.0 * 24.0 * 60.0, 24.0 * 60.0, 60.0, 1.0, 1.0 / 60.0];
+    let mut any = false;
+    let mut minutes = 0.0;
+    for (group, factor) in MINUTES.into_iter().enumerate() {
+        if let Some(m) = captures.get(group + 1) {
+            any = true;
+            minutes += m.as_str().parse::<f64>().ok()? * factor;
+        }
+    }
+    any.then(|| minutes.round() as u32)
+}
+
+/// Formats minutes so the metadata can parse them
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}min"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}min"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("PT1H30M" => Some(90); "hours and minutes")]
+    #[test_case("PT20M" => Some(20); "minutes")]
+    #[test_case("P0DT2H" => Some(120); "days")]
+    #[test_case("PT0.5H" => Some(30); "decimal")]
+    #[test_case("PT90S" => Some(2); "seconds")]
+    #[test_case("P1D" => Some(1440); "one day")]
+    #[test_case("PT" => None; "empty time")]
+    #[test_case("1 hour" => None; "not iso")]
+    fn iso8601(s: &str) -> Option<u32> {
+        iso8601_minutes(s)
+    }
+
+    #[test_case(45 => "45min"; "minutes")]
+    #[test_case(120 => "2h"; "hours")]
+    #[test_case(95 => "1h 35min"; "both")]
+    fn format(minutes: u32) -> String {
+        format_minutes(minutes)
+    }
+
+    #[test_case("Salt &amp; pepper" => Some("Salt & pepper".to_string()); "named entity")]
+    #[test_case("<p>Mix  it&#33;</p>" => Some("Mix it!".to_string()); "tags and numeric entity")]
+    #[test_case("&#x2F;&unknown;" => Some("/&unknown;".to_string()); "hex and unknown")]
+    #[test_case("  <br> " => None; "only markup")]
+    fn clean_text(s: &str) -> Option<String> {
+        clean(s)
+    }
+}
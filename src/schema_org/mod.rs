@@ -0,0 +1,68 @@
+//! Import and export recipes as [schema.org/Recipe](https://schema.org/Recipe) JSON-LD
+//!
+//! Most recipe websites embed their recipes in this format. The imported
+//! [`Recipe`](crate::Recipe) can be written as cooklang source with
+//! [`Recipe::to_cooklang`](crate::Recipe::to_cooklang). The ingredients are
+//! parsed with [`ingredient_line`](crate::ingredient_line).
+//!
+//! A [`ScaledRecipe`](crate::ScaledRecipe) can be exported with
+//! [`to_json_ld`] to embed it in a web page, or with
+//! [`to_json_ld_with_formatter`] to choose how the quantities are written.
+//!
+//! ```
+//! # use cooklang::{schema_org, Converter, CooklangParser, Extensions};
+//! let json = r#"{
+//!     "@context": "https://schema.org",
+//!     "@type": "Recipe",
+//!     "name": "Flatbread",
+//!     "recipeYield": "4 servings",
+//!     "totalTime": "PT1H30M",
+//!     "recipeIngredient": ["2 1/2 cups flour, sifted", "1 tsp salt", "water"],
+//!     "recipeInstructions": [
+//!         { "@type": "HowToStep", "text": "Mix everything." },
+//!         { "@type": "HowToStep", "text": "Cook in a hot pan." }
+//!     ]
+//! }"#;
+//! let recipe = schema_org::from_json_ld(json, &Converter::default())?;
+//! assert_eq!(recipe.ingredients[0].name, "flour");
+//! assert_eq!(recipe.ingredients[0].note.as_deref(), Some("sifted"));
+//!
+//! let src = recipe.to_cooklang(Extensions::all());
+//! assert_eq!(
+//!     src,
+//!     indoc::indoc! {"
+//!         >> servings: 4
+//!         >> time: 1h 30min
+//!
+//!         >> [mode]: components
+//!         @flour{2 1/2*%cups}(sifted)
+//!         @salt{1*%tsp}
+//!         @water{}
+//!         >> [mode]: all
+//!
+//!         Mix everything.
+//!
+//!         Cook in a hot pan.
+//!     "}
+//! );
+//! # Ok::<(), schema_org::ImportError>(())
+//! ```
+//!
+//! ```
+//! # use cooklang::{schema_org, CooklangParser};
+//! let parser = CooklangParser::default();
+//! let src = ">> servings: 2\nBoil @water{1*%l} in a #pot for ~{10%min}.";
+//! let recipe = parser.parse(src, "Hot water").into_output().unwrap();
+//! let recipe = recipe.scale(4, parser.converter());
+//!
+//! let json = schema_org::to_json_ld(&recipe, parser.converter());
+//! assert_eq!(json["recipeYield"][0], "4");
+//! assert_eq!(json["recipeIngredient"][0], "2 l water");
+//! assert_eq!(json["recipeInstructions"][0]["text"], "Boil water in a pot for 10 min.");
+//! ```
+
+mod export;
+mod import;
+
+pub use export::{to_json_ld, to_json_ld_with_formatter};
+pub use import::{from_html, from_json_ld, from_value, ImportError};
@@ -7,6 +7,7 @@ use std::ops::{Deref, Range};
 /// The offsets are zero-indexed charactere offsets from the beginning of the source
 /// code.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Span {
     start: usize,
     end: usize,
@@ -24,6 +25,12 @@ impl Span {
         }
     }
 
+    /// Moves the span `delta` bytes
+    pub(crate) fn shift(&mut self, delta: isize) {
+        self.start = self.start.wrapping_add_signed(delta);
+        self.end = self.end.wrapping_add_signed(delta);
+    }
+
     /// Start offset of the span
     pub fn start(&self) -> usize {
         self.start
@@ -0,0 +1,458 @@
+//! Write a [`Recipe`] back to cooklang source
+//!
+//! The output is meant to be parsed again with the same [`Extensions`] given
+//! to the writer. When that happens, the parsed recipe will be equal to the one
+//! written. The locations in the source will be different, but they are not
+//! compared. Some things cannot be represented without the right extensions
+//! enabled, so they are just omitted:
+//!
+//! - Modifiers, notes and aliases need their `COMPONENT_*` extension.
+//! - Components not used in any step (defined with the `[mode]: components`
+//!   special metadata key) need [`MODES`](Extensions::MODES).
+//!
+//! Adjacent text items in a step are written together, so if they came from a
+//! partial component, like `user@mail`, they will be parsed as a single item.
+//! The text will be the same.
+//!
+//! ```
+//! # use cooklang::{CooklangParser, Extensions};
+//! let parser = CooklangParser::default();
+//! let src = ">> servings: 2|4\nAdd @flour{200|400%g} to a #bowl.";
+//! let recipe = parser.parse(src, "name").into_output().unwrap();
+//!
+//! let written = recipe.to_cooklang(parser.extensions());
+//! let again = parser.parse(&written, "name").into_output().unwrap();
+//! assert_eq!(recipe, again);
+//! ```
+
+use std::fmt::Write;
+
+use crate::{
+    ast::Modifiers,
+    model::{
+        ComponentKind, Cookware, Ingredient, IngredientReferenceTarget, Item, Section, Step, Timer,
+    },
+    quantity::{Quantity, QuantityValue, Value},
+    Extensions, Recipe,
+};
+
+/// Write a recipe as cooklang source
+///
+/// See the [module level documentation](self) for the details.
+pub fn write_recipe<D>(
+    recipe: &Recipe<D>,
+    extensions: Extensions,
+    mut w: impl std::io::Write,
+) -> std::io::Result<()> {
+    w.write_all(recipe.to_cooklang(extensions).as_bytes())
+}
+
+impl<D> Recipe<D> {
+    /// Get the cooklang source of the recipe
+    ///
+    /// See the [writer module](crate::writer) for the details.
+    pub fn to_cooklang(&self, extensions: Extensions) -> String {
+        let mut writer = Writer::new(self, extensions);
+        writer.recipe();
+        writer.out
+    }
+}
+
+struct Writer<'r, D> {
+    recipe: &'r Recipe<D>,
+    extensions: Extensions,
+    out: String,
+    /// Components not used in any step, by kind and in order
+    pending: [std::collections::VecDeque<usize>; 3],
+}
+
+const KINDS: [ComponentKind; 3] = [
+    ComponentKind::IngredientKind,
+    ComponentKind::CookwareKind,
+    ComponentKind::TimerKind,
+];
+
+fn kind_index(kind: ComponentKind) -> usize {
+    match kind {
+        ComponentKind::IngredientKind => 0,
+        ComponentKind::CookwareKind => 1,
+        ComponentKind::TimerKind => 2,
+    }
+}
+
+impl<'r, D> Writer<'r, D> {
+    fn new(recipe: &'r Recipe<D>, extensions: Extensions) -> Self {
+        let mut used = [
+            vec![false; recipe.ingredients.len()],
+            vec![false; recipe.cookware.len()],
+            vec![false; recipe.timers.len()],
+        ];
+        for item in recipe
+            .sections
+            .iter()
+            .flat_map(|s| &s.steps)
+            .flat_map(|s| &s.items)
+        {
+            if let Item::ItemComponent { value } = item {
+                used[kind_index(value.kind)][value.index] = true;
+            }
+        }
+        let pending = used.map(|used| {
+            used.into_iter()
+                .enumerate()
+                .filter_map(|(index, used)| (!used).then_some(index))
+                .collect()
+        });
+
+        Self {
+            recipe,
+            extensions,
+            out: String::new(),
+            pending,
+        }
+    }
+
+    fn ext(&self, ext: Extensions) -> bool {
+        self.extensions.contains(ext)
+    }
+
+    fn recipe(&mut self) {
+        let mut first_block = true;
+        let mut block = |w: &mut Self| {
+            if !first_block {
+                w.out.push('\n');
+            }
+            first_block = false;
+        };
+
+        if !self.recipe.metadata.map.is_empty() {
+            block(self);
+            for (key, value) in &self.recipe.metadata.map {
+                self.out.push_str(">> ");
+                escape(&mut self.out, key, &[':']);
+                self.out.push_str(": ");
+                escape(&mut self.out, value, &[]);
+                self.out.push('\n');
+            }
+        }
+
+        for (index, section) in self.recipe.sections.iter().enumerate() {
+            if index > 0 || section.name.is_some() {
+                block(self);
+                self.section_header(section);
+            }
+            for step in &section.steps {
+                let pending = self.take_pending(Some(step));
+                if !pending.is_empty() {
+                    block(self);
+                    self.components_block(pending);
+                }
+                block(self);
+                self.step(step);
+            }
+        }
+
+        let pending = self.take_pending(None);
+        if !pending.is_empty() {
+            block(self);
+            self.components_block(pending);
+        }
+    }
+
+    fn section_header(&mut self, section: &Section) {
+        self.out.push('=');
+        if let Some(name) = &section.name {
+            self.out.push(' ');
+            escape(&mut self.out, name, &['=']);
+        }
+        self.out.push('\n');
+    }
+
+    /// Takes the components that have to be defined before `step` (or all
+    /// of them if `None`) that are not used in any step.
+    ///
+    /// Returns nothing if they can't be written.
+    fn take_pending(&mut self, step: Option<&Step>) -> Vec<(ComponentKind, usize)> {
+        let mut limit = [usize::MAX; 3];
+        if let Some(step) = step {
+            for item in &step.items {
+                if let Item::ItemComponent { value } = item {
+                    let l = &mut limit[kind_index(value.kind)];
+                    *l = (*l).min(value.index);
+                }
+            }
+        }
+
+        let mut to_write = Vec::new();
+        for (k, kind) in KINDS.into_iter().enumerate() {
+            while let Some(&index) = self.pending[k].front() {
+                if index > limit[k] {
+                    break;
+                }
+                self.pending[k].pop_front();
+                to_write.push((kind, index));
+            }
+        }
+
+        if !self.ext(Extensions::MODES) {
+            to_write.clear();
+        }
+        to_write
+    }
+
+    fn components_block(&mut self, components: Vec<(ComponentKind, usize)>) {
+        self.out.push_str(">> [mode]: components\n");
+        for (kind, index) in components {
+            self.component(kind, index);
+            self.out.push('\n');
+        }
+        self.out.push_str(">> [mode]: all\n");
+    }
+
+    fn step(&mut self, step: &Step) {
+        let is_text = step.is_text();
+        if is_text {
+            self.out.push('>');
+        }
+
+        let mut line_start = !is_text;
+        for (index, item) in step.items.iter().enumerate() {
+            let prev = index.checked_sub(1).and_then(|i| step.items.get(i));
+            let next = step.items.get(index + 1);
+            match item {
+                Item::Text { value } => {
+                    if self.is_line_join(value, prev, next) {
+                        self.out.push('\n');
+                        line_start = true;
+                        continue;
+                    }
+                    let mut value = value.as_str();
+                    if let Some(c) = value.chars().next() {
+                        // At the start of a line these would change the line kind.
+                        // After a text step marker '>', another one would be
+                        // a metadata line. Right after a component, a '(' would
+                        // start a note.
+                        let after_component = matches!(prev, Some(Item::ItemComponent { .. }));
+                        if ((line_start || is_text) && matches!(c, '>' | '='))
+                            || (after_component && c == '(')
+                        {
+                            self.out.push('\\');
+                            self.out.push(c);
+                            value = &value[c.len_utf8()..];
+                        }
+                    }
+                    escape(&mut self.out, value, &['@', '#', '~']);
+                }
+                Item::ItemComponent { value } => self.component(value.kind, value.index),
+                Item::InlineQuantity { value } => {
+                    let q = &self.recipe.inline_quantities[*value];
+                    self.inline_quantity(q);
+                }
+            }
+            line_start = false;
+        }
+        self.out.push('\n');
+    }
+
+    /// With [`Extensions::MULTILINE_STEPS`], when lines are joined in a
+    /// single step, a single space text item is added in between. So if
+    /// there's one next to other text, it has to be written as a line break
+    /// or the items will be joined together.
+    fn is_line_join(&self, text: &str, prev: Option<&Item>, next: Option<&Item>) -> bool {
+        if !self.ext(Extensions::MULTILINE_STEPS) || text != " " {
+            return false;
+        }
+        let (Some(prev), Some(next)) = (prev, next) else {
+            return false;
+        };
+        let is_text = |item: &Item| matches!(item, Item::Text { .. });
+        let ends_trimmed =
+            !matches!(prev, Item::Text { value } if value.ends_with(char::is_whitespace));
+        let starts_trimmed =
+            !matches!(next, Item::Text { value } if value.starts_with(char::is_whitespace));
+        (is_text(prev) || is_text(next)) && ends_trimmed && starts_trimmed
+    }
+
+    fn component(&mut self, kind: ComponentKind, index: usize) {
+        match kind {
+            ComponentKind::IngredientKind => self.ingredient(&self.recipe.ingredients[index]),
+            ComponentKind::CookwareKind => self.cookware(&self.recipe.cookware[index]),
+            ComponentKind::TimerKind => self.timer(&self.recipe.timers[index]),
+        }
+    }
+
+    fn ingredient(&mut self, ingredient: &Ingredient) {
+        self.out.push('@');
+        if self.ext(Extensions::COMPONENT_MODIFIERS) {
+            let intermediate = ingredient
+                .relation
+                .references_to()
+                .filter(|_| self.ext(Extensions::INTERMEDIATE_INGREDIENTS))
+                .and_then(|(index, target)| match target {
+                    IngredientReferenceTarget::IngredientTarget => None,
+                    IngredientReferenceTarget::StepTarget => Some(format!("({index})")),
+                    IngredientReferenceTarget::SectionTarget => Some(format!("(={index})")),
+                });
+            self.modifiers(ingredient.modifiers(), intermediate.as_deref());
+        }
+        self.name(&ingredient.name, ingredient.alias.as_deref());
+        self.out.push('{');
+        if let Some(q) = &ingredient.quantity {
+            self.quantity(q);
+        }
+        self.out.push('}');
+        self.note(ingredient.note.as_deref());
+    }
+
+    fn cookware(&mut self, cookware: &Cookware) {
+        self.out.push('#');
+        if self.ext(Extensions::COMPONENT_MODIFIERS) {
+            self.modifiers(cookware.modifiers(), None);
+        }
+        self.name(&cookware.name, cookware.alias.as_deref());
+        self.out.push('{');
+        if let Some(value) = &cookware.quantity {
+            self.quantity_value(value);
+        }
+        self.out.push('}');
+        self.note(cookware.note.as_deref());
+    }
+
+    fn timer(&mut self, timer: &Timer) {
+        self.out.push('~');
+        if let Some(name) = &timer.name {
+            self.name(name, None);
+        }
+        self.out.push('{');
+        if let Some(q) = &timer.quantity {
+            self.quantity(q);
+        }
+        self.out.push('}');
+    }
+
+    fn modifiers(&mut self, modifiers: Modifiers, intermediate: Option<&str>) {
+        if modifiers.contains(Modifiers::RECIPE) {
+            self.out.push('@');
+        }
+        if modifiers.contains(Modifiers::REF) {
+            self.out.push('&');
+            if let Some(intermediate) = intermediate {
+                self.out.push_str(intermediate);
+            }
+        }
+        if modifiers.contains(Modifiers::HIDDEN) {
+            self.out.push('-');
+        }
+        if modifiers.contains(Modifiers::OPT) {
+            self.out.push('?');
+        }
+        if modifiers.contains(Modifiers::NEW) {
+            self.out.push('+');
+        }
+    }
+
+    fn name(&mut self, name: &str, alias: Option<&str>) {
+        const NAME_SPECIAL: &[char] = &['{', '}', '|', '@', '#', '~'];
+        let mut name = name;
+        // would be parsed as modifiers
+        if let Some(c) = name
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '@' | '&' | '?' | '+' | '-' | '('))
+        {
+            self.out.push('\\');
+            self.out.push(c);
+            name = &name[c.len_utf8()..];
+        }
+        escape(&mut self.out, name, NAME_SPECIAL);
+        if let Some(alias) = alias.filter(|_| self.ext(Extensions::COMPONENT_ALIAS)) {
+            self.out.push('|');
+            escape(&mut self.out, alias, NAME_SPECIAL);
+        }
+    }
+
+    fn note(&mut self, note: Option<&str>) {
+        if let Some(note) = note.filter(|_| self.ext(Extensions::COMPONENT_NOTE)) {
+            self.out.push('(');
+            escape(&mut self.out, note, &[')']);
+            self.out.push(')');
+        }
+    }
+
+    fn quantity(&mut self, quantity: &Quantity) {
+        self.quantity_value(&quantity.value);
+        if let Some(unit) = quantity.unit_text() {
+            self.out.push('%');
+            escape(&mut self.out, unit, &['{', '}']);
+        }
+    }
+
+    fn quantity_value(&mut self, value: &QuantityValue) {
+        match value {
+            QuantityValue::Fixed { value } => self.value(value),
+            QuantityValue::Linear { value } => {
+                self.value(value);
+                self.out.push('*');
+            }
+            QuantityValue::ByServings { values } => {
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push('|');
+                    }
+                    self.value(value);
+                }
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Number { value } => write!(self.out, "{value}").unwrap(),
+            Value::Fraction { value } => write!(self.out, "{value}").unwrap(),
+            Value::Range { value } => {
+                write!(self.out, "{}-{}", value.start(), value.end()).unwrap()
+            }
+            Value::Text { value } => {
+                let mut value = value.as_str();
+                // avoid parsing it as a number
+                if let Some(c) = value
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.out.push('\\');
+                    self.out.push(c);
+                    value = &value[c.len_utf8()..];
+                }
+                escape(&mut self.out, value, &['|', '*', '%', '{', '}']);
+            }
+        }
+    }
+
+    fn inline_quantity(&mut self, quantity: &Quantity) {
+        // written so the temperature regex can find it again
+        self.quantity_value(&quantity.value);
+        if let Some(unit) = quantity.unit_text() {
+            write!(self.out, " {unit}").unwrap();
+        }
+    }
+}
+
+/// Escapes `text` into `out`
+///
+/// Backslashes and comment starts are always escaped. Apart from that, every
+/// char in `special`.
+fn escape(out: &mut String, text: &str, special: &[char]) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        let needs_escape = c == '\\'
+            || special.contains(&c)
+            || (c == '-' && next == Some('-'))
+            || (c == '[' && next == Some('-'));
+        if needs_escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
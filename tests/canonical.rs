@@ -185,6 +185,7 @@ impl TestValue {
     fn from_cooklang_value(value: QuantityValue) -> Self {
         match value {
             QuantityValue::Fixed { value } => match value {
+                Value::Fraction { value } => TestValue::Number(value.to_f64()),
                 Value::Number { value } => TestValue::Number(value),
                 Value::Range { .. } => panic!("unexpected range value"),
                 Value::Text { value } => TestValue::Text(value),
@@ -0,0 +1,35 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)] // each test binary uses only some of them
+
+use cooklang::{Quantity, QuantityValue, Value};
+
+/// Fixed quantity with an optional unit
+pub fn quantity(value: f64, unit: Option<&str>) -> Quantity {
+    Quantity::new(
+        QuantityValue::Fixed {
+            value: value.into(),
+        },
+        unit.map(str::to_string),
+    )
+}
+
+/// Fixed quantity with a unit
+pub fn q(value: f64, unit: &str) -> Quantity {
+    quantity(value, Some(unit))
+}
+
+/// Value of a scaled quantity
+pub fn value(q: &Quantity) -> &Value {
+    match &q.value {
+        QuantityValue::Fixed { value } => value,
+        _ => panic!("not scaled"),
+    }
+}
+
+/// Number of a scaled quantity
+pub fn number(q: &Quantity) -> f64 {
+    match value(q) {
+        Value::Number { value } => *value,
+        _ => panic!("not a scaled number"),
+    }
+}
@@ -87,3 +87,190 @@ fn step_number(src: &str) -> Vec<Vec<Option<u32>>> {
         .collect();
     numbers
 }
+
+#[test_case("@salt{1*%tsp}" => true; "number")]
+#[test_case("@salt{1-2*%tsp}" => true; "range")]
+#[test_case("@salt{a pinch*}" => false; "text")]
+fn auto_scale_marker(src: &str) -> bool {
+    let r = cooklang::parse(src, "test");
+    let ok = !r.has_errors();
+    if ok {
+        let q = r.output().unwrap().ingredients[0]
+            .quantity
+            .as_ref()
+            .unwrap();
+        assert!(matches!(q.value, cooklang::QuantityValue::Linear { .. }));
+    }
+    ok
+}
+
+mod spans {
+    use cooklang::{convert::System, Converter, CooklangParser, Extensions};
+    use indoc::indoc;
+
+    const RECIPE: &str = indoc! {r#"
+        >> servings: 2
+        == Dough ==
+        Mix @flour{200%g} and  @milk{300%ml} in a #bowl.
+
+        Rest ~rest{10%min} at 20 ºC.
+    "#};
+
+    fn text(input: &str, span: Option<cooklang::Span>) -> &str {
+        &input[span.expect("has span").range()]
+    }
+
+    #[test]
+    fn entities() {
+        let recipe = CooklangParser::new(Extensions::all(), Default::default())
+            .parse(RECIPE, "test")
+            .into_output()
+            .unwrap();
+
+        let spans = recipe.ingredients.iter().map(|i| text(RECIPE, i.span));
+        assert_eq!(
+            spans.collect::<Vec<_>>(),
+            ["@flour{200%g}", "@milk{300%ml}"]
+        );
+        assert_eq!(text(RECIPE, recipe.cookware[0].span), "#bowl");
+        assert_eq!(text(RECIPE, recipe.timers[0].span), "~rest{10%min}");
+
+        let section = &recipe.sections[0];
+        assert_eq!(text(RECIPE, section.span), "Dough");
+        assert_eq!(
+            text(RECIPE, section.steps[0].span),
+            "Mix @flour{200%g} and  @milk{300%ml} in a #bowl."
+        );
+        assert_eq!(
+            text(RECIPE, section.steps[1].span),
+            "Rest ~rest{10%min} at 20 ºC."
+        );
+
+        let servings = recipe.metadata.span("servings").unwrap();
+        assert_eq!(&RECIPE[servings.key.range()], "servings");
+        assert_eq!(&RECIPE[servings.value.range()], "2");
+
+        assert_eq!(recipe.inline_quantities.len(), 1);
+        let inline = recipe.inline_quantities[0].span().unwrap();
+        assert_eq!(&RECIPE[inline.range()], "20 ºC");
+    }
+
+    #[test]
+    fn unnamed_section() {
+        let recipe = cooklang::parse("A step.\n", "test").into_output().unwrap();
+        assert_eq!(recipe.sections[0].span, None);
+        assert_eq!(
+            text("A step.\n", recipe.sections[0].steps[0].span),
+            "A step."
+        );
+    }
+
+    #[test]
+    fn not_compared() {
+        let parser = CooklangParser::new(Extensions::all(), Converter::bundled());
+        let a = parser.parse(RECIPE, "test").into_output().unwrap();
+        let b = parser
+            .parse(&format!("\n\n{RECIPE}"), "test")
+            .into_output()
+            .unwrap();
+        assert_ne!(a.ingredients[0].span, b.ingredients[0].span);
+        assert_ne!(a.metadata.span("servings"), b.metadata.span("servings"));
+        assert_ne!(a.inline_quantities[0].span(), b.inline_quantities[0].span());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn kept_when_converted() {
+        let parser = CooklangParser::new(Extensions::all(), Converter::bundled());
+        let mut recipe = parser.parse(RECIPE, "test").into_output().unwrap();
+        let span = recipe.inline_quantities[0].span();
+        let errors = recipe.convert(System::Imperial, parser.converter());
+        assert!(errors.is_empty());
+        assert_eq!(recipe.inline_quantities[0].unit_text(), Some("°F"));
+        assert_eq!(recipe.inline_quantities[0].span(), span);
+    }
+}
+
+mod schema {
+    use std::time::Duration;
+
+    use cooklang::{
+        error::{CooklangError, CooklangWarning, RichError},
+        metadata::schema::{Date, KeySchema, MetadataSchema, MetadataType},
+        CooklangParser,
+    };
+    use indoc::indoc;
+
+    fn parser() -> CooklangParser {
+        let schema = MetadataSchema::new()
+            .key("rating", KeySchema::new(MetadataType::Integer))
+            .key("rest", KeySchema::new(MetadataType::Duration))
+            .key("created", KeySchema::new(MetadataType::Date).required())
+            .key(
+                "diets",
+                KeySchema::new(MetadataType::List(Box::new(MetadataType::Text))),
+            );
+        CooklangParser::default().with_metadata_schema(schema)
+    }
+
+    #[test]
+    fn typed_values() {
+        let input = indoc! {"
+            >> created: 2024-01-31
+            >> rest: 1h
+            >> diets: vegan, gluten free
+            >> rating: 5
+            >> other: value
+        "};
+        let (recipe, warnings) = parser().parse(input, "test").into_result().unwrap();
+        assert!(warnings.is_empty());
+        let meta = &recipe.metadata;
+        assert_eq!(meta.get::<Date>("created"), Date::new(2024, 1, 31));
+        assert_eq!(
+            meta.get::<Duration>("rest"),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            meta.get::<Vec<String>>("diets"),
+            Some(vec!["vegan".to_string(), "gluten free".to_string()])
+        );
+        assert_eq!(meta.get::<u32>("rating"), Some(5));
+        assert_eq!(meta.get::<String>("rating"), None);
+        assert_eq!(meta.get::<String>("other"), None);
+        assert_eq!(meta.map["other"], "value");
+    }
+
+    #[test]
+    fn diagnostics() {
+        let input = ">> created: 2024-01-31\n>> rating: great\n";
+        let (recipe, warnings) = parser().parse(input, "test").into_result().unwrap();
+        assert_eq!(recipe.metadata.get::<i64>("rating"), None);
+        assert_eq!(recipe.metadata.map["rating"], "great");
+        assert_eq!(warnings.len(), 1);
+        let warning = warnings.iter().next().unwrap();
+        assert!(matches!(warning, CooklangWarning::Analysis(_)));
+        let spans = warning
+            .labels()
+            .into_iter()
+            .map(|(s, _)| s.range())
+            .collect::<Vec<_>>();
+        assert_eq!(spans, [25..32, 33..39]);
+
+        // required
+        let errors = parser()
+            .parse(">> created: yesterday\n", "test")
+            .into_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("created"));
+        let errors = parser().parse("Just a @step.", "test").into_errors();
+        assert!(matches!(errors.first(), Some(CooklangError::Analysis(_))));
+
+        // also when only parsing metadata
+        assert!(!parser().parse_metadata("Just a @step.").is_valid());
+        let meta = parser()
+            .parse_metadata(">> created: 2024-01-31")
+            .into_output()
+            .unwrap();
+        assert!(meta.get::<Date>("created").is_some());
+    }
+}
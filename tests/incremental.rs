@@ -0,0 +1,183 @@
+use cooklang::{
+    incremental::{IncrementalParse, TextEdit},
+    CooklangParser, Extensions,
+};
+use indoc::indoc;
+use test_case::test_case;
+
+const RECIPE: &str = indoc! {r#"
+    >> servings: 2|4
+    >> [mode]: all
+
+    = Dough
+    Mix @flour{500%g} with @water{300%ml} in a #bowl. -- comment
+    Knead ~{10%min}.
+
+    > A text step
+    that continues here.
+
+    == Topping [- block
+    comment -] ==
+    Add @&flour{10%g} and @cheese{2*%cups}(grated).
+    Bake at 180 ºC for ~bake{25%min}.
+"#};
+
+fn assert_same(parser: &CooklangParser, doc: &IncrementalParse) {
+    let full = parser.parse(doc.input(), doc.recipe_name());
+    let incremental = doc.result();
+    assert_eq!(
+        format!("{:?}", full.output()),
+        format!("{:?}", incremental.output()),
+        "different recipe for:\n{}",
+        doc.input()
+    );
+    assert_eq!(
+        format!("{:?}", full.warnings()),
+        format!("{:?}", incremental.warnings()),
+        "different warnings for:\n{}",
+        doc.input()
+    );
+    assert_eq!(
+        format!("{:?}", full.errors()),
+        format!("{:?}", incremental.errors()),
+        "different errors for:\n{}",
+        doc.input()
+    );
+}
+
+fn find(s: &str, pat: &str) -> usize {
+    s.find(pat).unwrap()
+}
+
+fn boundary(s: &str, mut i: usize) -> usize {
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn insert(at: usize, text: &str) -> TextEdit {
+    TextEdit::new(at..at, text)
+}
+
+#[test_case(|s| TextEdit::new(find(s, "500") .. find(s, "500") + 3, "250"); "inside a line")]
+#[test_case(|_| insert(0, "Start here.\n"); "at start")]
+#[test_case(|s| TextEdit::new(s.len()..s.len(), "Last @step{}"); "at end")]
+#[test_case(|s| insert(find(s, "Knead"), "\n"); "split step")]
+#[test_case(|s| TextEdit::new(find(s, "\nKnead") .. find(s, "\nKnead") + 1, " "); "join lines")]
+#[test_case(|s| TextEdit::new(find(s, "\n\n> A") .. find(s, "\n\n> A") + 1, ""); "remove blank line")]
+#[test_case(|s| TextEdit::new(find(s, "\nthat") .. find(s, "\nthat") + 1, "\n\n"); "end text step")]
+#[test_case(|s| insert(find(s, "Mix"), "[- "); "open block comment")]
+#[test_case(|s| TextEdit::new(find(s, "comment -]") + 8 .. find(s, "comment -]") + 10, ""); "close block comment")]
+#[test_case(|s| insert(find(s, "@flour{500"), "\\"); "escape")]
+#[test_case(|s| TextEdit::new(find(s, ">> [mode]") .. find(s, "= Dough"), ""); "remove lines")]
+#[test_case(|s| TextEdit::new(find(s, "@cheese{") + 8 .. find(s, "@cheese{") + 9, "2|3"); "new error")]
+#[test_case(|s| TextEdit::new(find(s, "servings: ") + 10 .. find(s, "servings: ") + 13, ""); "new warning")]
+#[test_case(|s| TextEdit::new(0..s.len(), ""); "remove everything")]
+fn single_edit(edit: fn(&str) -> TextEdit) {
+    for ext in [Extensions::all(), Extensions::COMPAT, Extensions::empty()] {
+        let parser = CooklangParser::new(ext, Default::default());
+        let mut doc = parser.parse_incremental(RECIPE, "test");
+        assert_same(&parser, &doc);
+        let edit = edit(RECIPE);
+        parser.reparse(&mut doc, [edit]);
+        assert_same(&parser, &doc);
+    }
+}
+
+#[test]
+fn typing() {
+    let parser = CooklangParser::default();
+    let mut doc = parser.parse_incremental("", "test");
+    for (i, c) in RECIPE.char_indices() {
+        parser.reparse(&mut doc, [TextEdit::new(i..i, c)]);
+        assert_same(&parser, &doc);
+    }
+    assert_eq!(doc.input(), RECIPE);
+
+    while !doc.input().is_empty() {
+        let end = doc.input().len();
+        let start = boundary(doc.input(), end - 1);
+        parser.reparse(&mut doc, [TextEdit::new(start..end, "")]);
+        assert_same(&parser, &doc);
+    }
+}
+
+#[test]
+fn random_edits() {
+    const PIECES: &[&str] = &[
+        "", "\n", "\n\n", "@", "#", "~", "{", "}", "%", "|", "*", ">", ">> ", "= ", "==", "-- ",
+        "[-", "-]", "(", ")", "&", "1/2", "salt", " ", "\r\n", "º",
+    ];
+    // simple deterministic lcg, no need for anything better
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move |max: usize| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as usize % max.max(1)
+    };
+    // long enough for the analysis to resume from the middle
+    let long = RECIPE.repeat(8);
+    for (ext, input) in [
+        (Extensions::all(), RECIPE),
+        (Extensions::empty(), RECIPE),
+        (Extensions::all(), long.as_str()),
+    ] {
+        let parser = CooklangParser::new(ext, Default::default());
+        let mut doc = parser.parse_incremental(input, "test");
+        for _ in 0..500 {
+            let len = doc.input().len();
+            let start = boundary(doc.input(), next(len + 1));
+            let end = boundary(doc.input(), (start + next(8)).min(len));
+            let text = PIECES[next(PIECES.len())];
+            parser.reparse(&mut doc, [TextEdit::new(start..end, text)]);
+            assert_same(&parser, &doc);
+        }
+    }
+}
+
+#[test]
+fn many_edits() {
+    let parser = CooklangParser::default();
+    let mut doc = parser.parse_incremental("Add @salt.\n", "test");
+    parser.reparse(
+        &mut doc,
+        [
+            TextEdit::new(0..0, ">> servings: 2\n"),
+            TextEdit::new(20..24, "pepper{1%tsp}"),
+            TextEdit::new(15..15, "\n"),
+        ],
+    );
+    assert_eq!(doc.input(), ">> servings: 2\n\nAdd @pepper{1%tsp}.\n");
+    assert_same(&parser, &doc);
+}
+
+#[test]
+fn edit_while_invalid() {
+    let parser = CooklangParser::default();
+    let input = RECIPE.repeat(8);
+    let mut doc = parser.parse_incremental(&input, "test");
+
+    // the analysis does not run while there is an error in the parser
+    let error = "Add @salt{1/0}.\n";
+    parser.reparse(&mut doc, [insert(input.len(), error)]);
+    assert!(doc.result().output().is_none());
+
+    // but the edits before the error still change the result after fixing it
+    let at = find(&input, "500");
+    parser.reparse(&mut doc, [TextEdit::new(at..at + 3, "250")]);
+    let end = doc.input().len();
+    parser.reparse(&mut doc, [TextEdit::new(end - error.len()..end, "")]);
+    assert!(doc.result().output().is_some());
+    assert_same(&parser, &doc);
+}
+
+#[test]
+fn other_extensions() {
+    let all = CooklangParser::default();
+    let none = CooklangParser::new(Extensions::empty(), Default::default());
+    let mut doc = all.parse_incremental(RECIPE, "test");
+    none.reparse(&mut doc, []);
+    assert_same(&none, &doc);
+}
@@ -0,0 +1,208 @@
+use cooklang::{
+    nutrition::{NutrientDatabase, NutritionFile, NutritionWarning},
+    scale::ScaleTarget,
+    CooklangParser,
+};
+use indoc::indoc;
+
+const DB: &str = indoc! {r#"
+    [ingredient.flour]
+    per_100g = { kcal = 364, protein = 10, fat = 1 }
+
+    [ingredient.egg]
+    aliases = ["eggs"]
+    piece = 50
+    per_100g = { kcal = 140, protein = 12, fat = 10 }
+
+    [ingredient.milk]
+    per_100g = { kcal = 60, protein = 3, fat = 3 }
+"#};
+
+fn db() -> NutrientDatabase {
+    let file: NutritionFile = toml::from_str(DB).unwrap();
+    NutrientDatabase::from_file(file)
+}
+
+#[test]
+fn totals() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            indoc! {r#"
+                >> servings: 2
+                Mix @flour{0.5%kg}, @eggs{2} and @&flour{100%g}.
+            "#},
+            "test",
+        )
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let facts = recipe.nutrition(&db(), parser.converter());
+    assert!(facts.warnings.is_empty(), "{:?}", facts.warnings);
+    assert_eq!(facts.total.get("kcal"), Some(6.0 * 364.0 + 140.0));
+    assert_eq!(facts.total.get("protein"), Some(60.0 + 12.0));
+    assert_eq!(facts.servings, Some(2));
+    let per_serving = facts.per_serving.unwrap();
+    assert_eq!(per_serving.get("fat"), Some((6.0 + 10.0) / 2.0));
+}
+
+#[test]
+fn scaling() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(">> servings: 1|2\n@flour{100|200%g} @egg{1*}", "test")
+        .into_output()
+        .unwrap();
+    let one = recipe
+        .clone()
+        .default_scale()
+        .nutrition(&db(), parser.converter());
+    let two = recipe
+        .clone()
+        .scale(2, parser.converter())
+        .nutrition(&db(), parser.converter());
+    assert_eq!(one.total.get("kcal"), Some(364.0 + 70.0));
+    assert_eq!(two.total.get("kcal"), Some(2.0 * (364.0 + 70.0)));
+    assert_eq!(one.per_serving, two.per_serving);
+    assert_eq!(two.servings, Some(2));
+
+    let by_factor = recipe
+        .scale_to(ScaleTarget::by_factor(2.0), parser.converter())
+        .nutrition(&db(), parser.converter());
+    assert_eq!(by_factor.total, two.total);
+    assert_eq!(by_factor.servings, Some(2));
+}
+
+#[test]
+fn warnings() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            "@salt{1%tsp} @flour{a bit} @milk{1%cup} @flour @egg{1%cm} @egg{2-4%g} @flour{}",
+            "test",
+        )
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let facts = recipe.nutrition(&db(), parser.converter());
+    let warnings = facts
+        .warnings
+        .iter()
+        .map(|w| match w {
+            NutritionWarning::UnknownIngredient { index, .. } => format!("unknown {index}"),
+            NutritionWarning::NoQuantity { index, .. } => format!("no quantity {index}"),
+            NutritionWarning::TextValue { index, value, .. } => format!("text {index} {value}"),
+            NutritionWarning::IncompatibleUnit {
+                index, quantity, ..
+            } => {
+                format!("incompatible {index} {quantity}")
+            }
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "unknown 0",
+            "text 1 a bit",
+            "incompatible 2 1 cup",
+            "no quantity 3",
+            "incompatible 4 1 cm",
+            "no quantity 6",
+        ]
+    );
+    // the range uses the middle value
+    assert_eq!(facts.total.get("kcal"), Some(3.0 / 100.0 * 140.0));
+    assert_eq!(facts.servings, None);
+    assert_eq!(facts.per_serving, None);
+}
+
+#[cfg(feature = "nutrition_csv")]
+#[test]
+fn csv_errors() {
+    let err = NutrientDatabase::from_csv("name,kcal\nflour,1,2").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error in CSV line 2: expected 2 columns, found 3"
+    );
+    let err = NutrientDatabase::from_csv("name,kcal\n\nflour,lots").unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Error in CSV line 3: invalid number in column 'kcal'"));
+}
+
+#[cfg(feature = "nutrition_csv")]
+#[test]
+fn csv_quoted() {
+    let db = NutrientDatabase::from_csv(indoc! {r#"
+        name,"kcal"
+        "cheese, grated", 402
+        "say ""cheese""",1
+    "#})
+    .unwrap();
+    let cheese = db.get("cheese, grated").unwrap();
+    assert_eq!(cheese.per_100g.get("kcal"), Some(402.0));
+    assert!(db.get(r#"say "cheese""#).is_some());
+}
+
+#[test]
+fn volume_with_density() {
+    let densities: cooklang::convert::DensityFile =
+        toml::from_str("[ingredient.milk]\ndensity = 1.0").unwrap();
+    let converter = cooklang::Converter::builder()
+        .with_bundled_units()
+        .unwrap()
+        .with_density_file(densities)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let parser = CooklangParser::new(cooklang::Extensions::all(), converter);
+    let recipe = parser
+        .parse("@milk{0.5%l}", "test")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let facts = recipe.nutrition(&db(), parser.converter());
+    assert!(facts.warnings.is_empty(), "{:?}", facts.warnings);
+    assert_eq!(facts.total.get("kcal"), Some(300.0));
+}
+
+#[test]
+fn count_units() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse("@eggs{1%dozen}", "test")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let facts = recipe.nutrition(&db(), parser.converter());
+    assert!(facts.warnings.is_empty(), "{:?}", facts.warnings);
+    assert_eq!(facts.total.get("kcal"), Some(6.0 * 140.0));
+}
+
+#[test]
+fn count_units_without_piece() {
+    let units: cooklang::convert::UnitsFile = toml::from_str(indoc! {r#"
+        [[quantity]]
+        quantity = "count"
+        best = ["unit"]
+        units = [
+            { names = ["unit", "units"], symbols = ["u"], ratio = 1 },
+            { names = ["pair", "pairs"], symbols = [], ratio = 2 },
+        ]
+    "#})
+    .unwrap();
+    let converter = cooklang::Converter::builder()
+        .with_units_file(units)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let parser = CooklangParser::new(cooklang::Extensions::all(), converter);
+    let recipe = parser
+        .parse("@eggs{3%pairs}", "test")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let facts = recipe.nutrition(&db(), parser.converter());
+    assert!(facts.warnings.is_empty(), "{:?}", facts.warnings);
+    assert_eq!(facts.total.get("kcal"), Some(6.0 * 70.0));
+}
@@ -0,0 +1,238 @@
+use cooklang::{
+    quantity::format::QuantityFormatter,
+    render::{self, Html, Markdown, Render},
+    scale::ScaleTarget,
+    CooklangParser,
+};
+use indoc::indoc;
+
+const RECIPE: &str = indoc! {r#"
+    >> servings: 2|4
+    >> description: Fluffy <and> easy
+    >> tags: breakfast
+
+    = Batter
+    Mix @flour{250*%g}(sifted) with @eggs{2|4} and a pinch of @-salt.
+
+    Add @milk{250*%ml}, then more @&milk{100*%ml}.
+
+    = Cooking
+    > Use a *nonstick* pan.
+
+    Cook in a #pan{} for ~{2%min} each at 180 ºC.
+"#};
+
+#[test]
+fn markdown() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(RECIPE, "Pancakes").into_output().unwrap();
+    let recipe = recipe.scale(4, parser.converter());
+    assert_eq!(
+        render::markdown(&recipe, parser.converter()),
+        indoc! {r#"
+            # Pancakes
+
+            Fluffy \<and\> easy
+
+            - servings: 4
+            - tags: breakfast
+
+            ## Ingredients
+
+            - 500 g **flour** (sifted)
+            - 4 **eggs**
+            - 700 ml **milk**
+
+            ## Steps
+
+            ### Batter
+
+            1. Mix **flour** (500 g) with **eggs** (4) and a pinch of **salt**.
+
+            2. Add **milk** (500 ml), then more **milk** (200 ml).
+
+            ### Cooking
+
+            Use a \*nonstick\* pan.
+
+            1. Cook in a *pan* for **2 min** each at 180 ºC.
+        "#}
+    );
+}
+
+#[test]
+fn html() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(RECIPE, "Pancakes").into_output().unwrap();
+    let recipe = recipe.scale(4, parser.converter());
+    assert_eq!(
+        render::html(&recipe, parser.converter()),
+        indoc! {r#"
+            <article class="recipe">
+            <header>
+            <h1>Pancakes</h1>
+            <p class="description">Fluffy &lt;and&gt; easy</p>
+            <dl class="metadata">
+            <dt>servings</dt><dd>4</dd>
+            <dt>tags</dt><dd>breakfast</dd>
+            </dl>
+            </header>
+            <section class="ingredients">
+            <h2>Ingredients</h2>
+            <ul>
+            <li data-index="0"><span class="quantity">500 g</span> <span class="name">flour</span> <span class="note">sifted</span></li>
+            <li data-index="1"><span class="quantity">4</span> <span class="name">eggs</span></li>
+            <li data-index="3"><span class="quantity">700 ml</span> <span class="name">milk</span></li>
+            </ul>
+            </section>
+            <section class="steps">
+            <h2>Steps</h2>
+            <section data-index="0">
+            <h3>Batter</h3>
+            <ol>
+            <li value="1">Mix <span class="ingredient" data-index="0">flour</span> <span class="quantity">(500 g)</span> with <span class="ingredient" data-index="1">eggs</span> <span class="quantity">(4)</span> and a pinch of <span class="ingredient" data-index="2">salt</span>.</li>
+            <li value="2">Add <span class="ingredient" data-index="3">milk</span> <span class="quantity">(500 ml)</span>, then more <span class="ingredient" data-index="4">milk</span> <span class="quantity">(200 ml)</span>.</li>
+            </ol>
+            </section>
+            <section data-index="1">
+            <h3>Cooking</h3>
+            <p>Use a *nonstick* pan.</p>
+            <ol>
+            <li value="1">Cook in a <span class="cookware" data-index="0">pan</span> for <span class="timer" data-index="0">2 min</span> each at <span class="inline-quantity" data-index="0">180 ºC</span>.</li>
+            </ol>
+            </section>
+            </section>
+            </article>
+        "#}
+    );
+}
+
+#[test]
+fn minimal() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse("Boil water.\nWait.\n\nServe.", "Water")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    assert_eq!(
+        render::markdown(&recipe, parser.converter()),
+        indoc! {"
+            # Water
+
+            ## Steps
+
+            1. Boil water. Wait.
+
+            2. Serve.
+        "}
+    );
+    assert_eq!(
+        render::html(&recipe, parser.converter()),
+        indoc! {r#"
+            <article class="recipe">
+            <header>
+            <h1>Water</h1>
+            </header>
+            <section class="steps">
+            <h2>Steps</h2>
+            <section data-index="0">
+            <ol>
+            <li value="1">Boil water. Wait.</li>
+            <li value="2">Serve.</li>
+            </ol>
+            </section>
+            </section>
+            </article>
+        "#}
+    );
+}
+
+#[test]
+fn scaled_header() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(">> servings: 2\n>> yield: 1 loaf\n@flour{500*%g}", "Bread")
+        .into_output()
+        .unwrap()
+        .scale_to(ScaleTarget::by_factor(2.0), parser.converter());
+    let md = render::markdown(&recipe, parser.converter());
+    assert!(md.contains("- servings: 4\n- yield: 2 loaf\n"), "{md}");
+}
+
+#[test]
+fn block_markers() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse("> # Not a heading\n\n1. Not a list", "Escapes")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let md = render::markdown(&recipe, parser.converter());
+    assert!(md.contains("\n\\# Not a heading\n"), "{md}");
+    assert!(md.contains("\n1. 1\\. Not a list\n"), "{md}");
+}
+
+#[test]
+fn plain_text_defaults() {
+    struct Plain;
+    impl Render for Plain {}
+
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(RECIPE, "Pancakes").into_output().unwrap();
+    let recipe = recipe.scale(4, parser.converter());
+    assert_eq!(
+        Plain.render(&recipe, parser.converter()),
+        indoc! {"
+            Pancakes
+
+            Fluffy <and> easy
+
+            servings: 4
+            tags: breakfast
+
+            Ingredients:
+            - 500 g flour (sifted)
+            - 4 eggs
+            - 700 ml milk
+
+            Steps:
+
+            Batter:
+
+            1. Mix flour (500 g) with eggs (4) and a pinch of salt.
+
+            2. Add milk (500 ml), then more milk (200 ml).
+
+            Cooking:
+
+            Use a *nonstick* pan.
+
+            1. Cook in a pan for 2 min each at 180 ºC.
+        "}
+    );
+}
+
+#[test]
+fn quantity_formatter() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            ">> yield: 1.5 loaf\nAdd @milk{0.333%cup} and @&milk{1/2%cup}, wait ~{2.5%min}.",
+            "Milky",
+        )
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let formatter = QuantityFormatter::cooking();
+    let md = Markdown.render_with_formatter(&recipe, parser.converter(), &formatter);
+    assert!(md.contains("- yield: 1 ½ loaf\n"), "{md}");
+    // the total is fitted to the best unit
+    assert!(md.contains("- 13 ¼ tbs **milk**\n"), "{md}");
+    assert!(
+        md.contains("1. Add **milk** (⅓ cup) and **milk** (½ cup), wait **2 ½ min**."),
+        "{md}"
+    );
+    let html = Html::default().render_with_formatter(&recipe, parser.converter(), &formatter);
+    assert!(html.contains(r#"<span class="timer" data-index="0">2 ½ min</span>"#));
+}
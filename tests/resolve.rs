@@ -0,0 +1,500 @@
+use cooklang::{
+    error::RichError,
+    resolve::{FsSource, MemorySource, ResolveError, Resolver},
+    CooklangParser,
+};
+
+fn resolve(recipes: &[(&str, &str)], roots: &[&str]) -> cooklang::resolve::RecipeGraph {
+    let parser = CooklangParser::default();
+    let source = MemorySource::from_iter(recipes.iter().copied());
+    Resolver::new(&parser, source).resolve(roots.iter().copied())
+}
+
+#[test]
+fn filesystem() {
+    let parser = CooklangParser::default();
+    let source = FsSource::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/collection"));
+    let graph = Resolver::new(&parser, source).resolve(["Pizza.cook"]);
+    assert!(graph.is_valid(), "{:?}", graph.errors());
+    assert_eq!(
+        graph.order().collect::<Vec<_>>(),
+        [
+            "sauces/Sofrito.cook",
+            "sauces/Tomato sauce.cook",
+            "Pizza.cook"
+        ]
+    );
+    assert_eq!(
+        graph.get("sauces/Sofrito.cook").unwrap().recipe.name,
+        "Sofrito"
+    );
+    assert_eq!(graph.total_time("Pizza.cook"), Some(55));
+    assert_eq!(graph.total_time("sauces/Sofrito.cook"), Some(5));
+
+    let tree = graph.ingredient_tree("Pizza.cook").unwrap();
+    let leaves = tree
+        .leaves()
+        .iter()
+        .map(|n| n.ingredient.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(leaves, ["tomatoes", "onion", "garlic", "cheese"]);
+}
+
+#[test]
+fn shared_dependency() {
+    let graph = resolve(
+        &[
+            ("Menu", "@@Pasta{} and @@Salad{}"),
+            ("Pasta", ">> time: 10\n@@Dressing{} @pasta"),
+            ("Salad", ">> time: 5\n@@Dressing{} @lettuce"),
+            ("Dressing", ">> time: 2\n@oil @vinegar"),
+        ],
+        &["Menu"],
+    );
+    assert!(graph.is_valid());
+    assert_eq!(graph.order().next(), Some("Dressing"));
+    let mut dependents = graph.dependents("Dressing").collect::<Vec<_>>();
+    dependents.sort();
+    assert_eq!(dependents, ["Pasta", "Salad"]);
+    assert_eq!(graph.all_dependencies("Menu").len(), 3);
+    // the dressing is only made once
+    assert_eq!(graph.total_time("Menu"), Some(17));
+    assert_eq!(graph.get("Menu").unwrap().dependencies.len(), 2);
+}
+
+#[test]
+fn cycle() {
+    let graph = resolve(
+        &[("A", "@@B{}"), ("B", "Add @@C{}"), ("C", "@@A{} @salt")],
+        &["A"],
+    );
+    assert_eq!(graph.errors().len(), 1);
+    let err = &graph.errors()[0];
+    assert!(matches!(err, ResolveError::Cycle { path, .. } if path == &["A", "B", "C", "A"]));
+    assert_eq!(err.to_string(), "Recipe reference cycle: A -> B -> C -> A");
+    assert_eq!(err.recipe(), "C");
+    let (span, _) = &err.labels()[0];
+    assert_eq!(&"@@A{} @salt"[span.range()], "@@A{}");
+
+    // the tree stops at the cycle
+    let tree = graph.ingredient_tree("A").unwrap();
+    let c = tree.ingredients[0].recipe.as_ref().unwrap().ingredients[0]
+        .recipe
+        .as_ref()
+        .unwrap();
+    assert_eq!(c.id, "C");
+    assert!(c.ingredients[0].recipe.is_none());
+    assert_eq!(c.ingredients[1].ingredient.name, "salt");
+}
+
+#[test]
+fn self_reference() {
+    let graph = resolve(&[("A", "@@A{}")], &["A"]);
+    assert!(matches!(
+        graph.errors(),
+        [ResolveError::Cycle { path, .. }] if path == &["A", "A"]
+    ));
+}
+
+#[test]
+fn errors() {
+    let graph = resolve(
+        &[
+            ("A", "@@Missing{} @@B{} @@C{} @@../Outside{}"),
+            ("C", "@&salt{}"),
+        ],
+        &["A", "D"],
+    );
+    let mut errors = graph.errors().iter().map(|e| match e {
+        ResolveError::NotFound { reference, .. } => format!("not found {reference}"),
+        ResolveError::Io { id, .. } => format!("io {id}"),
+        ResolveError::Parse { id, report, .. } => {
+            assert!(report.has_errors());
+            format!("parse {id}")
+        }
+        ResolveError::Cycle { .. } => panic!("no cycle"),
+    });
+    assert_eq!(errors.next().unwrap(), "not found Missing");
+    assert_eq!(errors.next().unwrap(), "not found B");
+    assert_eq!(errors.next().unwrap(), "not found ../Outside");
+    assert_eq!(errors.next().unwrap(), "parse C");
+    assert_eq!(errors.next().unwrap(), "io D");
+    assert!(errors.next().is_none());
+
+    // the valid recipes are still there
+    assert!(graph.get("A").is_some());
+    assert!(graph.get("C").is_none());
+}
+
+#[test]
+fn references_are_not_dependencies() {
+    let graph = resolve(&[("A", "@@B{1} then @&@B{2}"), ("B", "@salt")], &["A"]);
+    assert!(graph.is_valid());
+    assert_eq!(graph.get("A").unwrap().dependencies.len(), 1);
+}
+
+mod collection {
+    use std::{fs, path::PathBuf};
+
+    use cooklang::{
+        collection::{Changes, Collection, Query},
+        CooklangParser,
+    };
+
+    /// Temporary directory removed on drop
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("cooklang-collection-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, file: &str, content: &str) {
+            let path = self.0.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn ids<'a>(entries: impl IntoIterator<Item = &'a cooklang::collection::Entry>) -> Vec<&'a str> {
+        entries.into_iter().map(|e| e.id()).collect()
+    }
+
+    fn collection(dir: &Dir) -> Collection {
+        let mut collection = Collection::new(&dir.0, CooklangParser::default());
+        collection.refresh().unwrap();
+        collection
+    }
+
+    fn sample(name: &str) -> Dir {
+        let dir = Dir::new(name);
+        dir.write(
+            "Hummus.cook",
+            ">> tags: vegan, dip\n>> time: 15 min\n>> servings: 4\n>> author: Ana\n\
+             Blend @chickpeas{400%g} and @tahini in a #food processor.",
+        );
+        dir.write(
+            "mains/Falafel.cook",
+            ">> tags: vegan\n>> prep time: 20 min\n>> cook time: 15 min\n>> servings: 2|4\n\
+             >> source: https://example.com/falafel\n\
+             Mix @dried chickpeas{250%g} and @@../Hummus{}. Fry in a #pan.",
+        );
+        dir.write(
+            "mains/Omelette.cook",
+            ">> time: 10 min\n>> servings: 1\n>> author: Bob <https://bob.example.com>\n\
+             Beat @eggs{3} and cook in a #Pan.",
+        );
+        dir.write(".hidden/Secret.cook", "@secret");
+        dir.write("notes.txt", "not a recipe");
+        dir
+    }
+
+    #[test]
+    fn index() {
+        let dir = sample("index");
+        let collection = collection(&dir);
+        assert_eq!(
+            ids(collection.iter()),
+            ["Hummus.cook", "mains/Falafel.cook", "mains/Omelette.cook"]
+        );
+        let falafel = collection.get("mains/Falafel.cook").unwrap();
+        assert_eq!(falafel.name(), "Falafel");
+        assert_eq!(falafel.total_time(), Some(35));
+        assert!(falafel.has_valid_metadata());
+    }
+
+    #[test]
+    fn search() {
+        let dir = sample("search");
+        let collection = collection(&dir);
+        let search = |q: Query| ids(collection.search(&q));
+
+        assert_eq!(search(Query::new()).len(), 3);
+        assert_eq!(search(Query::new().tag("DIP")), ["Hummus.cook"]);
+        assert_eq!(
+            search(Query::new().tag("vegan")),
+            ["Hummus.cook", "mains/Falafel.cook"]
+        );
+        assert_eq!(
+            search(Query::new().tag("vegan").tag("dip")),
+            ["Hummus.cook"]
+        );
+        assert_eq!(
+            search(Query::new().ingredient("Chickpeas")),
+            ["Hummus.cook"]
+        );
+        // recipe references use the recipe name
+        assert_eq!(
+            search(Query::new().ingredient("hummus")),
+            ["mains/Falafel.cook"]
+        );
+        assert_eq!(
+            search(Query::new().cookware("pan")),
+            ["mains/Falafel.cook", "mains/Omelette.cook"]
+        );
+        assert_eq!(search(Query::new().author("ana")), ["Hummus.cook"]);
+        assert_eq!(
+            search(Query::new().author("bob.example")),
+            ["mains/Omelette.cook"]
+        );
+        assert_eq!(
+            search(Query::new().source("example.com")),
+            ["mains/Falafel.cook"]
+        );
+        assert_eq!(
+            search(Query::new().max_time(15)),
+            ["Hummus.cook", "mains/Omelette.cook"]
+        );
+        assert_eq!(search(Query::new().min_time(20)), ["mains/Falafel.cook"]);
+        assert_eq!(
+            search(Query::new().servings(4)),
+            ["Hummus.cook", "mains/Falafel.cook"]
+        );
+        assert!(search(Query::new().tag("vegan").servings(1)).is_empty());
+    }
+
+    #[test]
+    fn refresh() {
+        let dir = sample("refresh");
+        let mut collection = collection(&dir);
+
+        assert!(collection.refresh().unwrap().is_empty());
+
+        dir.write(
+            "mains/Omelette.cook",
+            ">> tags: quick\n@eggs{2} and @cheese",
+        );
+        dir.write(
+            "desserts/Flan.cook",
+            ">> tags: quick\n@eggs{4} @milk{500%ml}",
+        );
+        fs::remove_file(dir.0.join("Hummus.cook")).unwrap();
+        let changes = collection.refresh().unwrap();
+        assert_eq!(
+            changes,
+            Changes {
+                added: vec!["desserts/Flan.cook".into()],
+                updated: vec!["mains/Omelette.cook".into()],
+                removed: vec!["Hummus.cook".into()],
+                failed: vec![],
+            }
+        );
+        assert_eq!(
+            ids(collection.search(&Query::new().tag("quick").ingredient("eggs"))),
+            ["desserts/Flan.cook", "mains/Omelette.cook"]
+        );
+        assert_eq!(
+            ids(collection.search(&Query::new().ingredient("cheese"))),
+            ["mains/Omelette.cook"]
+        );
+    }
+
+    #[test]
+    fn unreadable_file() {
+        let dir = sample("unreadable");
+        let mut collection = collection(&dir);
+        let len = collection.len();
+
+        fs::write(dir.0.join("Hummus.cook"), b"@chickpeas \xff").unwrap();
+        dir.write("New.cook", "@eggs");
+        let changes = collection.refresh().unwrap();
+        assert_eq!(
+            changes,
+            Changes {
+                added: vec!["New.cook".into()],
+                failed: vec![("Hummus.cook".into(), std::io::ErrorKind::InvalidData)],
+                ..Default::default()
+            }
+        );
+        assert_eq!(collection.len(), len + 1);
+        assert!(collection.get("Hummus.cook").is_some());
+
+        dir.write("Hummus.cook", "@chickpeas");
+        let changes = collection.refresh().unwrap();
+        assert_eq!(changes.updated, ["Hummus.cook"]);
+        assert!(changes.failed.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let dir = Dir::new("errors");
+        dir.write("Bad.cook", ">> servings: many\n@missing{1|2}");
+        let collection = collection(&dir);
+        let bad = collection.get("Bad.cook").unwrap();
+        assert_eq!(collection.search(&Query::new()).len(), 1);
+        assert!(collection
+            .search(&Query::new().ingredient("missing"))
+            .is_empty());
+        assert!(bad.parse(collection.parser()).unwrap().has_errors());
+    }
+}
+
+mod ingredient_list {
+    use cooklang::{
+        ingredient_list::{IngredientList, IngredientListWarning},
+        resolve::{MemorySource, Resolver},
+        CooklangParser,
+    };
+
+    /// Expands `root` and returns the list as `name: total` strings
+    fn expand(recipes: &[(&str, &str)], root: &str) -> (Vec<String>, IngredientList) {
+        let parser = CooklangParser::default();
+        let source = MemorySource::from_iter(recipes.iter().copied());
+        let graph = Resolver::new(&parser, source).resolve([root]);
+        let recipe = graph.get(root).unwrap().recipe.clone().default_scale();
+        let mut list = IngredientList::new();
+        list.add_recipe_expanded(&recipe, root, &graph, parser.converter());
+        let entries = list
+            .iter()
+            .map(|(name, q)| {
+                let total = q
+                    .total()
+                    .into_vec()
+                    .iter()
+                    .map(|q| q.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{name}: {total}")
+            })
+            .collect();
+        (entries, list)
+    }
+
+    #[test]
+    fn yield_units() {
+        let (list, _) = expand(
+            &[
+                ("Pizza", "@@Sauce{1%l} and @tomatoes{100%g}"),
+                ("Sauce", ">> yield: 500 ml\n@tomatoes{400*%g} @salt{1%tsp}"),
+            ],
+            "Pizza",
+        );
+        assert_eq!(list, ["salt: 1 tsp", "tomatoes: 900 g"]);
+    }
+
+    #[test]
+    fn servings() {
+        let (list, _) = expand(
+            &[
+                ("Menu", "@@Salad{4} @@Soup{3%servings} @@Bread{}"),
+                ("Salad", ">> servings: 2\n@lettuce{1*}"),
+                ("Soup", ">> servings: 2|3\n@water{1|1.5%l}"),
+                ("Bread", "@flour{500*%g}"),
+            ],
+            "Menu",
+        );
+        assert_eq!(list, ["flour: 500 g", "lettuce: 2", "water: 1.5 l"]);
+    }
+
+    #[test]
+    fn nested_origins() {
+        let (list, ingredients) = expand(
+            &[
+                ("Lasagna", "@@sauces/Bolognese{2} @@sauces/Bechamel{} @salt"),
+                ("sauces/Bolognese", "@@./Sofrito{} @beef{250*%g} @salt"),
+                ("sauces/Sofrito", "@onion{1*} @salt"),
+                ("sauces/Bechamel", "@milk{500%ml}"),
+            ],
+            "Lasagna",
+        );
+        assert_eq!(list, ["beef: 500 g", "milk: 500 ml", "onion: 2", "salt: "]);
+        assert_eq!(ingredients.origins("onion"), ["sauces/Sofrito"]);
+        assert_eq!(ingredients.origins("beef"), ["sauces/Bolognese"]);
+        assert_eq!(
+            ingredients.origins("salt"),
+            ["sauces/Sofrito", "sauces/Bolognese"]
+        );
+        assert!(ingredients.origins("missing").is_empty());
+    }
+
+    #[test]
+    fn not_expanded() {
+        let (list, ingredients) = expand(
+            &[
+                ("A", "@@Missing{1} @@Sauce{2%cups} @@B{}"),
+                ("Sauce", "@tomatoes{1*}"),
+                ("B", "@@A{} @flour"),
+            ],
+            "A",
+        );
+        // not resolved, no yield to scale to and a cycle
+        assert_eq!(list, ["A: ", "Missing: 1", "Sauce: 2 c", "flour: "]);
+        assert_eq!(ingredients.origins("A"), ["B"]);
+        // the other two are reported by the resolver
+        assert!(matches!(
+            ingredients.warnings(),
+            [IngredientListWarning::RecipeNotExpanded { recipe, ingredient: None, source: None }]
+                if recipe == "Sauce"
+        ));
+    }
+
+    #[test]
+    fn ingredient_not_scaled() {
+        let (list, ingredients) = expand(
+            &[
+                ("Menu", "@@Soup{3} @bread{1}"),
+                ("Soup", ">> servings: 2|4\n@water{1|2%l} @salt{1*%tsp}"),
+            ],
+            "Menu",
+        );
+        // 3 servings is not one of the values of the water
+        assert_eq!(list, ["Soup: 3", "bread: 1"]);
+        assert!(matches!(
+            ingredients.warnings(),
+            [IngredientListWarning::RecipeNotExpanded { recipe, ingredient: Some(ingredient), source: Some(_) }]
+                if recipe == "Soup" && ingredient == "water"
+        ));
+    }
+
+    #[test]
+    fn scaled_parent() {
+        let parser = CooklangParser::default();
+        let source = MemorySource::from_iter([
+            ("Pizza", ">> servings: 2\n@@Dough{} @@Sauce{100*%ml}"),
+            ("Dough", "@flour{300*%g}"),
+            ("Sauce", ">> yield: 200%ml\n@tomatoes{2*}"),
+        ]);
+        let graph = Resolver::new(&parser, source).resolve(["Pizza"]);
+        let recipe = graph.get("Pizza").unwrap().recipe.clone();
+        let list = IngredientList::from_recipe(
+            &recipe.clone().scale(4, parser.converter()),
+            parser.converter(),
+        );
+        assert_eq!(list.iter().count(), 2);
+
+        let mut list = IngredientList::new();
+        let scaled = recipe.scale(4, parser.converter());
+        list.add_recipe_expanded(&scaled, "Pizza", &graph, parser.converter());
+        let totals = list
+            .iter()
+            .map(|(name, q)| format!("{name}: {}", q.total().into_vec()[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(totals, ["flour: 600 g", "tomatoes: 2"]);
+    }
+
+    #[test]
+    fn scale_error_warning() {
+        let parser = CooklangParser::default();
+        let recipe = parser
+            .parse(">> servings: 2|4\n@water{1|2%l} @salt{1*%tsp}", "Soup")
+            .into_output()
+            .unwrap()
+            .scale(3, parser.converter());
+        let list = IngredientList::from_recipe(&recipe, parser.converter());
+        assert_eq!(list.iter().count(), 2);
+        assert!(matches!(
+            list.warnings(),
+            [IngredientListWarning::IngredientNotScaled { ingredient, .. }] if ingredient == "water"
+        ));
+    }
+}
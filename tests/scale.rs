@@ -0,0 +1,361 @@
+use cooklang::{
+    convert::System,
+    scale::{
+        ComponentPolicy, ScaleError, ScaleOutcome, ScalePolicy, ScaleRule, ScaleTarget,
+        ScaleTargetKind,
+    },
+    Converter, CooklangParser, Quantity, QuantityValue,
+};
+use indoc::indoc;
+
+mod common;
+use common::{number, quantity};
+
+const RECIPE: &str = indoc! {r#"
+    >> servings: 2
+    Mix @flour{500*%g} and @water{300*%ml} in a #bowl{1}.
+    Bake at 250 °C for ~{40%min}.
+"#};
+
+fn unit(q: &Quantity) -> &str {
+    q.unit_text().unwrap()
+}
+
+#[test]
+fn default_policy() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(RECIPE, "test").into_output().unwrap();
+    let scaled = recipe.clone().scale(8, parser.converter());
+    let with_policy = recipe.scale_with_policy(8, parser.converter(), &ScalePolicy::default());
+    assert_eq!(with_policy.ingredients, scaled.ingredients);
+    assert_eq!(with_policy.timers, scaled.timers);
+
+    // ingredients scaled linearly and fitted
+    let flour = scaled.ingredients[0].quantity.as_ref().unwrap();
+    assert_eq!((number(flour), unit(flour)), (2.0, "kg"));
+    let water = scaled.ingredients[1].quantity.as_ref().unwrap();
+    assert_eq!((number(water), unit(water)), (1.2, "l"));
+    // timers can't be marked as scalable
+    let timer = scaled.timers[0].quantity.as_ref().unwrap();
+    assert_eq!((number(timer), unit(timer)), (40.0, "min"));
+    // temperature untouched
+    let temp = &scaled.inline_quantities[0];
+    assert_eq!((number(temp), unit(temp)), (250.0, "°C"));
+
+    let data = scaled.scaled_data().unwrap();
+    assert!(matches!(data.cookware[..], [ScaleOutcome::Fixed]));
+    assert!(matches!(data.timers[..], [ScaleOutcome::Fixed]));
+    assert!(matches!(data.inline_quantities[..], [ScaleOutcome::Fixed]));
+}
+
+#[test]
+fn custom_policy() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(RECIPE, "test").into_output().unwrap();
+    let policy = ScalePolicy {
+        ingredients: ComponentPolicy {
+            rule: ScaleRule::Linear,
+            scale_fixed: false,
+            fit: false,
+        },
+        cookware: ComponentPolicy {
+            rule: ScaleRule::Linear,
+            scale_fixed: true,
+            fit: false,
+        },
+        timers: ComponentPolicy {
+            rule: ScaleRule::Power { exponent: 0.5 },
+            scale_fixed: true,
+            fit: true,
+        },
+        inline_quantities: ComponentPolicy {
+            rule: ScaleRule::Never,
+            scale_fixed: true,
+            fit: true,
+        },
+    };
+    let scaled = recipe.scale_with_policy(8, parser.converter(), &policy);
+
+    let flour = scaled.ingredients[0].quantity.as_ref().unwrap();
+    assert_eq!((number(flour), unit(flour)), (2000.0, "g"));
+    // 40 * sqrt(4) min, fitted
+    let timer = scaled.timers[0].quantity.as_ref().unwrap();
+    assert_eq!((number(timer), unit(timer)), (80.0 / 60.0, "h"));
+    let temp = &scaled.inline_quantities[0];
+    assert_eq!((number(temp), unit(temp)), (250.0, "°C"));
+    assert_eq!(
+        scaled.cookware[0].quantity,
+        Some(QuantityValue::Fixed { value: 4.0.into() })
+    );
+
+    let data = scaled.scaled_data().unwrap();
+    assert!(matches!(
+        data.ingredients[..],
+        [ScaleOutcome::Scaled, ScaleOutcome::Scaled]
+    ));
+    assert!(matches!(data.cookware[..], [ScaleOutcome::Scaled]));
+    assert!(matches!(data.timers[..], [ScaleOutcome::Scaled]));
+    assert!(matches!(data.inline_quantities[..], [ScaleOutcome::Fixed]));
+}
+
+#[test]
+fn by_servings_with_rules() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            ">> servings: 2|4\nCook for ~{10|15%min} and ~{5%min}.",
+            "test",
+        )
+        .into_output()
+        .unwrap();
+    let policy = ScalePolicy {
+        timers: ComponentPolicy {
+            rule: ScaleRule::Power { exponent: 0.5 },
+            scale_fixed: true,
+            fit: false,
+        },
+        ..Default::default()
+    };
+    let scaled = recipe
+        .clone()
+        .scale_with_policy(4, parser.converter(), &policy);
+    // the servings values are always used
+    assert_eq!(number(scaled.timers[0].quantity.as_ref().unwrap()), 15.0);
+    assert_eq!(
+        number(scaled.timers[1].quantity.as_ref().unwrap()),
+        5.0 * 2f64.sqrt()
+    );
+
+    let policy = ScalePolicy {
+        timers: ComponentPolicy {
+            rule: ScaleRule::Never,
+            scale_fixed: true,
+            fit: false,
+        },
+        ..Default::default()
+    };
+    let scaled = recipe.scale_with_policy(4, parser.converter(), &policy);
+    // except when never scaling
+    assert_eq!(number(scaled.timers[0].quantity.as_ref().unwrap()), 10.0);
+    assert_eq!(number(scaled.timers[1].quantity.as_ref().unwrap()), 5.0);
+}
+
+#[test]
+fn by_factor() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(">> servings: 2|3\n@flour{500*%g} @salt{1|1.5%tsp}", "test")
+        .into_output()
+        .unwrap();
+
+    let scaled = recipe
+        .clone()
+        .scale_to(ScaleTarget::by_factor(1.5), parser.converter());
+    let data = scaled.scaled_data().unwrap();
+    assert_eq!(data.target.kind(), &ScaleTargetKind::Factor);
+    assert_eq!(data.target.requested_servings(), None);
+    assert_eq!(data.target.target_servings(), 3);
+    // results in 3 servings, so the servings values are used
+    assert_eq!(data.target.index(), Some(1));
+    assert_eq!(
+        number(scaled.ingredients[0].quantity.as_ref().unwrap()),
+        750.0
+    );
+    assert_eq!(
+        number(scaled.ingredients[1].quantity.as_ref().unwrap()),
+        1.5
+    );
+
+    let scaled = recipe.scale_to(ScaleTarget::by_factor(2.0), parser.converter());
+    let data = scaled.scaled_data().unwrap();
+    assert_eq!(
+        number(scaled.ingredients[0].quantity.as_ref().unwrap()),
+        1.0
+    );
+    assert!(matches!(
+        data.ingredients[..],
+        [ScaleOutcome::Scaled, ScaleOutcome::Error(_)]
+    ));
+}
+
+#[test]
+fn to_ingredient() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            "Mix @flour{500*%g}, @water{300*%ml} and @salt{10%g}. Add @yeast.",
+            "test",
+        )
+        .into_output()
+        .unwrap();
+
+    let target =
+        ScaleTarget::ingredient(&recipe, 0, quantity(750.0, Some("g")), parser.converter())
+            .unwrap();
+    assert_eq!(target.factor(), 1.5);
+    let target =
+        ScaleTarget::ingredient(&recipe, 0, quantity(1.0, Some("kg")), parser.converter()).unwrap();
+    assert_eq!(target.factor(), 2.0);
+    assert_eq!(target.kind(), &ScaleTargetKind::Ingredient { index: 0 });
+    let scaled = recipe.clone().scale_to(target, parser.converter());
+    let water = scaled.ingredients[1].quantity.as_ref().unwrap();
+    assert_eq!((number(water), unit(water)), (600.0, "ml"));
+    let salt = scaled.ingredients[2].quantity.as_ref().unwrap();
+    assert_eq!((number(salt), unit(salt)), (10.0, "g"));
+
+    // crossing to mass with the water density
+    let file = toml::from_str("[ingredient.water]\ndensity = 1.0").unwrap();
+    let converter = Converter::builder()
+        .with_bundled_units()
+        .unwrap()
+        .with_density_file(file)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let target =
+        ScaleTarget::ingredient(&recipe, 1, quantity(600.0, Some("g")), &converter).unwrap();
+    assert!((target.factor() - 2.0).abs() < 1e-9);
+
+    let err =
+        |index, q| ScaleTarget::ingredient(&recipe, index, q, parser.converter()).unwrap_err();
+    assert!(matches!(
+        err(0, quantity(1.0, Some("l"))),
+        ScaleError::IncompatibleTarget { .. }
+    ));
+    assert!(matches!(
+        err(0, quantity(1.0, None)),
+        ScaleError::IncompatibleTarget { .. }
+    ));
+    assert!(matches!(
+        err(2, quantity(20.0, Some("g"))),
+        ScaleError::InvalidTarget { .. }
+    ));
+    assert!(matches!(
+        err(3, quantity(1.0, None)),
+        ScaleError::InvalidTarget { .. }
+    ));
+    assert!(matches!(
+        err(4, quantity(1.0, None)),
+        ScaleError::InvalidTarget { .. }
+    ));
+    assert!(matches!(
+        err(0, quantity(0.0, Some("g"))),
+        ScaleError::InvalidTarget { .. }
+    ));
+}
+
+#[test]
+fn to_yield() {
+    let parser = CooklangParser::default();
+    let loaves = parser
+        .parse(">> yield: 1\n@flour{500*%g}", "test")
+        .into_output()
+        .unwrap();
+    let target = ScaleTarget::for_yield(&loaves, quantity(2.0, None), parser.converter()).unwrap();
+    let scaled = loaves.clone().scale_to(target, parser.converter());
+    assert_eq!(
+        number(scaled.ingredients[0].quantity.as_ref().unwrap()),
+        1.0
+    );
+    assert!(matches!(
+        scaled.scaled_data().unwrap().target.kind(),
+        ScaleTargetKind::Yield
+    ));
+    assert!(matches!(
+        ScaleTarget::for_yield(&loaves, quantity(2.0, Some("l")), parser.converter()),
+        Err(ScaleError::IncompatibleTarget { .. })
+    ));
+
+    let sauce = parser
+        .parse(">> yield: 500%ml\n@tomatoes{800*%g}", "test")
+        .into_output()
+        .unwrap();
+    let target =
+        ScaleTarget::for_yield(&sauce, quantity(0.25, Some("l")), parser.converter()).unwrap();
+    assert_eq!(target.factor(), 0.5);
+    let scaled = sauce.scale_to(target, parser.converter());
+    assert_eq!(scaled.scaled_yield().unwrap().to_string(), "250 ml");
+    // no servings declared, so the base is 1
+    assert_eq!(scaled.scaled_data().unwrap().target.target_servings(), 1);
+
+    let no_yield = parser
+        .parse("@flour{500*%g}", "test")
+        .into_output()
+        .unwrap();
+    assert!(matches!(
+        ScaleTarget::for_yield(&no_yield, quantity(2.0, None), parser.converter()),
+        Err(ScaleError::InvalidTarget { .. })
+    ));
+}
+
+#[test]
+fn rescale() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            ">> servings: 2|4\n@flour{500*%g} @salt{1|2%tsp} ~{10%min} 200 °C",
+            "test",
+        )
+        .into_output()
+        .unwrap();
+
+    let scaled = recipe.clone().scale(4, parser.converter());
+    assert_eq!(
+        number(scaled.ingredients[0].quantity.as_ref().unwrap()),
+        1.0
+    );
+
+    let scaled = scaled.rescale(8, parser.converter());
+    let flour = scaled.ingredients[0].quantity.as_ref().unwrap();
+    assert_eq!((number(flour), unit(flour)), (2.0, "kg"));
+    let data = scaled.scaled_data().unwrap();
+    assert_eq!(data.target.target_servings(), 8);
+    assert_eq!(data.target.requested_servings(), Some(8));
+    assert_eq!(data.target.factor(), 4.0);
+    assert!(matches!(
+        data.ingredients[..],
+        [ScaleOutcome::Scaled, ScaleOutcome::Error(_)]
+    ));
+
+    let scaled = scaled.rescale(2, parser.converter());
+    assert!(scaled.is_default_scaled());
+    assert_eq!(
+        scaled.ingredients,
+        recipe.clone().default_scale().ingredients
+    );
+
+    let target = ScaleTarget::by_factor(2.0);
+    let scaled = scaled.unscale().scale_to(target, parser.converter());
+    assert_eq!(
+        number(scaled.ingredients[1].quantity.as_ref().unwrap()),
+        2.0
+    );
+
+    // conversions are lost too
+    let mut scaled = scaled;
+    scaled.convert(System::Imperial, parser.converter());
+    assert_eq!(unit(scaled.ingredients[0].quantity.as_ref().unwrap()), "lb");
+    assert_eq!(scaled.unscale(), recipe);
+}
+
+#[test]
+fn yield_metadata() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(">> yield: 1.5 l\n@stock{1*%l} @carrots{300*%g}", "test")
+        .into_output()
+        .unwrap();
+    assert_eq!(recipe.metadata.yields, Some(quantity(1.5, Some("l"))));
+    let target =
+        ScaleTarget::for_yield(&recipe, quantity(3.0, Some("l")), parser.converter()).unwrap();
+    let scaled = recipe.scale_to(target, parser.converter());
+    let stock = scaled.ingredients[0].quantity.as_ref().unwrap();
+    assert_eq!((number(stock), unit(stock)), (2.0, "l"));
+
+    let res = parser.parse(">> yield: some soup\n@stock{1*%l}", "test");
+    assert!(res.is_valid());
+    assert_eq!(res.warnings().len(), 1);
+    let recipe = res.into_output().unwrap();
+    assert_eq!(recipe.metadata.yields, None);
+    assert_eq!(recipe.metadata.map["yield"], "some soup");
+}
@@ -0,0 +1,299 @@
+#![cfg(feature = "schema_org")]
+
+use cooklang::{
+    model::{Item, Section},
+    quantity::format::QuantityFormatter,
+    schema_org::{self, ImportError},
+    Converter, CooklangParser, Extensions,
+};
+use indoc::indoc;
+use serde_json::json;
+
+const JSON_LD: &str = r#"{
+    "@context": "https://schema.org",
+    "@graph": [
+        { "@type": "WebSite", "name": "Some site" },
+        {
+            "@type": ["Recipe", "NewsArticle"],
+            "name": "Pancakes",
+            "description": "Fluffy &amp; easy",
+            "author": { "@type": "Person", "name": "Jane", "url": "https://example.com/jane" },
+            "keywords": "Breakfast, Sweet, breakfast",
+            "recipeYield": ["8", "8 pancakes"],
+            "prepTime": "PT10M",
+            "cookTime": "PT20M",
+            "totalTime": "PT30M",
+            "recipeIngredient": [
+                "250 g flour",
+                "2 eggs",
+                "500 ml milk (cold)",
+                "a pinch of salt"
+            ],
+            "recipeInstructions": [
+                {
+                    "@type": "HowToSection",
+                    "name": "Batter",
+                    "itemListElement": [
+                        { "@type": "HowToStep", "text": "Mix the flour with the eggs." },
+                        { "@type": "HowToStep", "text": "Add the <b>milk</b> slowly." }
+                    ]
+                },
+                {
+                    "@type": "HowToSection",
+                    "name": "Cooking",
+                    "itemListElement": [
+                        { "@type": "HowToStep", "text": "Cook in a pan." }
+                    ]
+                }
+            ]
+        }
+    ]
+}"#;
+
+fn step_texts(section: &Section) -> Vec<String> {
+    section
+        .steps
+        .iter()
+        .map(|step| {
+            step.items
+                .iter()
+                .map(|i| match i {
+                    Item::Text { value } => value.as_str(),
+                    _ => "",
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn import_graph() {
+    let recipe = schema_org::from_json_ld(JSON_LD, &Converter::default()).unwrap();
+    assert_eq!(recipe.name, "Pancakes");
+
+    let meta = &recipe.metadata;
+    assert_eq!(meta.description.as_deref(), Some("Fluffy & easy"));
+    let author = meta.author.as_ref().unwrap();
+    assert_eq!(author.name(), Some("Jane"));
+    assert_eq!(
+        author.url().map(|u| u.as_str()),
+        Some("https://example.com/jane")
+    );
+    assert_eq!(meta.tags, ["breakfast", "sweet"]);
+    assert_eq!(meta.servings.as_deref(), Some(&[8][..]));
+    assert_eq!(
+        meta.map.get("yield").map(String::as_str),
+        Some("8 pancakes")
+    );
+    assert_eq!(meta.map.get("prep time").map(String::as_str), Some("10min"));
+    assert_eq!(meta.map.get("cook time").map(String::as_str), Some("20min"));
+
+    let names = recipe
+        .ingredients
+        .iter()
+        .map(|i| i.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["flour", "eggs", "milk", "a pinch of salt"]);
+    assert_eq!(recipe.ingredients[2].note.as_deref(), Some("cold"));
+    let q = recipe.ingredients[2].quantity.as_ref().unwrap();
+    assert_eq!(q.to_string(), "500 ml");
+    assert!(recipe.ingredients[3].quantity.is_none());
+
+    assert_eq!(recipe.sections.len(), 2);
+    assert_eq!(recipe.sections[0].name.as_deref(), Some("Batter"));
+    assert_eq!(recipe.sections[1].name.as_deref(), Some("Cooking"));
+    assert_eq!(
+        step_texts(&recipe.sections[0]),
+        ["Mix the flour with the eggs.", "Add the milk slowly."]
+    );
+}
+
+#[test]
+fn import_html() {
+    let html = format!(
+        indoc! {r#"
+            <html><head>
+            <script type="application/ld+json">{{ "@type": "Organization" }}</script>
+            <script type="application/ld+json">{}</script>
+            </head></html>
+        "#},
+        JSON_LD
+    );
+    let recipe = schema_org::from_html(&html, &Converter::default()).unwrap();
+    assert_eq!(recipe.name, "Pancakes");
+
+    assert!(matches!(
+        schema_org::from_html("<html></html>", &Converter::default()),
+        Err(ImportError::NoRecipe)
+    ));
+}
+
+#[test]
+fn instructions_as_text() {
+    let json = r#"{
+        "@type": "Recipe",
+        "name": "Toast",
+        "recipeInstructions": "Toast the bread.\nButter it."
+    }"#;
+    let recipe = schema_org::from_json_ld(json, &Converter::default()).unwrap();
+    assert_eq!(recipe.sections.len(), 1);
+    assert_eq!(
+        step_texts(&recipe.sections[0]),
+        ["Toast the bread.", "Butter it."]
+    );
+}
+
+#[test]
+fn errors() {
+    let converter = Converter::default();
+    assert!(matches!(
+        schema_org::from_json_ld(r#"{ "@type": "Person" }"#, &converter),
+        Err(ImportError::NoRecipe)
+    ));
+    assert!(matches!(
+        schema_org::from_json_ld("{", &converter),
+        Err(ImportError::Json(_))
+    ));
+}
+
+#[test]
+fn roundtrip_cooklang() {
+    let parser = CooklangParser::default();
+    let recipe = schema_org::from_json_ld(JSON_LD, parser.converter()).unwrap();
+    let src = recipe.to_cooklang(Extensions::all());
+    let parsed = parser.parse(&src, "Pancakes").into_output().unwrap();
+    assert_eq!(parsed.ingredients, recipe.ingredients);
+    assert_eq!(parsed.sections, recipe.sections);
+    assert_eq!(parsed.metadata.map, recipe.metadata.map);
+}
+
+const COOKLANG: &str = indoc! {r#"
+    >> servings: 2|4
+    >> author: Jane <https://example.com/jane>
+    >> source: https://example.com/pancakes
+    >> tags: breakfast, sweet
+    >> prep time: 10 min
+    >> cook time: 1h 5min
+    >> yield: 6 pancakes
+
+    = Batter
+    Mix @flour{250*%g}(sifted) with @eggs{2|4} and @milk{250*%ml}.
+
+    Add more @&milk{250*%ml} and a pinch of @-salt.
+
+    = Cooking
+    > Use a nonstick pan.
+
+    Cook in a #pan{} for ~{2%min} each at 180 ºC.
+"#};
+
+#[test]
+fn export() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(COOKLANG, "Pancakes").into_output().unwrap();
+    assert_eq!(recipe.inline_quantities.len(), 1);
+    let recipe = recipe.scale(4, parser.converter());
+    let json = schema_org::to_json_ld(&recipe, parser.converter());
+    assert_eq!(
+        json,
+        json!({
+            "@context": "https://schema.org",
+            "@type": "Recipe",
+            "name": "Pancakes",
+            "author": {
+                "@type": "Person",
+                "name": "Jane",
+                "url": "https://example.com/jane"
+            },
+            "isBasedOn": "https://example.com/pancakes",
+            "keywords": "breakfast, sweet",
+            "recipeYield": ["4", "12 pancakes"],
+            "prepTime": "PT10M",
+            "cookTime": "PT1H5M",
+            "totalTime": "PT1H15M",
+            "recipeIngredient": ["500 g flour, sifted", "4 eggs", "1 l milk"],
+            "recipeInstructions": [
+                {
+                    "@type": "HowToSection",
+                    "name": "Batter",
+                    "itemListElement": [
+                        { "@type": "HowToStep", "text": "Mix flour with eggs and milk." },
+                        { "@type": "HowToStep", "text": "Add more milk and a pinch of salt." }
+                    ]
+                },
+                {
+                    "@type": "HowToSection",
+                    "name": "Cooking",
+                    "itemListElement": [
+                        { "@type": "HowToTip", "text": "Use a nonstick pan." },
+                        { "@type": "HowToStep", "text": "Cook in a pan for 2 min each at 180 ºC." }
+                    ]
+                }
+            ]
+        })
+    );
+}
+
+#[test]
+fn export_with_formatter() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse(
+            ">> yield: 1.5 loaf\nAdd @milk{1.5%cup} and wait ~{2.5%min}.",
+            "Milky",
+        )
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let formatter = QuantityFormatter::cooking();
+    let json = schema_org::to_json_ld_with_formatter(&recipe, parser.converter(), &formatter);
+    assert_eq!(json["recipeYield"], json!(["1 ½ loaf"]));
+    assert_eq!(json["recipeIngredient"], json!(["1 ½ c milk"]));
+    assert_eq!(
+        json["recipeInstructions"],
+        json!([{ "@type": "HowToStep", "text": "Add milk and wait 2 ½ min." }])
+    );
+}
+
+#[test]
+fn export_default_scaled() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(COOKLANG, "Pancakes").into_output().unwrap();
+    let json = schema_org::to_json_ld(&recipe.default_scale(), parser.converter());
+    assert_eq!(json["recipeYield"], json!(["2", "6 pancakes"]));
+    assert_eq!(
+        json["recipeIngredient"],
+        json!(["250 g flour, sifted", "2 eggs", "500 ml milk"])
+    );
+
+    let recipe = parser
+        .parse("Just @salt.", "Salt")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let json = schema_org::to_json_ld(&recipe, parser.converter());
+    assert!(json.get("recipeYield").is_none());
+    assert_eq!(
+        json["recipeInstructions"],
+        json!([{ "@type": "HowToStep", "text": "Just salt." }])
+    );
+}
+
+#[test]
+fn export_import() {
+    let parser = CooklangParser::default();
+    let recipe = parser.parse(COOKLANG, "Pancakes").into_output().unwrap();
+    let json = schema_org::to_json_ld(&recipe.default_scale(), parser.converter());
+    let imported = schema_org::from_value(&json, parser.converter()).unwrap();
+    assert_eq!(imported.name, "Pancakes");
+    assert_eq!(imported.metadata.servings.as_deref(), Some(&[2][..]));
+    assert_eq!(imported.metadata.tags, ["breakfast", "sweet"]);
+    let names = imported
+        .ingredients
+        .iter()
+        .map(|i| i.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["flour", "eggs", "milk"]);
+    assert_eq!(imported.ingredients[0].note.as_deref(), Some("sifted"));
+    assert_eq!(imported.sections.len(), 2);
+}
@@ -22,3 +22,171 @@ fn serde_test() {
 
     assert_eq!(recipe, deserialized);
 }
+
+#[cfg(feature = "interchange")]
+mod interchange {
+    use cooklang::{
+        interchange::{self, Document, InterchangeError, VERSION},
+        scale::Scaled,
+        CooklangParser, Recipe, ScaledRecipe,
+    };
+    use indoc::indoc;
+    use serde_json::{json, Value};
+
+    const RECIPE: &str = indoc! {"
+        >> servings: 2|4
+        >> tags: bread
+
+        Mix @flour{500%g} with @water{300%ml}, @salt{1|2%tsp} and @yeast{7%g}.
+        Use a #bowl{} and wait ~{1%hour}.
+
+        Bake at 220 ºC.
+    "};
+
+    const V0_RECIPE: &str = include_str!("interchange/v0_recipe.json");
+    const V0_DEFAULT_SCALED: &str = include_str!("interchange/v0_default_scaled.json");
+    const V0_SCALED: &str = include_str!("interchange/v0_scaled.json");
+
+    fn parse() -> (Recipe, CooklangParser) {
+        let parser = CooklangParser::default();
+        let recipe = parser.parse(RECIPE, "bread").into_output().unwrap();
+        (recipe, parser)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (recipe, parser) = parse();
+        let json = interchange::to_string(&recipe).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], VERSION);
+        assert_eq!(interchange::from_str::<()>(&json).unwrap(), recipe);
+
+        let scaled = recipe.scale(4, parser.converter());
+        let value = interchange::to_value(&scaled).unwrap();
+        let back: ScaledRecipe = interchange::from_value(value.clone()).unwrap();
+        assert_eq!(interchange::to_value(&back).unwrap(), value);
+
+        let document: Document<Scaled> = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&document).unwrap(), value);
+    }
+
+    #[test]
+    fn migrate_v0() {
+        let (recipe, parser) = parse();
+
+        let migrated: Recipe = interchange::from_str(V0_RECIPE).unwrap();
+        assert_eq!(migrated, recipe);
+
+        let migrated: ScaledRecipe = interchange::from_str(V0_DEFAULT_SCALED).unwrap();
+        assert!(migrated.is_default_scaled());
+        let default_scaled = recipe.clone().default_scale();
+        assert_eq!(migrated.ingredients, default_scaled.ingredients);
+        assert_eq!(migrated.unscale().ingredients, default_scaled.ingredients);
+
+        let migrated: ScaledRecipe = interchange::from_str(V0_SCALED).unwrap();
+        let data = migrated.scaled_data().unwrap();
+        assert_eq!(data.target.target_servings(), 4);
+        assert_eq!(data.target.factor(), 2.0);
+        assert_eq!(data.target.index(), Some(1));
+        let scaled = recipe.clone().scale(4, parser.converter());
+        assert_eq!(migrated.ingredients, scaled.ingredients);
+        // the quantities before scaling are lost
+        assert_eq!(migrated.unscale().ingredients, scaled.ingredients);
+
+        let value = interchange::migrate(serde_json::from_str(V0_SCALED).unwrap()).unwrap();
+        assert_eq!(value["version"], VERSION);
+        assert_eq!(value["data"]["target"]["kind"]["type"], "servings");
+    }
+
+    #[test]
+    fn migrate_current_without_version() {
+        let (recipe, parser) = parse();
+        let scaled = recipe.scale(4, parser.converter());
+        let value = serde_json::to_value(&scaled).unwrap();
+        let back: ScaledRecipe = interchange::from_value(value).unwrap();
+        assert_eq!(
+            interchange::to_value(&back).unwrap(),
+            interchange::to_value(&scaled).unwrap()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            interchange::from_value::<()>(json!([])),
+            Err(InterchangeError::NotAnObject)
+        ));
+        assert!(matches!(
+            interchange::from_value::<()>(json!({ "version": "1" })),
+            Err(InterchangeError::InvalidVersion)
+        ));
+        assert!(matches!(
+            interchange::from_value::<()>(json!({ "version": VERSION + 1 })),
+            Err(InterchangeError::UnsupportedVersion { version }) if version == VERSION as u64 + 1
+        ));
+        let mut broken: Value = serde_json::from_str(V0_SCALED).unwrap();
+        broken["data"]["target"] = Value::Null;
+        assert!(matches!(
+            interchange::from_value::<Scaled>(broken),
+            Err(InterchangeError::Migration { from: 0, .. })
+        ));
+        // a scaled recipe is not a recipe
+        assert!(matches!(
+            interchange::from_str::<()>(V0_SCALED),
+            Err(InterchangeError::Json(_))
+        ));
+        assert!(serde_json::from_value::<Document>(json!({ "version": 100 })).is_err());
+    }
+
+    #[cfg(feature = "schema")]
+    mod schema {
+        use super::*;
+
+        fn check_schema<D: schemars::JsonSchema>(path: &str) -> jsonschema::JSONSchema {
+            let generated = interchange::json_schema::<D>();
+            let generated = serde_json::to_string_pretty(&generated).unwrap() + "\n";
+            let path = format!("{}/schema/{path}", env!("CARGO_MANIFEST_DIR"));
+            if std::env::var_os("COOKLANG_UPDATE_SCHEMA").is_some() {
+                std::fs::write(&path, &generated).unwrap();
+            }
+            let stored = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(
+                stored, generated,
+                "{path} is outdated, run the tests with COOKLANG_UPDATE_SCHEMA=1 to update it. \
+                If the format changed, increase the version and add a migration."
+            );
+            jsonschema::JSONSchema::compile(&serde_json::from_str(&stored).unwrap()).unwrap()
+        }
+
+        fn assert_valid(schema: &jsonschema::JSONSchema, document: &Value) {
+            if let Err(errors) = schema.validate(document) {
+                let errors = errors.map(|e| e.to_string()).collect::<Vec<_>>();
+                panic!("invalid document: {errors:#?}");
+            }
+        }
+
+        #[test]
+        fn recipe() {
+            let schema = check_schema::<()>("recipe.json");
+            let (recipe, _) = parse();
+            assert_valid(&schema, &interchange::to_value(&recipe).unwrap());
+            let migrated = interchange::migrate(serde_json::from_str(V0_RECIPE).unwrap()).unwrap();
+            assert_valid(&schema, &migrated);
+            assert!(!schema.is_valid(&serde_json::from_str(V0_RECIPE).unwrap()));
+        }
+
+        #[test]
+        fn scaled_recipe() {
+            let schema = check_schema::<Scaled>("scaled_recipe.json");
+            let (recipe, parser) = parse();
+            let scaled = recipe.clone().scale(4, parser.converter());
+            assert_valid(&schema, &interchange::to_value(&scaled).unwrap());
+            let scaled = recipe.default_scale();
+            assert_valid(&schema, &interchange::to_value(&scaled).unwrap());
+            for v0 in [V0_SCALED, V0_DEFAULT_SCALED] {
+                let migrated = interchange::migrate(serde_json::from_str(v0).unwrap()).unwrap();
+                assert_valid(&schema, &migrated);
+            }
+        }
+    }
+}
@@ -0,0 +1,567 @@
+use cooklang::{
+    convert::{ConverterBuilderError, PhysicalQuantity, System, UnitsFile},
+    Converter, CooklangParser, Extensions, Quantity, QuantityValue, TotalQuantity, UnitInfo,
+};
+use indoc::indoc;
+
+mod common;
+use common::q;
+
+#[test]
+fn bundled_count() {
+    let converter = Converter::bundled();
+    let dozen = converter.all_units().find(|u| u.symbol() == "doz").unwrap();
+    assert_eq!(dozen.physical_quantity, PhysicalQuantity::Count);
+
+    assert_eq!(
+        converter.convert(&q(2.0, "dozen"), "pcs").unwrap(),
+        q(24.0, "pc")
+    );
+
+    let mut quantity = q(36.0, "pieces");
+    quantity.fit(&converter).unwrap();
+    assert_eq!(quantity, q(3.0, "doz"));
+}
+
+#[test]
+fn group_count() {
+    let parser = CooklangParser::default();
+    let recipe = parser
+        .parse("@eggs{1%dozen} @&eggs{6%pieces}", "test")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let grouped = recipe.ingredients[0].group_quantities(&recipe.ingredients, parser.converter());
+    assert_eq!(grouped.total(), TotalQuantity::Single(q(1.5, "doz")));
+}
+
+#[test]
+fn custom_count_units() {
+    let file: UnitsFile = toml::from_str(indoc! {r#"
+        [[quantity]]
+        quantity = "count"
+        best = ["clove", "head"]
+        units = [
+            { names = ["clove", "cloves"], symbols = [], ratio = 1 },
+            { names = ["head", "heads"], symbols = [], ratio = 10 },
+        ]
+    "#})
+    .unwrap();
+    // no other quantity is needed
+    let converter = Converter::builder()
+        .with_units_file(file)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert_eq!(converter.unit_count(), 2);
+
+    let parser = CooklangParser::new(Extensions::all(), converter);
+    let recipe = parser
+        .parse("@garlic{1%head} @&garlic{5%cloves}", "test")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let total = recipe.ingredients[0]
+        .total_quantity(&recipe.ingredients, parser.converter())
+        .unwrap();
+    assert_eq!(total, Some(q(1.5, "head")));
+}
+
+#[test]
+fn us_customary_and_imperial() {
+    let converter = Converter::bundled();
+    let ml = |q: &Quantity| converter.convert(q, "ml").unwrap().to_string();
+    assert_eq!(ml(&q(1.0, "cup")), "284.131 ml");
+    assert_eq!(ml(&q(1.0, "US cup")), "236.588 ml");
+    assert_eq!(ml(&q(1.0, "tbsp")), "17.758 ml");
+    assert_eq!(ml(&q(1.0, "US tbsp")), "14.787 ml");
+
+    let cup = converter.all_units().find(|u| u.symbol() == "c").unwrap();
+    assert_eq!(cup.system, Some(System::Imperial));
+    let us_cup = converter
+        .all_units()
+        .find(|u| u.symbol() == "US cup")
+        .unwrap();
+    assert_eq!(us_cup.system, Some(System::UsCustomary));
+    assert_eq!(
+        "us_customary".parse::<System>().unwrap(),
+        System::UsCustomary
+    );
+}
+
+#[test]
+fn bare_units_us_customary() {
+    let converter = Converter::builder()
+        .with_bundled_units()
+        .unwrap()
+        .with_bare_units_system(System::UsCustomary)
+        .finish()
+        .unwrap();
+    let ml = |q: &Quantity| converter.convert(q, "ml").unwrap().to_string();
+    assert_eq!(ml(&q(1.0, "cup")), "236.588 ml");
+    assert_eq!(ml(&q(1.0, "c")), "236.588 ml");
+    assert_eq!(ml(&q(1.0, "tbsp")), "14.787 ml");
+    assert_eq!(ml(&q(1.0, "US tsp")), "4.929 ml");
+
+    let parser = CooklangParser::new(Extensions::all(), converter);
+    let recipe = parser
+        .parse("@milk{1%cup}", "test")
+        .into_output()
+        .unwrap()
+        .default_scale();
+    let quantity = recipe.ingredients[0].quantity.as_ref().unwrap();
+    let unit = quantity
+        .unit()
+        .unwrap()
+        .unit_info_or_parse(parser.converter());
+    assert!(matches!(unit, UnitInfo::Known(u) if u.symbol() == "US cup"));
+    assert_eq!(
+        parser
+            .converter()
+            .convert(quantity, "ml")
+            .unwrap()
+            .to_string(),
+        "236.588 ml"
+    );
+}
+
+#[test]
+fn convert_recipe_to_us_customary() {
+    let parser = CooklangParser::default();
+    let mut recipe = parser
+        .parse("@milk{500%ml} @flour{1%kg} @butter{2%tbsp}", "test")
+        .into_output()
+        .unwrap();
+    let errors = recipe.convert(System::UsCustomary, parser.converter());
+    assert!(errors.is_empty());
+    let quantities = recipe
+        .ingredients
+        .iter()
+        .map(|i| i.quantity.as_ref().unwrap().to_string())
+        .collect::<Vec<_>>();
+    // mass units are shared with imperial
+    assert_eq!(quantities, ["2.113 US cup", "2.205 lb", "2.402 US tbsp"]);
+
+    recipe.convert(System::Imperial, parser.converter());
+    assert_eq!(
+        recipe.ingredients[0].quantity.as_ref().unwrap().to_string(),
+        "1.76 c"
+    );
+}
+
+#[test]
+fn us_customary_best_units() {
+    let units = |best: &str| -> UnitsFile {
+        toml::from_str(&format!(
+            indoc! {r#"
+                [[quantity]]
+                quantity = "mass"
+                best = {}
+                [quantity.units]
+                metric = [{{ names = ["gram"], symbols = ["g"], ratio = 1 }}]
+                imperial = [{{ names = ["ounce"], symbols = ["oz"], ratio = 28.35 }}]
+            "#},
+            best
+        ))
+        .unwrap()
+    };
+    let build = |best| {
+        Converter::builder()
+            .with_units_file(units(best))
+            .unwrap()
+            .finish()
+    };
+
+    let converter = build(r#"{ metric = ["g"], imperial = ["oz"] }"#).unwrap();
+    assert_eq!(
+        converter
+            .convert(&q(56.7, "g"), System::UsCustomary)
+            .unwrap(),
+        q(2.0, "oz")
+    );
+    assert!(build(r#"{ metric = ["g"], imperial = ["oz"], us_customary = ["oz"] }"#).is_ok());
+    assert!(matches!(
+        build(r#"{ metric = ["g"], imperial = ["oz"], us_customary = ["g"] }"#),
+        Err(ConverterBuilderError::IncorrectUnitSystem { .. })
+    ));
+}
+
+#[test]
+fn quantity_formatter() {
+    use cooklang::quantity::format::{NumberFormat, QuantityFormatter};
+
+    let converter = Converter::bundled();
+    let formatter = QuantityFormatter::cooking();
+    let fmt = |value: QuantityValue, unit: &str| {
+        Quantity::new(value, Some(unit.into())).format(&formatter, &converter)
+    };
+    let fixed = |n: f64| QuantityValue::Fixed { value: n.into() };
+
+    assert_eq!(fmt(fixed(0.333), "cups"), "⅓ cups");
+    assert_eq!(fmt(fixed(1.667), "tbsp"), "1 ¾ tbsp");
+    // any name of the unit
+    assert_eq!(fmt(fixed(0.1), "teaspoons"), "¼ teaspoons");
+    assert_eq!(fmt(fixed(236.588), "g"), "237 g");
+    assert_eq!(fmt(fixed(180.4), "°C"), "180 °C");
+    assert_eq!(fmt(fixed(0.3), "cup"), "0.3 cup");
+    assert_eq!(
+        fmt(
+            QuantityValue::Fixed {
+                value: (0.49..=0.51).into()
+            },
+            "cup"
+        ),
+        "½ cup"
+    );
+    assert_eq!(
+        fmt(
+            QuantityValue::ByServings {
+                values: vec![0.5.into(), 1.5.into()]
+            },
+            "handful"
+        ),
+        "½|1 ½ handful"
+    );
+
+    let formatter = QuantityFormatter::new()
+        .with_quantity(PhysicalQuantity::Mass, NumberFormat::decimal(1))
+        .with_unit("handful", NumberFormat::fraction(&[2]).ascii());
+    let total = TotalQuantity::Many(vec![
+        Quantity::new(fixed(1.26), Some("kg".into())),
+        Quantity::new(fixed(1.5), Some("handful".into())),
+        Quantity::new(fixed(1.0 / 3.0), Some("l".into())),
+    ]);
+    assert_eq!(
+        total.format(&formatter, &converter),
+        "1.3 kg, 1 1/2 handful, 0.333 l"
+    );
+    assert_eq!(total.to_string(), "1.26 kg, 1.5 handful, 0.333 l");
+}
+
+mod density {
+    use cooklang::{
+        convert::{ConverterBuilderError, DensityFile, System},
+        Converter, CooklangParser, Extensions, TotalQuantity,
+    };
+    use indoc::indoc;
+
+    use crate::common::q;
+
+    const DENSITIES: &str = indoc! {r#"
+        [ingredient.flour]
+        aliases = ["all purpose flour"]
+        density = 0.5
+
+        [ingredient.honey]
+        density = 1.4
+    "#};
+
+    fn converter(extra: &str) -> Converter {
+        let file: DensityFile = toml::from_str(&format!("{extra}\n{DENSITIES}")).unwrap();
+        Converter::builder()
+            .with_bundled_units()
+            .unwrap()
+            .with_density_file(file)
+            .unwrap()
+            .finish()
+            .unwrap()
+    }
+
+    #[test]
+    fn lookup() {
+        let converter = converter("");
+        assert_eq!(converter.density("flour").unwrap().value, 0.5);
+        let alias = converter.density(" All Purpose Flour").unwrap();
+        assert_eq!(&*alias.ingredient, "flour");
+        assert!(converter.density("sugar").is_none());
+    }
+
+    #[test]
+    fn layers_override() {
+        let file: DensityFile = toml::from_str("[ingredient.Flour]\ndensity = 0.6").unwrap();
+        let converter = Converter::builder()
+            .with_bundled_units()
+            .unwrap()
+            .with_density_file(toml::from_str(DENSITIES).unwrap())
+            .unwrap()
+            .with_density_file(file)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(converter.density("flour").unwrap().value, 0.6);
+        // the alias of the first layer is still there
+        assert_eq!(converter.density("all purpose flour").unwrap().value, 0.5);
+    }
+
+    #[test]
+    fn builder_errors() {
+        let file: DensityFile = toml::from_str("[ingredient.flour]\ndensity = 0").unwrap();
+        let err = Converter::builder().with_density_file(file).unwrap_err();
+        assert!(matches!(err, ConverterBuilderError::InvalidDensity { .. }));
+
+        let file: DensityFile = toml::from_str("prefer = \"time\"").unwrap();
+        let err = Converter::builder().with_density_file(file).unwrap_err();
+        assert!(matches!(
+            err,
+            ConverterBuilderError::InvalidDensityPreference { .. }
+        ));
+    }
+
+    #[test]
+    fn convert_ingredient() {
+        let converter = converter("");
+
+        let (grams, density) = converter
+            .convert_ingredient(&q(200.0, "ml"), "g", "flour")
+            .unwrap();
+        assert_eq!(grams, q(100.0, "g"));
+        assert_eq!(&*density.unwrap().ingredient, "flour");
+
+        let (ml, density) = converter
+            .convert_ingredient(&q(1.4, "kg"), "ml", "honey")
+            .unwrap();
+        assert_eq!(ml, q(1000.0, "ml"));
+        assert!(density.is_some());
+
+        // same physical quantity, density not used
+        let (kg, density) = converter
+            .convert_ingredient(&q(1000.0, "g"), "kg", "flour")
+            .unwrap();
+        assert_eq!(kg, q(1.0, "kg"));
+        assert!(density.is_none());
+
+        assert!(converter
+            .convert_ingredient(&q(200.0, "ml"), "g", "sugar")
+            .is_err());
+    }
+
+    #[test]
+    fn try_add() {
+        let converter = converter("");
+        let (sum, density) = q(100.0, "g")
+            .try_add_ingredient(&q(200.0, "ml"), "flour", &converter)
+            .unwrap();
+        assert_eq!(sum, q(200.0, "g"));
+        assert!(density.is_some());
+
+        assert!(q(100.0, "g").try_add(&q(200.0, "ml"), &converter).is_err());
+        assert!(q(100.0, "g")
+            .try_add_ingredient(&q(200.0, "ml"), "sugar", &converter)
+            .is_err());
+    }
+
+    #[test]
+    fn grouped_quantities() {
+        let parser = CooklangParser::new(Extensions::all(), converter(""));
+        let recipe = parser
+            .parse(
+                "@flour{0.5%l} @&flour{150%g} @sugar{0.5%l} @&sugar{150%g}",
+                "test",
+            )
+            .into_output()
+            .unwrap()
+            .default_scale();
+
+        let flour = recipe.ingredients[0].group_quantities(&recipe.ingredients, parser.converter());
+        assert_eq!(flour.total(), TotalQuantity::Single(q(800.0, "ml")));
+        assert_eq!(flour.densities().len(), 1);
+
+        let sugar = recipe.ingredients[2].group_quantities(&recipe.ingredients, parser.converter());
+        assert_eq!(
+            sugar.total(),
+            TotalQuantity::Many(vec![q(500.0, "ml"), q(150.0, "g")])
+        );
+        assert!(sugar.densities().is_empty());
+
+        let total = recipe.ingredients[0]
+            .total_quantity(&recipe.ingredients, parser.converter())
+            .unwrap();
+        assert_eq!(total, Some(q(800.0, "ml")));
+
+        let (total, densities) = recipe.ingredients[0]
+            .total_quantity_with_densities(&recipe.ingredients, parser.converter())
+            .unwrap();
+        assert_eq!(total, Some(q(800.0, "ml")));
+        assert_eq!(densities.len(), 1);
+        assert_eq!(&*densities[0].ingredient, "flour");
+    }
+
+    #[test]
+    fn ingredient_list() {
+        let parser = CooklangParser::new(Extensions::all(), converter(""));
+        let recipe = parser
+            .parse("@flour{1%l}\n\n@flour{500%g}", "test")
+            .into_output()
+            .unwrap()
+            .default_scale();
+        let mut list = cooklang::ingredient_list::IngredientList::new();
+        list.add_recipe(&recipe, parser.converter());
+        let (name, flour) = list.iter().next().unwrap();
+        assert_eq!(name, "flour");
+        assert_eq!(flour.total(), TotalQuantity::Single(q(2.0, "l")));
+    }
+
+    #[test]
+    fn recipe_convert() {
+        let parser = CooklangParser::new(Extensions::all(), converter("prefer = \"mass\""));
+        let mut recipe = parser
+            .parse("@flour{2%cups} @milk{1%cup} @honey{1%kg}", "test")
+            .into_output()
+            .unwrap();
+        let conversion = recipe.convert_with_densities(System::Metric, parser.converter());
+        assert!(conversion.errors.is_empty());
+        assert_eq!(conversion.densities.len(), 1);
+        assert_eq!(conversion.densities[0].0, 0);
+
+        let quantities = recipe
+            .ingredients
+            .iter()
+            .map(|i| i.quantity.as_ref().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(quantities, ["284.131 g", "284.131 ml", "1 kg"]);
+    }
+
+    #[test]
+    fn recipe_convert_without_preference() {
+        let parser = CooklangParser::new(Extensions::all(), converter(""));
+        let mut recipe = parser
+            .parse("@flour{2%cups}", "test")
+            .into_output()
+            .unwrap();
+        let conversion = recipe.convert_with_densities(System::Metric, parser.converter());
+        assert!(conversion.densities.is_empty());
+        assert_eq!(
+            recipe.ingredients[0].quantity.as_ref().unwrap().to_string(),
+            "568.261 ml"
+        );
+    }
+}
+
+mod fractions {
+    use cooklang::{
+        quantity::{
+            format::{NumberFormat, QuantityFormatter},
+            Fraction,
+        },
+        CooklangParser, Quantity, QuantityValue, TotalQuantity, Value,
+    };
+    use test_case::test_case;
+
+    use crate::common::value;
+
+    fn fraction(num: u32, den: u32) -> Value {
+        Value::Fraction {
+            value: Fraction::new(num, den).unwrap(),
+        }
+    }
+
+    #[test]
+    fn exact_sum() {
+        let parser = CooklangParser::default();
+        let recipe = parser
+            .parse("@milk{1/3%cup} @&milk{1/3%cup} @&milk{1%cup}", "test")
+            .into_output()
+            .unwrap()
+            .default_scale();
+        let grouped =
+            recipe.ingredients[0].group_quantities(&recipe.ingredients, parser.converter());
+        let TotalQuantity::Single(total) = grouped.total() else {
+            panic!("many quantities")
+        };
+        assert_eq!(value(&total), &fraction(5, 3));
+        assert_eq!(total.to_string(), "1 2/3 c");
+        assert_eq!(
+            total.format(&QuantityFormatter::new(), parser.converter()),
+            "1.667 c"
+        );
+        let formatter = QuantityFormatter::new().with_default(NumberFormat::fraction(&[3]));
+        assert_eq!(total.format(&formatter, parser.converter()), "1 ⅔ c");
+    }
+
+    #[test]
+    fn format_follows_unit() {
+        let parser = CooklangParser::default();
+        let recipe = parser
+            .parse("@x{1/2%g} @y{3/2%cup} @z{1/5%cup}", "test")
+            .into_output()
+            .unwrap()
+            .default_scale();
+        let formatter = QuantityFormatter::cooking();
+        let formatted = recipe
+            .ingredients
+            .iter()
+            .map(|i| {
+                i.quantity
+                    .as_ref()
+                    .unwrap()
+                    .format(&formatter, parser.converter())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(formatted, ["1 g", "1 ½ cup", "0.2 cup"]);
+    }
+
+    #[test_case(3 => fraction(1, 2); "simple factor")]
+    #[test_case(5 => fraction(5, 6); "other factor")]
+    #[test_case(4 => fraction(2, 3); "double")]
+    fn exact_scale(target: u32) -> Value {
+        let parser = CooklangParser::default();
+        let recipe = parser
+            .parse(">> servings: 2\n@eggs{1/3*}", "test")
+            .into_output()
+            .unwrap()
+            .scale(target, parser.converter());
+        value(recipe.ingredients[0].quantity.as_ref().unwrap()).clone()
+    }
+
+    #[test]
+    fn mixed_with_numbers() {
+        assert_eq!(fraction(1, 2).try_add(&1.0.into()).unwrap(), fraction(3, 2));
+        assert_eq!(
+            fraction(1, 2).try_add(&0.25.into()).unwrap(),
+            Value::Number { value: 0.75 }
+        );
+        assert_eq!(
+            fraction(1, 2).try_add(&(1.0..=2.0).into()).unwrap(),
+            Value::Range { value: 1.5..=2.5 }
+        );
+    }
+
+    #[test]
+    fn conversion_keeps_fraction_in_same_unit() {
+        let parser = CooklangParser::default();
+        let q = Quantity::new(
+            QuantityValue::Fixed {
+                value: fraction(3, 4),
+            },
+            Some("cups".into()),
+        );
+        let converted = parser.converter().convert(&q, "cup").unwrap();
+        assert_eq!(value(&converted), &fraction(3, 4));
+        let converted = parser.converter().convert(&q, "ml").unwrap();
+        assert!(matches!(value(&converted), Value::Number { .. }));
+    }
+
+    #[test]
+    fn serde() {
+        let value = fraction(3, 2);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"type":"fraction","value":{"num":3,"den":2}}"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        // reduced when deserialized
+        let json = r#"{"type":"fraction","value":{"num":2,"den":4}}"#;
+        assert_eq!(serde_json::from_str::<Value>(json).unwrap(), fraction(1, 2));
+        let json = r#"{"type":"fraction","value":{"num":2,"den":0}}"#;
+        assert!(serde_json::from_str::<Value>(json).is_err());
+    }
+
+    #[test]
+    fn writer() {
+        let parser = CooklangParser::default();
+        let recipe = parser
+            .parse("Add @milk{1 1/2%cup}.", "test")
+            .into_output()
+            .unwrap();
+        let out = recipe.to_cooklang(cooklang::Extensions::all());
+        assert_eq!(out, "Add @milk{1 1/2%cup}.\n");
+    }
+}
@@ -0,0 +1,421 @@
+use cooklang::{Converter, CooklangParser, Extensions};
+use indoc::indoc;
+use test_case::test_case;
+
+fn roundtrip(src: &str, extensions: Extensions) {
+    let parser = CooklangParser::new(extensions, Converter::default());
+    let result = parser.parse(src, "test");
+    assert!(!result.has_errors(), "source has errors:\n{src}");
+    let recipe = result.into_output().unwrap();
+    let written = recipe.to_cooklang(extensions);
+    let result = parser.parse(&written, "test");
+    assert!(
+        !result.has_errors(),
+        "written recipe has errors:\n{written}"
+    );
+    let again = result.into_output().unwrap();
+    assert_eq!(recipe, again, "written:\n{written}");
+}
+
+#[test_case(
+    indoc! {r#"
+        >> servings: 2|4
+        >> tags: one, two
+        >> zzz: last key
+        >> aaa: but not sorted
+
+        Add @flour{200|400%g} and @water{=150%ml} to a #bowl.
+        Wait ~{10%min} and then ~rest{1%h}.
+    "#}; "basic"
+)]
+#[test_case(
+    indoc! {r#"
+        = Dough
+        Mix @flour{500%g} and @&flour{} again.
+
+        == Topping ==
+        Add @cheese.
+    "#}; "sections"
+)]
+#[test_case(
+    indoc! {r#"
+        Before any section.
+
+        = Named
+        Inside.
+    "#}; "unnamed first section"
+)]
+#[test_case(
+    indoc! {r#"
+        > This is a text step with @not an ingredient.
+        A regular step.
+        > Another text
+    "#}; "text steps"
+)]
+#[test_case(
+    indoc! {r#"
+        Use @-salt{}, @?pepper{} and @@other recipe{2}.
+        Then @+salt{2%g} and #-pot.
+    "#}; "modifiers"
+)]
+#[test_case(
+    indoc! {r#"
+        Add @tipo zero flour|flour{100%g}(sifted).
+        Use a #big pan|pan(greased).
+    "#}; "alias and note"
+)]
+#[test_case(
+    indoc! {r#"
+        Mix @flour{} and @water{}.
+        Bake it.
+
+        Use @&(0)dough{} and @&(~1)dough{}.
+
+        = Other
+        Add @&(=0)stuff{}.
+    "#}; "intermediate references"
+)]
+#[test_case(
+    indoc! {r#"
+        >> servings: 1|2|3
+        >> [auto scale]: true
+
+        Add @a{1}, @b{1|2|3}, @c{=4%kg} and @d{2*%cups}.
+    "#}; "scaling markers"
+)]
+#[test_case(
+    indoc! {r#"
+        Values @a{1.5%kg}, @b{1/2%cup}, @c{2-3}, @d{some}, @e{1 and a half}.
+    "#}; "values"
+)]
+#[test_case(
+    indoc! {r#"
+        First line
+        continues here with @salt.
+        And more.
+
+        New step.
+    "#}; "multiline"
+)]
+#[test_case(
+    indoc! {r#"
+        >> [mode]: components
+        @flour{200%g}
+        #pot
+        >> [mode]: all
+
+        Put @&flour in the #&pot.
+    "#}; "components mode"
+)]
+#[test_case(
+    indoc! {r#"
+        Preheat the oven to 180 ºC and wait.
+    "#}; "inline quantities"
+)]
+#[test_case(
+    indoc! {r#"
+        Escape \@this, \#that and \~the other \\ one.
+        Not a \-- comment. And \[- not a block -].
+        Mail: user@example.com
+    "#}; "escapes"
+)]
+#[test_case(
+    indoc! {r#"
+        > text
+        continued @x
+
+        new
+    "#}; "multiline text step"
+)]
+fn writer_roundtrip(src: &str) {
+    roundtrip(src, Extensions::all());
+}
+
+#[test_case(Extensions::empty(); "none")]
+#[test_case(Extensions::COMPAT; "compat")]
+#[test_case(Extensions::all(); "all")]
+fn writer_roundtrip_extensions(extensions: Extensions) {
+    let src = indoc! {r#"
+        >> source: somewhere
+        Add @flour{200%g} to a #bowl{}.
+        Wait ~{10%min}.
+        Add @salt and @pepper{}.
+    "#};
+    roundtrip(src, extensions);
+}
+
+#[test_case(Extensions::empty(); "none")]
+#[test_case(Extensions::all(); "all")]
+fn sections_and_text_steps_without_extensions(extensions: Extensions) {
+    let src = indoc! {r#"
+        = S%#b
+        > A text step.
+
+        A step.
+    "#};
+    roundtrip(src, extensions);
+}
+
+#[test]
+fn metadata_first() {
+    let parser = CooklangParser::default();
+    let src = indoc! {r#"
+        A step.
+        >> key: value
+    "#};
+    let recipe = parser.parse(src, "test").into_output().unwrap();
+    let written = recipe.to_cooklang(parser.extensions());
+    assert_eq!(
+        written,
+        indoc! {r#"
+            >> key: value
+
+            A step.
+        "#}
+    );
+}
+
+#[test]
+fn components_block_layout() {
+    let parser = CooklangParser::default();
+    let src = indoc! {r#"
+        >> key: value
+        >> [mode]: components
+        @flour{200%g}
+        >> [mode]: all
+        Add the @&flour.
+    "#};
+    let recipe = parser.parse(src, "test").into_output().unwrap();
+    let written = recipe.to_cooklang(parser.extensions());
+    assert_eq!(
+        written,
+        indoc! {r#"
+            >> key: value
+
+            >> [mode]: components
+            @flour{200%g}
+            >> [mode]: all
+
+            Add the @&flour{}.
+        "#}
+    );
+}
+
+mod format {
+    use cooklang::{format, Converter, CooklangParser, Extensions};
+    use indoc::indoc;
+    use test_case::test_case;
+
+    fn fmt(input: &str) -> String {
+        format::format(input, Extensions::all())
+            .into_output()
+            .expect("input has errors")
+    }
+
+    #[test_case(
+        indoc! {r#"
+            Step one.
+            >> servings: 2
+            >>   source :  somewhere
+        "#},
+        indoc! {r#"
+            >> servings: 2
+            >> source: somewhere
+
+            Step one.
+        "#}; "metadata first"
+    )]
+    #[test_case(
+        indoc! {r#"
+            >> [mode]: components
+            @flour{200%g}
+            >> [mode]: all
+        "#},
+        indoc! {r#"
+            >> [mode]: components
+
+            @flour{200%g}
+
+            >> [mode]: all
+        "#}; "special metadata in place"
+    )]
+    #[test_case(
+        indoc! {r#"
+            == Dough ==
+            Mix.
+            ===Filling
+            Fill.
+            =
+        "#},
+        indoc! {r#"
+            = Dough
+            Mix.
+
+            = Filling
+            Fill.
+
+            =
+        "#}; "section fences"
+    )]
+    #[test_case(
+        indoc! {r#"
+            Add @flour{1 kg} and @water{ 2 % l }.
+            Wait ~{ 10 min } and ~rest{1/2 %h}.
+            Add @eggs{2|4} and @salt{1*%tsp}.
+        "#},
+        indoc! {r#"
+            Add @flour{1%kg} and @water{2%l}.
+            Wait ~{10%min} and ~rest{1/2%h}.
+            Add @eggs{2|4} and @salt{1*%tsp}.
+        "#}; "quantity syntax"
+    )]
+    #[test_case(
+        indoc! {r#"
+            Add @salt{}, @pepper{}pepper, @olive oil{} and @-&(1)dough{}(note).
+            Use a #pot{} and #pan{}. Add @egg{
+        "#},
+        indoc! {r#"
+            Add @salt, @pepper{}pepper, @olive oil{} and @-&(1)dough(note).
+            Use a #pot and #pan. Add @egg{
+        "#}; "redundant braces"
+    )]
+    #[test_case(
+        indoc! {r#"
+            First step
+              continues here.
+            Second step?
+
+
+            Third step.
+        "#},
+        indoc! {r#"
+            First step
+            continues here.
+            Second step?
+
+            Third step.
+        "#}; "multiline steps"
+    )]
+    #[test_case(
+        indoc! {r#"
+            -- comment about the step
+            Step. -- trailing comment
+
+            [- standalone -]
+            >> source: me [- hidden -]
+            Another @salt{1 [- pinch -] %g}.
+            ==  Section  == -- section comment
+        "#},
+        indoc! {r#"
+            [- standalone -]
+            >> source: me [- hidden -]
+
+            -- comment about the step
+            Step. -- trailing comment
+
+            Another @salt{1 [- pinch -] %g}.
+
+            = Section -- section comment
+        "#}; "comments"
+    )]
+    #[test_case(
+        indoc! {r#"
+            >text step
+            >   another one
+        "#},
+        indoc! {r#"
+            > text step
+            > another one
+        "#}; "text steps"
+    )]
+    #[test_case(
+        "  >> not: metadata\n  = not a section\n",
+        "  >> not: metadata\n\\= not a section\n"; "indented line kinds"
+    )]
+    fn format_cases(input: &str, expected: &str) {
+        let formatted = fmt(input);
+        assert_eq!(formatted, expected);
+        assert_eq!(fmt(&formatted), formatted, "not idempotent");
+    }
+
+    #[test]
+    fn comments_survive() {
+        let input = indoc! {r#"
+            -- one
+            >> a: b -- two
+            Step [- three -] with @salt{1 [- four -] %g}.
+            == Sect [- five -] ==
+            [- six -]
+        "#};
+        let formatted = fmt(input);
+        for c in [
+            "-- one",
+            "-- two",
+            "[- three -]",
+            "[- four -]",
+            "[- five -]",
+            "[- six -]",
+        ] {
+            assert!(formatted.contains(c), "{c} lost in:\n{formatted}");
+        }
+    }
+
+    #[test]
+    fn check() {
+        let ext = Extensions::all();
+        let check = |s| format::check(s, ext).into_output().unwrap();
+        assert!(check(">> a: b\n\nStep @salt.\n"));
+        assert!(!check(">> a: b\nStep @salt{}.\n"));
+        assert!(!check("Step.  \n"));
+        assert!(format::check("Step @salt{1|2*}\n", ext)
+            .into_output()
+            .is_none());
+    }
+
+    #[test_case("Add @salt{} {x}.\n", "Add @salt{} {x}.\n"; "brace later in the line")]
+    #[test_case("@a{} b{}\n", "@a{} b{}\n"; "brace after a word")]
+    #[test_case("@a{} b @c{}\n", "@a b @c\n"; "brace after another component")]
+    #[test_case("@a{} b\n{x}\n", "@a b\n{x}\n"; "brace in the next line")]
+    fn keeps_needed_braces(input: &str, expected: &str) {
+        let formatted = fmt(input);
+        assert_eq!(formatted, expected);
+        let parser = CooklangParser::default();
+        let a = parser.parse(input, "a").into_output().unwrap();
+        let b = parser.parse(&formatted, "a").into_output().unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test_case("   = not section?\n"; "section marker")]
+    #[test_case("  >> not: metadata\n"; "metadata marker")]
+    #[test_case("  > not a text step\n"; "text step marker")]
+    #[test_case("\tA step with @salt.\n"; "tab")]
+    #[test_case("A step\n   = that continues\n"; "next line")]
+    fn keeps_indented_steps(input: &str) {
+        let parser = CooklangParser::default();
+        let a = parser.parse(input, "a").into_output().unwrap();
+        let b = parser.parse(&fmt(input), "a").into_output().unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn same_recipe() {
+        let input = include_str!("../benches/test_recipe.cook");
+        for ext in [Extensions::all(), Extensions::COMPAT, Extensions::empty()] {
+            let parser = CooklangParser::new(ext, Converter::default());
+            let Some(formatted) = format::format(input, ext).into_output() else {
+                continue;
+            };
+            assert_eq!(
+                format::format(&formatted, ext).into_output().unwrap(),
+                formatted
+            );
+            let a = parser.parse(input, "a").into_output().unwrap();
+            let b = parser.parse(&formatted, "a").into_output().unwrap();
+            assert_eq!(a.metadata, b.metadata);
+            assert_eq!(a.ingredients, b.ingredients);
+            assert_eq!(a.cookware, b.cookware);
+            assert_eq!(a.timers, b.timers);
+            assert_eq!(a.sections.len(), b.sections.len());
+        }
+    }
+}